@@ -0,0 +1,647 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use syn::__private::ToTokens;
+
+use crate::modules::{flatten_use_tree, Module, ModuleTree};
+
+
+// markers of the signatures, `#[non_exhaustive] struct S { .. }` and
+// `fn f (& self) { .. }` for a trait method with a default
+const NON_EXHAUSTIVE: &str = "#[non_exhaustive] ";
+const PRIVATE_FIELDS: &str = " { .. }";
+const DEFAULT_BODY: &str = " { .. }";
+const DEFAULT_VALUE: &str = " = ..";
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiKind {
+    Mod,
+    Fn,
+    Struct,
+    Enum,
+    Union,
+    Trait,
+    TypeAlias,
+    Const,
+    Static,
+    Macro,
+    Field,
+    Variant,
+    Method,
+    TraitItem,
+    TraitImpl,
+    ReExport,
+}
+
+
+impl fmt::Display for ApiKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ApiKind::Mod => "mod",
+            ApiKind::Fn => "fn",
+            ApiKind::Struct => "struct",
+            ApiKind::Enum => "enum",
+            ApiKind::Union => "union",
+            ApiKind::Trait => "trait",
+            ApiKind::TypeAlias => "type",
+            ApiKind::Const => "const",
+            ApiKind::Static => "static",
+            ApiKind::Macro => "macro",
+            ApiKind::Field => "field",
+            ApiKind::Variant => "variant",
+            ApiKind::Method => "method",
+            ApiKind::TraitItem => "trait item",
+            ApiKind::TraitImpl => "impl",
+            ApiKind::ReExport => "re-export",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+/// A public item reachable from the crate root, under one of its public
+/// paths. Fields, variants, methods and trait items are items on their own
+/// below the path of their type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiItem {
+    pub path: String,
+    pub kind: ApiKind,
+    pub signature: String,
+    // adding the item breaks users, like a variant of an exhaustive enum
+    pub breaking_if_added: bool,
+}
+
+
+impl ApiItem {
+
+    pub fn new(path: String, kind: ApiKind, signature: String) -> Self {
+        ApiItem{path, kind, signature, breaking_if_added: false}
+    }
+
+    pub fn breaking_if_added(mut self, breaking: bool) -> Self {
+        self.breaking_if_added = breaking;
+        self
+    }
+
+    /// The path without the crate name, to compare crates across versions.
+    pub fn relative_path(&self) -> &str {
+        match self.path.find("::") {
+            Some(pos) => &self.path[pos + 2..],
+            None => ""
+        }
+    }
+}
+
+
+/// The public API of a crate.
+#[derive(Debug, Default)]
+pub struct Api {
+    pub items: Vec<ApiItem>,
+    // type definitions, by module and name, and their public paths
+    type_paths: BTreeMap<(String, String), Vec<String>>,
+    visited: BTreeSet<(String, String)>,
+}
+
+
+fn render<T: ToTokens>(node: &T) -> String {
+    node.to_token_stream().to_string()
+}
+
+
+fn header(keyword: &str, ident: &syn::Ident, generics: &syn::Generics) -> String {
+    let mut header = format!("{} {}{}", keyword, ident, render(generics));
+    if let Some(where_clause) = &generics.where_clause {
+        header = format!("{} {}", header, render(where_clause));
+    }
+    header
+}
+
+
+fn is_pub(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+
+fn is_non_exhaustive(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("non_exhaustive"))
+}
+
+
+/// Traits of the `#[derive(..)]` attributes.
+fn derived_traits(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs.iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .flat_map(|meta| match meta {
+            syn::Meta::List(list) => list.nested.into_iter().collect(),
+            _ => vec![]
+        })
+        .filter_map(|nested| match nested {
+            syn::NestedMeta::Meta(meta) => Some(render(meta.path()).replace(' ', "")),
+            _ => None
+        })
+        .collect()
+}
+
+
+/// Path of a trait impl below its type, named after the last segment of
+/// the trait so a derive and a hand-written impl are the same item.
+fn trait_impl_path(type_path: &str, trait_path: &str) -> String {
+    format!("{}::<impl {}>", type_path, trait_path.rsplit("::").next().unwrap())
+}
+
+
+fn is_macro_export(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("macro_export"))
+}
+
+
+/// Name an item declares in the type or value namespace.
+fn item_ident(item: &syn::Item) -> Option<&syn::Ident> {
+    match item {
+        syn::Item::Fn(item_fn) => Some(&item_fn.sig.ident),
+        syn::Item::Struct(item_struct) => Some(&item_struct.ident),
+        syn::Item::Enum(item_enum) => Some(&item_enum.ident),
+        syn::Item::Union(item_union) => Some(&item_union.ident),
+        syn::Item::Trait(item_trait) => Some(&item_trait.ident),
+        syn::Item::Type(item_type) => Some(&item_type.ident),
+        syn::Item::Const(item_const) => Some(&item_const.ident),
+        syn::Item::Static(item_static) => Some(&item_static.ident),
+        syn::Item::Mod(item_mod) => Some(&item_mod.ident),
+        _ => None
+    }
+}
+
+
+impl Api {
+
+    /// Walks the crate from its root through `pub mod` and `pub use`,
+    /// test modules are left out.
+    pub fn from_tree(tree: &ModuleTree, crate_name: &str) -> Self {
+        let mut api = Api::default();
+        if let Some(root) = tree.get(crate_name) {
+            api.export_module(tree, root, crate_name);
+            for module in tree.modules.iter().filter(|m| !m.is_test) {
+                for item in &module.items {
+                    match item {
+                        syn::Item::Impl(item_impl) => api.export_impl(module, item_impl),
+                        syn::Item::Macro(item_macro) if is_macro_export(&item_macro.attrs) => {
+                            if let Some(ident) = &item_macro.ident {
+                                api.items.push(ApiItem::new(
+                                    format!("{}::{}!", crate_name, ident), ApiKind::Macro,
+                                    format!("macro_rules! {}", ident),
+                                ));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        api.items.sort_by(|a, b| a.path.cmp(&b.path));
+        api.items.dedup_by(|a, b| a.path == b.path && a.kind == b.kind);
+        api
+    }
+
+    fn export_module(&mut self, tree: &ModuleTree, module: &Module, public_path: &str) {
+        if module.is_test || !self.visited.insert((module.name.clone(), public_path.to_owned())) {
+            return;
+        }
+        for item in &module.items {
+            match item {
+                syn::Item::Use(item_use) if is_pub(&item_use.vis) => {
+                    let mut uses = vec![];
+                    flatten_use_tree(&mut Vec::new(), &item_use.tree, &mut uses);
+                    for (path, alias) in uses {
+                        self.export_use(tree, module, &path, alias, public_path);
+                    }
+                }
+                syn::Item::Mod(item_mod) if is_pub(&item_mod.vis) => {
+                    let child_path = format!("{}::{}", public_path, item_mod.ident);
+                    self.items.push(ApiItem::new(
+                        child_path.clone(), ApiKind::Mod, format!("mod {}", item_mod.ident)
+                    ));
+                    if let Some(child) = tree.get(&format!("{}::{}", module.name, item_mod.ident)) {
+                        self.export_module(tree, child, &child_path);
+                    }
+                }
+                _ => self.export_item(module, item, None, public_path),
+            }
+        }
+    }
+
+    fn export_use(
+        &mut self, tree: &ModuleTree, module: &Module, path: &[String],
+        alias: Option<String>, public_path: &str
+    ) {
+        let target = tree.resolve_path(&module.name, path).and_then(|name| tree.get(&name));
+        let target = match target {
+            Some(target) => target,
+            None => {
+                // a re-export from another crate
+                let name = alias.unwrap_or_else(|| "*".to_owned());
+                self.items.push(ApiItem::new(
+                    format!("{}::{}", public_path, name), ApiKind::ReExport,
+                    format!("pub use {}", path.join("::")),
+                ));
+                return;
+            }
+        };
+        let alias = match alias {
+            Some(alias) => alias,
+            // `pub use module::*`
+            None => return self.export_module(tree, target, public_path),
+        };
+        let parent = match path.len() {
+            1 => Some(module.name.clone()),
+            n => tree.resolve_path(&module.name, &path[..n - 1]),
+        };
+        let name = path.last().unwrap();
+        if parent.map(|parent| target.name == format!("{}::{}", parent, name)).unwrap_or(false) {
+            // `pub use crate::module` re-exports the module
+            self.export_module(tree, target, &format!("{}::{}", public_path, alias));
+            return;
+        }
+        for item in &target.items {
+            match item {
+                syn::Item::Use(item_use) => {
+                    let mut uses = vec![];
+                    flatten_use_tree(&mut Vec::new(), &item_use.tree, &mut uses);
+                    for (inner_path, inner_alias) in uses {
+                        if inner_alias.as_ref() == Some(name) {
+                            self.export_use(tree, target, &inner_path, Some(alias.clone()), public_path);
+                        }
+                    }
+                }
+                _ if item_ident(item).map(|ident| ident == name).unwrap_or(false) => {
+                    self.export_item(target, item, Some(&alias), public_path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds a `pub` item, or any item when it is re-exported as `alias`.
+    fn export_item(&mut self, module: &Module, item: &syn::Item, alias: Option<&str>, public_path: &str) {
+        let ident = match item_ident(item) {
+            Some(ident) => ident,
+            None => return
+        };
+        let path = format!("{}::{}", public_path, alias.map(str::to_owned).unwrap_or_else(|| ident.to_string()));
+        let mut type_def = true;
+        match item {
+            syn::Item::Fn(item_fn) if alias.is_some() || is_pub(&item_fn.vis) => {
+                type_def = false;
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Fn, render(&item_fn.sig)));
+            }
+            syn::Item::Struct(item_struct) if alias.is_some() || is_pub(&item_struct.vis) => {
+                let non_exhaustive = is_non_exhaustive(&item_struct.attrs);
+                let private_fields = item_struct.fields.iter().any(|field| !is_pub(&field.vis));
+                let mut signature = header("struct", ident, &item_struct.generics);
+                if non_exhaustive {
+                    signature = format!("{}{}", NON_EXHAUSTIVE, signature);
+                }
+                if private_fields {
+                    signature = format!("{}{}", signature, PRIVATE_FIELDS);
+                }
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Struct, signature));
+                for (idx, field) in item_struct.fields.iter().enumerate() {
+                    if !is_pub(&field.vis) {
+                        continue;
+                    }
+                    let name = field.ident.as_ref().map(ToString::to_string).unwrap_or_else(|| idx.to_string());
+                    self.items.push(
+                        ApiItem::new(format!("{}::{}", path, name), ApiKind::Field, render(&field.ty))
+                            // struct literals need every field
+                            .breaking_if_added(!non_exhaustive && !private_fields)
+                    );
+                }
+            }
+            syn::Item::Union(item_union) if alias.is_some() || is_pub(&item_union.vis) => {
+                let signature = header("union", ident, &item_union.generics);
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Union, signature));
+                for field in item_union.fields.named.iter().filter(|field| is_pub(&field.vis)) {
+                    let name = field.ident.as_ref().unwrap();
+                    self.items.push(ApiItem::new(format!("{}::{}", path, name), ApiKind::Field, render(&field.ty)));
+                }
+            }
+            syn::Item::Enum(item_enum) if alias.is_some() || is_pub(&item_enum.vis) => {
+                let non_exhaustive = is_non_exhaustive(&item_enum.attrs);
+                let mut signature = header("enum", ident, &item_enum.generics);
+                if non_exhaustive {
+                    signature = format!("{}{}", NON_EXHAUSTIVE, signature);
+                }
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Enum, signature));
+                for variant in &item_enum.variants {
+                    self.items.push(
+                        ApiItem::new(format!("{}::{}", path, variant.ident), ApiKind::Variant, render(variant))
+                            // matches need every variant
+                            .breaking_if_added(!non_exhaustive)
+                    );
+                }
+            }
+            syn::Item::Trait(item_trait) if alias.is_some() || is_pub(&item_trait.vis) => {
+                let mut signature = header("trait", ident, &item_trait.generics);
+                if item_trait.unsafety.is_some() {
+                    signature = format!("unsafe {}", signature);
+                }
+                if !item_trait.supertraits.is_empty() {
+                    signature = format!("{}: {}", signature, render(&item_trait.supertraits));
+                }
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Trait, signature));
+                for trait_item in &item_trait.items {
+                    let (name, signature, required) = match trait_item {
+                        syn::TraitItem::Method(method) => {
+                            let mut signature = render(&method.sig);
+                            if method.default.is_some() {
+                                signature = format!("{}{}", signature, DEFAULT_BODY);
+                            }
+                            (&method.sig.ident, signature, method.default.is_none())
+                        }
+                        syn::TraitItem::Type(item_type) => {
+                            let mut signature = format!("type {}", item_type.ident);
+                            if !item_type.bounds.is_empty() {
+                                signature = format!("{}: {}", signature, render(&item_type.bounds));
+                            }
+                            if item_type.default.is_some() {
+                                signature = format!("{}{}", signature, DEFAULT_VALUE);
+                            }
+                            (&item_type.ident, signature, item_type.default.is_none())
+                        }
+                        syn::TraitItem::Const(item_const) => {
+                            let mut signature = format!("const {}: {}", item_const.ident, render(&item_const.ty));
+                            if item_const.default.is_some() {
+                                signature = format!("{}{}", signature, DEFAULT_VALUE);
+                            }
+                            (&item_const.ident, signature, item_const.default.is_none())
+                        }
+                        _ => continue
+                    };
+                    // implementations have to provide the required items
+                    self.items.push(
+                        ApiItem::new(format!("{}::{}", path, name), ApiKind::TraitItem, signature)
+                            .breaking_if_added(required)
+                    );
+                }
+            }
+            syn::Item::Type(item_type) if alias.is_some() || is_pub(&item_type.vis) => {
+                let signature = format!("{} = {}", header("type", ident, &item_type.generics), render(&item_type.ty));
+                self.items.push(ApiItem::new(path.clone(), ApiKind::TypeAlias, signature));
+            }
+            syn::Item::Const(item_const) if alias.is_some() || is_pub(&item_const.vis) => {
+                type_def = false;
+                let signature = format!("const {}: {}", ident, render(&item_const.ty));
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Const, signature));
+            }
+            syn::Item::Static(item_static) if alias.is_some() || is_pub(&item_static.vis) => {
+                type_def = false;
+                let mutability = if item_static.mutability.is_some() { "mut " } else { "" };
+                let signature = format!("static {}{}: {}", mutability, ident, render(&item_static.ty));
+                self.items.push(ApiItem::new(path.clone(), ApiKind::Static, signature));
+            }
+            _ => return
+        }
+        if type_def {
+            let attrs = match item {
+                syn::Item::Struct(item_struct) => &item_struct.attrs,
+                syn::Item::Enum(item_enum) => &item_enum.attrs,
+                syn::Item::Union(item_union) => &item_union.attrs,
+                _ => &[][..],
+            };
+            for trait_path in derived_traits(attrs) {
+                self.items.push(ApiItem::new(
+                    trait_impl_path(&path, &trait_path), ApiKind::TraitImpl,
+                    format!("#[derive({})]", trait_path),
+                ));
+            }
+            self.type_paths.entry((module.name.clone(), ident.to_string())).or_default().push(path);
+        }
+    }
+
+    /// Public paths of the type an impl block in `module` is for, the type
+    /// is looked up in the module first, then by its name in the crate.
+    fn impl_type_paths(&self, module: &Module, self_ty: &syn::Type) -> Vec<String> {
+        let name = match self_ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                path.segments.last().unwrap().ident.to_string()
+            }
+            _ => return vec![]
+        };
+        if let Some(paths) = self.type_paths.get(&(module.name.clone(), name.clone())) {
+            return paths.clone();
+        }
+        let mut candidates = self.type_paths.iter()
+            .filter(|((_, type_name), _)| *type_name == name);
+        match (candidates.next(), candidates.next()) {
+            (Some((_, paths)), None) => paths.clone(),
+            _ => vec![]
+        }
+    }
+
+    fn export_impl(&mut self, module: &Module, item_impl: &syn::ItemImpl) {
+        let paths = self.impl_type_paths(module, &item_impl.self_ty);
+        for path in paths {
+            match &item_impl.trait_ {
+                Some((negative, trait_path, _)) => {
+                    let negative = if negative.is_some() { "!" } else { "" };
+                    let trait_name = format!("{}{}", negative, render(trait_path).replace(' ', ""));
+                    self.items.push(ApiItem::new(
+                        trait_impl_path(&path, &trait_name), ApiKind::TraitImpl,
+                        format!("impl{} {}{} for {}",
+                            render(&item_impl.generics), negative, render(trait_path), render(&item_impl.self_ty)),
+                    ));
+                }
+                None => {
+                    for impl_item in &item_impl.items {
+                        match impl_item {
+                            syn::ImplItem::Method(method) if is_pub(&method.vis) => {
+                                self.items.push(ApiItem::new(
+                                    format!("{}::{}", path, method.sig.ident), ApiKind::Method,
+                                    render(&method.sig),
+                                ));
+                            }
+                            syn::ImplItem::Const(item_const) if is_pub(&item_const.vis) => {
+                                self.items.push(ApiItem::new(
+                                    format!("{}::{}", path, item_const.ident), ApiKind::Const,
+                                    format!("const {}: {}", item_const.ident, render(&item_const.ty)),
+                                ));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn count(&self, kind: ApiKind) -> usize {
+        self.items.iter().filter(|item| item.kind == kind).count()
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiChange {
+    Removed(ApiItem),
+    Added(ApiItem),
+    Changed(ApiItem, ApiItem),
+}
+
+
+impl ApiChange {
+
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            ApiChange::Removed(_) => true,
+            ApiChange::Added(item) => item.breaking_if_added,
+            ApiChange::Changed(old, new) => !is_relaxed(old, new),
+        }
+    }
+}
+
+
+/// Whether `new` only lifts restrictions of `old`: a type which is no
+/// longer `#[non_exhaustive]` or has no private fields left, or a trait
+/// item which got a default. A trait item losing its default is breaking.
+fn is_relaxed(old: &ApiItem, new: &ApiItem) -> bool {
+    match new.kind {
+        ApiKind::Struct | ApiKind::Enum => {
+            let unmarked = |signature: &str| {
+                signature.trim_start_matches(NON_EXHAUSTIVE).trim_end_matches(PRIVATE_FIELDS).to_owned()
+            };
+            let gained = |marker: &str| new.signature.contains(marker) && !old.signature.contains(marker);
+            unmarked(&old.signature) == unmarked(&new.signature)
+                && !gained(NON_EXHAUSTIVE) && !gained(PRIVATE_FIELDS)
+        }
+        ApiKind::TraitItem => {
+            old.breaking_if_added && !new.breaking_if_added
+                && new.signature.trim_end_matches(DEFAULT_BODY).trim_end_matches(DEFAULT_VALUE) == old.signature
+        }
+        _ => false
+    }
+}
+
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiChange::Removed(item) => write!(f, "removed {} {}: {}", item.kind, item.path, item.signature),
+            ApiChange::Added(item) => write!(f, "added {} {}: {}", item.kind, item.path, item.signature),
+            ApiChange::Changed(old, new) => {
+                write!(f, "changed {} {}: {} => {}", new.kind, new.path, old.signature, new.signature)
+            }
+        }
+    }
+}
+
+
+/// Changes from the `old` API to the `new` one, items are matched by
+/// their path without the crate name.
+pub fn diff_api(old: &Api, new: &Api) -> Vec<ApiChange> {
+    let key = |item: &ApiItem| (item.relative_path().to_owned(), item.kind.to_string());
+    let old_items: BTreeMap<_, &ApiItem> = old.items.iter().map(|item| (key(item), item)).collect();
+    let new_items: BTreeMap<_, &ApiItem> = new.items.iter().map(|item| (key(item), item)).collect();
+
+    let mut changes = vec![];
+    for (k, old_item) in &old_items {
+        match new_items.get(k) {
+            None => changes.push(ApiChange::Removed((*old_item).clone())),
+            Some(new_item) if new_item.signature != old_item.signature => {
+                changes.push(ApiChange::Changed((*old_item).clone(), (*new_item).clone()));
+            }
+            _ => {}
+        }
+    }
+    for (k, new_item) in &new_items {
+        if !old_items.contains_key(k) {
+            changes.push(ApiChange::Added((*new_item).clone()));
+        }
+    }
+    changes
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::modules::ModuleTree;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::{diff_api, Api, ApiChange, ApiItem, ApiKind};
+
+    #[test]
+    fn test_api_items() {
+        let dir = fixture_crate("api", &[
+            ("src/lib.rs", "pub mod shapes;\npub mod render;\nmod util;\n"),
+            ("src/shapes.rs", "pub struct Circle {\n    pub r: f64,\n}\n\nimpl Circle {\n    pub fn area(&self) -> f64 { self.r }\n    fn scale(&mut self) {}\n}\n\npub trait Shape {\n    fn area(&self) -> f64;\n    fn name(&self) -> &str { \"shape\" }\n}\n"),
+            ("src/render.rs", "pub mod canvas;\npub use canvas::*;\n"),
+            ("src/render/canvas.rs", "pub fn draw() {}\n\n#[cfg(test)]\nmod tests {\n    pub fn helper() {}\n}\n"),
+            ("src/util.rs", "pub struct Hidden;\n"),
+        ]);
+        let tree = ModuleTree::from_path(&dir, &Options::default());
+        let api = Api::from_tree(&tree, "fixture");
+        let find = |path: &str| api.items.iter().find(|item| item.path == path);
+
+        assert_eq!(find("fixture::shapes::Circle").unwrap().kind, ApiKind::Struct);
+        assert_eq!(find("fixture::shapes::Circle::r").unwrap().kind, ApiKind::Field);
+        assert_eq!(find("fixture::shapes::Circle::area").unwrap().kind, ApiKind::Method);
+        assert!(find("fixture::shapes::Circle::scale").is_none());
+        // a required trait method and one with a default
+        let area = find("fixture::shapes::Shape::area").unwrap();
+        assert!(area.breaking_if_added && !area.signature.ends_with("{ .. }"));
+        let name = find("fixture::shapes::Shape::name").unwrap();
+        assert!(!name.breaking_if_added && name.signature.ends_with("{ .. }"));
+        // re-exported through `pub use canvas::*`
+        assert!(find("fixture::render::draw").is_some());
+        assert!(find("fixture::render::canvas::draw").is_some());
+        assert!(find("fixture::util::Hidden").is_none());
+        assert!(api.items.iter().all(|item| !item.path.contains("::tests::")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_api_diff() {
+        let item = |path: &str, kind, signature: &str| ApiItem::new(path.to_owned(), kind, signature.to_owned());
+        let old = Api{items: vec![
+            item("a::f", ApiKind::Fn, "fn f (x : u32)"),
+            item("a::g", ApiKind::Fn, "fn g ()"),
+            item("a::E", ApiKind::Enum, "enum E"),
+        ], ..Api::default()};
+        let new = Api{items: vec![
+            item("b::f", ApiKind::Fn, "fn f (x : u64)"),
+            item("b::E", ApiKind::Enum, "enum E"),
+            item("b::E::V", ApiKind::Variant, "V").breaking_if_added(true),
+            item("b::h", ApiKind::Fn, "fn h ()"),
+        ], ..Api::default()};
+
+        let changes = diff_api(&old, &new);
+        assert_eq!(changes.len(), 4);
+        assert!(matches!(&changes[0], ApiChange::Changed(_, new) if new.path == "b::f"));
+        assert!(matches!(&changes[1], ApiChange::Removed(old) if old.path == "a::g"));
+        assert_eq!(changes.iter().filter(|c| c.is_breaking()).count(), 3);
+    }
+
+    #[test]
+    fn test_changed_is_breaking() {
+        let item = |kind, signature: &str, required| {
+            ApiItem::new("a::T".to_owned(), kind, signature.to_owned()).breaking_if_added(required)
+        };
+        let changed = |old: ApiItem, new: ApiItem| ApiChange::Changed(old, new).is_breaking();
+        let required = item(ApiKind::TraitItem, "fn f (& self)", true);
+        let provided = item(ApiKind::TraitItem, "fn f (& self) { .. }", false);
+        assert!(changed(provided.clone(), required.clone()));
+        assert!(!changed(required, provided));
+        assert!(changed(
+            item(ApiKind::TraitItem, "type Item = ..", false),
+            item(ApiKind::TraitItem, "type Item: Clone = ..", false),
+        ));
+
+        let exhaustive = item(ApiKind::Struct, "struct S", false);
+        let restricted = item(ApiKind::Struct, "#[non_exhaustive] struct S { .. }", false);
+        assert!(!changed(restricted.clone(), exhaustive.clone()));
+        assert!(changed(exhaustive, restricted));
+        assert!(changed(item(ApiKind::Struct, "struct S", false), item(ApiKind::Struct, "struct S < T >", false)));
+        assert!(changed(item(ApiKind::Fn, "fn f ()", false), item(ApiKind::Fn, "fn f () -> u32", false)));
+    }
+}
@@ -0,0 +1,189 @@
+use clap::{Arg, App, AppSettings, ArgMatches};
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+use happ::cargo::{CargoMetadata, Package, PackageSelection};
+
+
+// subcommands which analyze a crate as a whole, they run once on the
+// package directory instead of on each source root
+const CRATE_SUBCOMMANDS: [&str; 6] = ["deps", "coupling", "dead", "api", "docs", "tests"];
+
+// subcommands which do not take one input path, or never return
+const UNSUPPORTED_SUBCOMMANDS: [&str; 3] = ["watch", "api-diff", "cache"];
+
+
+fn main() {
+    // cargo runs `cargo happ cc ..` as `cargo-happ happ cc ..`
+    let mut args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|arg| arg == "happ").unwrap_or(false) {
+        args.remove(1);
+    }
+
+    let matches: ArgMatches = App::new("cargo-happ")
+        .bin_name("cargo happ")
+        .version("0.1")
+        .about("Runs a happ subcommand on the source roots of the packages of a cargo workspace")
+        .setting(AppSettings::TrailingVarArg)
+        .arg(
+            Arg::with_name("manifest-path")
+            .long("manifest-path")
+            .takes_value(true)
+            .help("Path to the Cargo.toml of the workspace or package")
+        )
+        .arg(
+            Arg::with_name("metadata")
+            .long("metadata")
+            .takes_value(true)
+            .conflicts_with("manifest-path")
+            .help("Reads the output of `cargo metadata --format-version 1` from this file instead of running cargo")
+        )
+        .arg(
+            Arg::with_name("package")
+            .short("p")
+            .long("package")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Package to analyze, the root package by default")
+        )
+        .arg(
+            Arg::with_name("workspace")
+            .long("workspace")
+            .conflicts_with("package")
+            .help("Analyzes every member of the workspace")
+        )
+        .arg(
+            Arg::with_name("exclude")
+            .long("exclude")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .requires("workspace")
+            .help("Package to leave out of --workspace")
+        )
+        .arg(
+            Arg::with_name("subcommand")
+            .required(true)
+            .multiple(true)
+            .help("The happ subcommand and its options, like `cc --max-nesting 4`, without the input path")
+        )
+        .get_matches_from(args);
+
+    let metadata = match matches.value_of("metadata") {
+        Some(json_path) => CargoMetadata::from_file(Path::new(json_path)),
+        None => CargoMetadata::from_manifest(matches.value_of("manifest-path").map(Path::new)),
+    };
+    let metadata = metadata.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        exit(1);
+    });
+
+    let selection = PackageSelection{
+        packages: matches.values_of("package").into_iter().flatten().map(String::from).collect(),
+        workspace: matches.is_present("workspace"),
+        exclude: matches.values_of("exclude").into_iter().flatten().map(String::from).collect(),
+    };
+    let packages = metadata.select(&selection).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        exit(1);
+    });
+
+    let subcommand: Vec<&str> = matches.values_of("subcommand").unwrap().collect();
+    let mut runs = vec![];
+    for package in packages {
+        let package_runs = happ_runs(&subcommand, package).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        });
+        runs.extend(package_runs.into_iter().map(|args| (package, args)));
+    }
+    let mut failed = false;
+    for (package, args) in runs {
+        println!("######## Package {} {}: {} ########", package.name, package.version, args.last().unwrap());
+        let status = Command::new(happ_binary())
+            .args(&args)
+            .status()
+            .unwrap_or_else(|e| {
+                eprintln!("error: cannot run happ: {}", e);
+                exit(1);
+            });
+        failed |= !status.success();
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+
+/// The arguments of the happ runs on a package, the subcommand followed by
+/// the package directory for the crate-level subcommands, or by each of its
+/// source roots for the others.
+fn happ_runs(subcommand: &[&str], package: &Package) -> Result<Vec<Vec<String>>, String> {
+    let name = subcommand[0];
+    if UNSUPPORTED_SUBCOMMANDS.contains(&name) {
+        return Err(format!("`{}` cannot run on the packages, run `happ {}` on a path instead", name, name));
+    }
+    let inputs = match CRATE_SUBCOMMANDS.contains(&name) {
+        true => vec![package.dir().to_owned()],
+        false => package.source_roots(),
+    };
+    let runs = inputs.into_iter()
+        .map(|input| {
+            let mut args: Vec<String> = subcommand.iter().map(|arg| arg.to_string()).collect();
+            args.push(input.to_string_lossy().into_owned());
+            args
+        })
+        .collect();
+    Ok(runs)
+}
+
+
+/// The `happ` installed along with `cargo-happ`, or the one on the `PATH`.
+fn happ_binary() -> PathBuf {
+    let sibling = std::env::current_exe()
+        .map(|exe| exe.with_file_name(format!("happ{}", std::env::consts::EXE_SUFFIX)));
+    match sibling {
+        Ok(path) if path.is_file() => path,
+        _ => PathBuf::from("happ"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use happ::cargo::CargoMetadata;
+    use super::happ_runs;
+
+    const METADATA: &str = r#"{
+        "packages": [{
+            "id": "app 0.1.0 (path+file:///ws)", "name": "app", "version": "0.1.0",
+            "manifest_path": "/ws/Cargo.toml",
+            "targets": [
+                {"name": "app", "kind": ["lib"], "src_path": "/ws/src/lib.rs"},
+                {"name": "it", "kind": ["test"], "src_path": "/ws/tests/it.rs"},
+                {"name": "demo", "kind": ["example"], "src_path": "/ws/examples/demo.rs"}
+            ]
+        }],
+        "workspace_members": ["app 0.1.0 (path+file:///ws)"],
+        "workspace_root": "/ws"
+    }"#;
+
+    #[test]
+    fn test_happ_runs() {
+        let metadata = CargoMetadata::from_json(METADATA).unwrap();
+        let app = metadata.package("app").unwrap();
+
+        assert_eq!(happ_runs(&["cc", "--max-nesting", "4"], app).unwrap(), vec![
+            vec!["cc", "--max-nesting", "4", "/ws/examples"],
+            vec!["cc", "--max-nesting", "4", "/ws/src"],
+            vec!["cc", "--max-nesting", "4", "/ws/tests"],
+        ]);
+        // once on the package, its integration tests are found from there
+        assert_eq!(happ_runs(&["tests"], app).unwrap(), vec![vec!["tests", "/ws"]]);
+        assert_eq!(happ_runs(&["deps", "--format", "dot"], app).unwrap(), vec![vec!["deps", "--format", "dot", "/ws"]]);
+        for name in ["watch", "api-diff", "cache"] {
+            assert!(happ_runs(&[name], app).is_err());
+        }
+    }
+}
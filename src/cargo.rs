@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+
+
+/// A target of a package, as listed by `cargo metadata`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub name: String,
+    pub kind: Vec<String>,  // "lib", "bin", "test", "bench", "example", "custom-build", ..
+    pub src_path: PathBuf,
+}
+
+
+impl Target {
+
+    pub fn is_build_script(&self) -> bool {
+        self.kind.iter().any(|kind| kind == "custom-build")
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<Target>,
+    pub features: BTreeMap<String, Vec<String>>,  // the `[features]` table
+}
+
+
+impl Package {
+
+    pub fn dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap()
+    }
+
+    /// The directories of the sources of the targets, like `src` and
+    /// `tests`, without the ones under another root. The build script is
+    /// left out, its directory is the whole package.
+    pub fn source_roots(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self.targets.iter()
+            .filter(|target| !target.is_build_script())
+            .filter_map(|target| target.src_path.parent().map(Path::to_path_buf))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs.iter()
+            .filter(|dir| !dirs.iter().any(|other| other != *dir && dir.starts_with(other)))
+            .cloned()
+            .collect()
+    }
+}
+
+
+/// Which packages `cargo happ` runs on, given as cargo takes them: the
+/// `--package` ones, or every member with `--workspace` but the
+/// `--exclude` ones, or the root package by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageSelection {
+    pub packages: Vec<String>,
+    pub workspace: bool,
+    pub exclude: Vec<String>,
+}
+
+
+/// The members of a workspace, from the output of
+/// `cargo metadata --format-version 1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CargoMetadata {
+    pub workspace_root: PathBuf,
+    pub packages: Vec<Package>,
+}
+
+
+fn json_str(value: &Value, key: &str) -> Result<String, String> {
+    value[key].as_str()
+        .map(String::from)
+        .ok_or_else(|| format!("cargo metadata: missing `{}`", key))
+}
+
+
+fn parse_target(value: &Value) -> Result<Target, String> {
+    let kind = value["kind"].as_array()
+        .map(|kinds| kinds.iter().filter_map(Value::as_str).map(String::from).collect())
+        .unwrap_or_default();
+    Ok(Target{
+        name: json_str(value, "name")?,
+        kind,
+        src_path: PathBuf::from(json_str(value, "src_path")?),
+    })
+}
+
+
+fn parse_package(value: &Value) -> Result<Package, String> {
+    let targets = value["targets"].as_array()
+        .map(|targets| targets.iter().map(parse_target).collect())
+        .unwrap_or_else(|| Ok(vec![]))?;
+    let features = value["features"].as_object()
+        .map(|table| table.iter()
+            .map(|(name, enabled)| {
+                let enabled = enabled.as_array().into_iter().flatten()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect();
+                (name.clone(), enabled)
+            })
+            .collect())
+        .unwrap_or_default();
+    Ok(Package{
+        id: json_str(value, "id")?,
+        name: json_str(value, "name")?,
+        version: json_str(value, "version")?,
+        manifest_path: PathBuf::from(json_str(value, "manifest_path")?),
+        targets,
+        features,
+    })
+}
+
+
+impl CargoMetadata {
+
+    /// Reads the workspace members out of `cargo metadata` JSON, with or
+    /// without `--no-deps`.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|e| format!("cargo metadata: {}", e))?;
+        let members: Vec<&str> = value["workspace_members"].as_array()
+            .ok_or("cargo metadata: missing `workspace_members`")?
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        let packages = value["packages"].as_array()
+            .ok_or("cargo metadata: missing `packages`")?
+            .iter()
+            .filter(|package| package["id"].as_str().map(|id| members.contains(&id)).unwrap_or(false))
+            .map(parse_package)
+            .collect::<Result<Vec<Package>, String>>()?;
+        Ok(CargoMetadata{
+            workspace_root: PathBuf::from(json_str(&value, "workspace_root")?),
+            packages,
+        })
+    }
+
+    /// Reads a JSON file saved from `cargo metadata`.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        CargoMetadata::from_json(&json)
+    }
+
+    /// Runs `cargo metadata` on the manifest, or on the one of the current
+    /// directory.
+    pub fn from_manifest(manifest_path: Option<&Path>) -> Result<Self, String> {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+        let mut command = Command::new(cargo);
+        command.args(["metadata", "--format-version", "1", "--no-deps"]);
+        if let Some(manifest_path) = manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+        let output = command.output().map_err(|e| format!("cargo metadata: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+        }
+        CargoMetadata::from_json(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// The package of the workspace root manifest, none for a virtual
+    /// manifest.
+    pub fn root_package(&self) -> Option<&Package> {
+        let manifest = self.workspace_root.join("Cargo.toml");
+        self.packages.iter().find(|package| package.manifest_path == manifest)
+    }
+
+    pub fn select(&self, selection: &PackageSelection) -> Result<Vec<&Package>, String> {
+        if !selection.exclude.is_empty() && !selection.workspace {
+            return Err("--exclude can only be used together with --workspace".to_owned());
+        }
+        for name in selection.packages.iter().chain(&selection.exclude) {
+            if self.package(name).is_none() {
+                return Err(format!("package `{}` not found in workspace", name));
+            }
+        }
+        let packages = if selection.workspace {
+            self.packages.iter()
+                .filter(|package| !selection.exclude.contains(&package.name))
+                .collect()
+        } else if !selection.packages.is_empty() {
+            self.packages.iter()
+                .filter(|package| selection.packages.contains(&package.name))
+                .collect()
+        } else {
+            match self.root_package() {
+                Some(package) => vec![package],
+                None => self.packages.iter().collect(),
+            }
+        };
+        Ok(packages)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+    use super::{CargoMetadata, PackageSelection};
+
+    const METADATA: &str = r#"{
+        "packages": [
+            {
+                "id": "app 0.1.0 (path+file:///ws)", "name": "app", "version": "0.1.0",
+                "manifest_path": "/ws/Cargo.toml",
+                "targets": [
+                    {"name": "app", "kind": ["bin"], "src_path": "/ws/src/main.rs"},
+                    {"name": "cli", "kind": ["bin"], "src_path": "/ws/src/bin/cli.rs"},
+                    {"name": "it", "kind": ["test"], "src_path": "/ws/tests/it.rs"},
+                    {"name": "build-script-build", "kind": ["custom-build"], "src_path": "/ws/build.rs"}
+                ]
+            },
+            {
+                "id": "core 0.1.0 (path+file:///ws/core)", "name": "core", "version": "0.1.0",
+                "manifest_path": "/ws/core/Cargo.toml",
+                "targets": [{"name": "core", "kind": ["lib"], "src_path": "/ws/core/src/lib.rs"}],
+                "features": {"default": ["json"], "json": ["dep:serde_json", "serde/std"]}
+            },
+            {
+                "id": "walkdir 2.3.2 (registry+https://github.com/rust-lang/crates.io-index)",
+                "name": "walkdir", "version": "2.3.2",
+                "manifest_path": "/registry/walkdir-2.3.2/Cargo.toml", "targets": []
+            }
+        ],
+        "workspace_members": ["app 0.1.0 (path+file:///ws)", "core 0.1.0 (path+file:///ws/core)"],
+        "workspace_root": "/ws"
+    }"#;
+
+    #[test]
+    fn test_metadata() {
+        let metadata = CargoMetadata::from_json(METADATA).unwrap();
+        assert_eq!(metadata.packages.len(), 2);
+
+        let app = metadata.root_package().unwrap();
+        assert_eq!(app.name, "app");
+        assert_eq!(app.source_roots(), vec![PathBuf::from("/ws/src"), PathBuf::from("/ws/tests")]);
+        assert!(app.features.is_empty());
+        let core = metadata.package("core").unwrap();
+        assert_eq!(core.features["default"], vec!["json"]);
+        assert_eq!(core.features["json"], vec!["dep:serde_json", "serde/std"]);
+
+        let names = |selection: PackageSelection| -> Vec<String> {
+            metadata.select(&selection).unwrap().iter().map(|p| p.name.clone()).collect()
+        };
+        assert_eq!(names(PackageSelection::default()), vec!["app"]);
+        assert_eq!(names(PackageSelection{packages: vec!["core".to_owned()], ..Default::default()}), vec!["core"]);
+        assert_eq!(
+            names(PackageSelection{workspace: true, exclude: vec!["app".to_owned()], ..Default::default()}),
+            vec!["core"]
+        );
+        assert!(metadata.select(&PackageSelection{packages: vec!["walkdir".to_owned()], ..Default::default()}).is_err());
+        assert!(metadata.select(&PackageSelection{exclude: vec!["app".to_owned()], ..Default::default()}).is_err());
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::{BTreeMap, BTreeSet};
+use syn::__private::ToTokens;
+use syn::visit_mut::{self, VisitMut};
+
+
+/// One build configuration to evaluate `#[cfg(..)]` and `#[cfg_attr(..)]`
+/// predicates against. Predicates it knows nothing about, like `unix`
+/// without a target OS, are neither true nor false and keep their item.
+/// The analyses take it in `Options::cfg`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CfgConfig {
+    pub features: BTreeSet<String>,
+    pub all_features: bool,
+    pub cfgs: BTreeSet<String>,  // `--cfg name`
+    pub key_values: BTreeSet<(String, String)>,  // `--cfg key="value"`
+    pub target_os: Option<String>,
+}
+
+
+fn target_family(target_os: &str) -> &'static str {
+    match target_os {
+        "windows" => "windows",
+        "unknown" | "none" => "",
+        _ => "unix",
+    }
+}
+
+
+impl CfgConfig {
+
+    /// Adds a `--cfg` option, `name` or `key="value"`.
+    pub fn add_cfg(&mut self, cfg: &str) {
+        match cfg.split_once('=') {
+            Some((key, value)) => {
+                self.key_values.insert((key.trim().to_owned(), value.trim().trim_matches('"').to_owned()));
+            }
+            None => {
+                self.cfgs.insert(cfg.trim().to_owned());
+            }
+        }
+    }
+
+    /// Enables a feature and, through the `[features]` table of the
+    /// manifest, the features it enables in turn.
+    pub fn enable_feature(&mut self, name: &str, table: &BTreeMap<String, Vec<String>>) {
+        // `dep:name` and `name/feature` enable the optional dependency `name`
+        let name = name.trim_start_matches("dep:").split('/').next().unwrap();
+        if name.ends_with('?') || !self.features.insert(name.to_owned()) {
+            return
+        }
+        for enabled in table.get(name).into_iter().flatten() {
+            self.enable_feature(enabled, table);
+        }
+    }
+
+    fn eval_name(&self, name: &str) -> Option<bool> {
+        if self.cfgs.contains(name) {
+            return Some(true)
+        }
+        match (name, &self.target_os) {
+            ("test", _) | ("doctest", _) | ("miri", _) => Some(false),
+            ("unix", Some(os)) | ("windows", Some(os)) => Some(target_family(os) == name),
+            _ => None
+        }
+    }
+
+    fn eval_key_value(&self, key: &str, value: &str) -> Option<bool> {
+        if self.key_values.contains(&(key.to_owned(), value.to_owned())) {
+            return Some(true)
+        }
+        match (key, &self.target_os) {
+            ("feature", _) => Some(self.all_features || self.features.contains(value)),
+            ("target_os", Some(os)) => Some(os == value),
+            ("target_family", Some(os)) => Some(target_family(os) == value),
+            _ if self.key_values.iter().any(|(k, _)| k == key) => Some(false),
+            _ => None
+        }
+    }
+
+    /// Evaluates a predicate, `None` when it depends on an unknown option.
+    pub fn eval(&self, predicate: &syn::NestedMeta) -> Option<bool> {
+        let meta = match predicate {
+            syn::NestedMeta::Meta(meta) => meta,
+            syn::NestedMeta::Lit(_) => return None,
+        };
+        match meta {
+            syn::Meta::Path(path) => self.eval_name(&path.get_ident()?.to_string()),
+            syn::Meta::NameValue(name_value) => match &name_value.lit {
+                syn::Lit::Str(value) => self.eval_key_value(&name_value.path.get_ident()?.to_string(), &value.value()),
+                _ => None
+            },
+            syn::Meta::List(list) => {
+                let results: Vec<Option<bool>> = list.nested.iter().map(|p| self.eval(p)).collect();
+                match list.path.get_ident()?.to_string().as_str() {
+                    "all" if results.contains(&Some(false)) => Some(false),
+                    "all" if results.iter().all(|r| *r == Some(true)) => Some(true),
+                    "any" if results.contains(&Some(true)) => Some(true),
+                    "any" if results.iter().all(|r| *r == Some(false)) => Some(false),
+                    "not" if results.len() == 1 => results[0].map(|r| !r),
+                    _ => None
+                }
+            }
+        }
+    }
+
+    /// Expands the `cfg_attr`s whose predicate holds and drops the ones
+    /// whose predicate does not, then tells whether the `cfg`s keep the
+    /// item.
+    pub fn process_attrs(&self, attrs: &mut Vec<syn::Attribute>) -> bool {
+        let mut processed = Vec::with_capacity(attrs.len());
+        for attr in attrs.drain(..) {
+            if !attr.path.is_ident("cfg_attr") {
+                processed.push(attr);
+                continue;
+            }
+            let list = match attr.parse_meta() {
+                Ok(syn::Meta::List(list)) if !list.nested.is_empty() => list,
+                _ => {
+                    processed.push(attr);
+                    continue;
+                }
+            };
+            let mut nested = list.nested.iter();
+            match self.eval(nested.next().unwrap()) {
+                Some(true) => {
+                    for meta in nested {
+                        let mut expanded = attr.clone();
+                        match meta {
+                            syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                                expanded.path = path.clone();
+                                expanded.tokens = Default::default();
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::List(list)) => {
+                                expanded.path = list.path.clone();
+                                expanded.tokens = proc_macro2::TokenTree::Group(proc_macro2::Group::new(
+                                    proc_macro2::Delimiter::Parenthesis, list.nested.to_token_stream(),
+                                )).into();
+                            }
+                            // `path = "imp.rs"` or `doc = ".."`
+                            syn::NestedMeta::Meta(syn::Meta::NameValue(name_value)) => {
+                                expanded.path = name_value.path.clone();
+                                expanded.tokens = Default::default();
+                                name_value.eq_token.to_tokens(&mut expanded.tokens);
+                                name_value.lit.to_tokens(&mut expanded.tokens);
+                            }
+                            syn::NestedMeta::Lit(_) => continue
+                        }
+                        processed.push(expanded);
+                    }
+                }
+                Some(false) => {}
+                None => processed.push(attr),
+            }
+        }
+        *attrs = processed;
+
+        attrs.iter()
+            .filter(|attr| attr.path.is_ident("cfg"))
+            .filter_map(|attr| attr.parse_meta().ok())
+            .all(|meta| match meta {
+                syn::Meta::List(list) if list.nested.len() == 1 => self.eval(&list.nested[0]) != Some(false),
+                _ => true
+            })
+    }
+
+    /// Removes the items, statements, fields, variants and match arms
+    /// configured out.
+    pub fn prune_file(&self, file: &mut syn::File) {
+        let mut pruner = CfgPruner{config: self};
+        pruner.visit_file_mut(file);
+    }
+}
+
+
+fn item_attrs(item: &mut syn::Item) -> Option<&mut Vec<syn::Attribute>> {
+    match item {
+        syn::Item::Const(i) => Some(&mut i.attrs),
+        syn::Item::Enum(i) => Some(&mut i.attrs),
+        syn::Item::ExternCrate(i) => Some(&mut i.attrs),
+        syn::Item::Fn(i) => Some(&mut i.attrs),
+        syn::Item::ForeignMod(i) => Some(&mut i.attrs),
+        syn::Item::Impl(i) => Some(&mut i.attrs),
+        syn::Item::Macro(i) => Some(&mut i.attrs),
+        syn::Item::Macro2(i) => Some(&mut i.attrs),
+        syn::Item::Mod(i) => Some(&mut i.attrs),
+        syn::Item::Static(i) => Some(&mut i.attrs),
+        syn::Item::Struct(i) => Some(&mut i.attrs),
+        syn::Item::Trait(i) => Some(&mut i.attrs),
+        syn::Item::TraitAlias(i) => Some(&mut i.attrs),
+        syn::Item::Type(i) => Some(&mut i.attrs),
+        syn::Item::Union(i) => Some(&mut i.attrs),
+        syn::Item::Use(i) => Some(&mut i.attrs),
+        _ => None
+    }
+}
+
+
+fn expr_attrs(expr: &mut syn::Expr) -> Option<&mut Vec<syn::Attribute>> {
+    match expr {
+        syn::Expr::Assign(e) => Some(&mut e.attrs),
+        syn::Expr::AssignOp(e) => Some(&mut e.attrs),
+        syn::Expr::Block(e) => Some(&mut e.attrs),
+        syn::Expr::Call(e) => Some(&mut e.attrs),
+        syn::Expr::ForLoop(e) => Some(&mut e.attrs),
+        syn::Expr::If(e) => Some(&mut e.attrs),
+        syn::Expr::Loop(e) => Some(&mut e.attrs),
+        syn::Expr::Macro(e) => Some(&mut e.attrs),
+        syn::Expr::Match(e) => Some(&mut e.attrs),
+        syn::Expr::MethodCall(e) => Some(&mut e.attrs),
+        syn::Expr::Return(e) => Some(&mut e.attrs),
+        syn::Expr::Unsafe(e) => Some(&mut e.attrs),
+        syn::Expr::While(e) => Some(&mut e.attrs),
+        _ => None
+    }
+}
+
+
+struct CfgPruner<'a> {
+    config: &'a CfgConfig,
+}
+
+
+impl<'a> CfgPruner<'a> {
+
+    fn keep_item(&self, item: &mut syn::Item) -> bool {
+        item_attrs(item).map(|attrs| self.config.process_attrs(attrs)).unwrap_or(true)
+    }
+
+    fn keep_stmt(&self, stmt: &mut syn::Stmt) -> bool {
+        match stmt {
+            syn::Stmt::Local(local) => self.config.process_attrs(&mut local.attrs),
+            syn::Stmt::Item(item) => self.keep_item(item),
+            syn::Stmt::Expr(expr) | syn::Stmt::Semi(expr, _) => {
+                expr_attrs(expr).map(|attrs| self.config.process_attrs(attrs)).unwrap_or(true)
+            }
+        }
+    }
+
+    fn prune_fields(&self, fields: &mut syn::punctuated::Punctuated<syn::Field, syn::Token![,]>) {
+        *fields = std::mem::take(fields).into_iter()
+            .filter_map(|mut field| match self.config.process_attrs(&mut field.attrs) {
+                true => Some(field),
+                false => None
+            })
+            .collect();
+    }
+}
+
+
+impl<'a> VisitMut for CfgPruner<'a> {
+
+    fn visit_file_mut(&mut self, i: &mut syn::File) {
+        if !self.config.process_attrs(&mut i.attrs) {
+            i.items.clear();
+        }
+        i.items.retain_mut(|item| self.keep_item(item));
+        visit_mut::visit_file_mut(self, i);
+    }
+
+    fn visit_item_mod_mut(&mut self, i: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut i.content {
+            items.retain_mut(|item| self.keep_item(item));
+        }
+        visit_mut::visit_item_mod_mut(self, i);
+    }
+
+    fn visit_item_impl_mut(&mut self, i: &mut syn::ItemImpl) {
+        i.items.retain_mut(|impl_item| match impl_item {
+            syn::ImplItem::Const(c) => self.config.process_attrs(&mut c.attrs),
+            syn::ImplItem::Method(m) => self.config.process_attrs(&mut m.attrs),
+            syn::ImplItem::Type(t) => self.config.process_attrs(&mut t.attrs),
+            syn::ImplItem::Macro(m) => self.config.process_attrs(&mut m.attrs),
+            _ => true
+        });
+        visit_mut::visit_item_impl_mut(self, i);
+    }
+
+    fn visit_item_trait_mut(&mut self, i: &mut syn::ItemTrait) {
+        i.items.retain_mut(|trait_item| match trait_item {
+            syn::TraitItem::Const(c) => self.config.process_attrs(&mut c.attrs),
+            syn::TraitItem::Method(m) => self.config.process_attrs(&mut m.attrs),
+            syn::TraitItem::Type(t) => self.config.process_attrs(&mut t.attrs),
+            syn::TraitItem::Macro(m) => self.config.process_attrs(&mut m.attrs),
+            _ => true
+        });
+        visit_mut::visit_item_trait_mut(self, i);
+    }
+
+    fn visit_item_enum_mut(&mut self, i: &mut syn::ItemEnum) {
+        i.variants = std::mem::take(&mut i.variants).into_iter()
+            .filter_map(|mut variant| match self.config.process_attrs(&mut variant.attrs) {
+                true => Some(variant),
+                false => None
+            })
+            .collect();
+        visit_mut::visit_item_enum_mut(self, i);
+    }
+
+    fn visit_fields_named_mut(&mut self, i: &mut syn::FieldsNamed) {
+        self.prune_fields(&mut i.named);
+        visit_mut::visit_fields_named_mut(self, i);
+    }
+
+    fn visit_fields_unnamed_mut(&mut self, i: &mut syn::FieldsUnnamed) {
+        self.prune_fields(&mut i.unnamed);
+        visit_mut::visit_fields_unnamed_mut(self, i);
+    }
+
+    fn visit_block_mut(&mut self, i: &mut syn::Block) {
+        i.stmts.retain_mut(|stmt| self.keep_stmt(stmt));
+        visit_mut::visit_block_mut(self, i);
+    }
+
+    fn visit_expr_match_mut(&mut self, i: &mut syn::ExprMatch) {
+        i.arms.retain_mut(|arm| self.config.process_attrs(&mut arm.attrs));
+        visit_mut::visit_expr_match_mut(self, i);
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::CfgConfig;
+
+    #[test]
+    fn test_eval() {
+        let mut config = CfgConfig::default();
+        config.features.insert("json".to_owned());
+        config.target_os = Some("linux".to_owned());
+
+        let eval = |config: &CfgConfig, predicate: syn::NestedMeta| config.eval(&predicate);
+        assert_eq!(eval(&config, parse_quote!(feature = "json")), Some(true));
+        assert_eq!(eval(&config, parse_quote!(feature = "yaml")), Some(false));
+        assert_eq!(eval(&config, parse_quote!(all(unix, not(test)))), Some(true));
+        assert_eq!(eval(&config, parse_quote!(any(windows, target_os = "macos"))), Some(false));
+        assert_eq!(eval(&config, parse_quote!(debug_assertions)), None);
+        assert_eq!(eval(&config, parse_quote!(any(debug_assertions, feature = "json"))), Some(true));
+    }
+
+    #[test]
+    fn test_prune_file() {
+        let mut config = CfgConfig::default();
+        config.add_cfg("feature=\"fast\"");
+        let mut file: syn::File = parse_quote! {
+            #[cfg_attr(feature = "fast", derive(Clone))]
+            #[cfg_attr(feature = "slow", derive(Debug))]
+            struct Config {
+                #[cfg(feature = "slow")]
+                cache: Cache,
+                name: String,
+            }
+
+            #[cfg(test)]
+            mod tests {}
+
+            fn run() {
+                #[cfg(feature = "fast")]
+                fast();
+                #[cfg(not(feature = "fast"))]
+                slow();
+            }
+        };
+        config.prune_file(&mut file);
+
+        assert_eq!(file.items.len(), 2);
+        let item_struct = match &file.items[0] {
+            syn::Item::Struct(item_struct) => item_struct,
+            _ => panic!("expected a struct"),
+        };
+        assert_eq!(item_struct.fields.len(), 1);
+        assert_eq!(item_struct.attrs.len(), 1);
+        assert!(item_struct.attrs[0].path.is_ident("derive"));
+        let item_fn = match &file.items[1] {
+            syn::Item::Fn(item_fn) => item_fn,
+            _ => panic!("expected a fn"),
+        };
+        assert_eq!(item_fn.block.stmts.len(), 1);
+    }
+
+    #[test]
+    fn test_cfg_attr_name_value() {
+        let config = CfgConfig{target_os: Some("linux".to_owned()), ..CfgConfig::default()};
+        let mut file: syn::File = parse_quote! {
+            #[cfg_attr(unix, path = "unix.rs")]
+            #[cfg_attr(windows, path = "windows.rs")]
+            mod imp;
+
+            #[cfg_attr(unix, doc = "Unix only.", must_use)]
+            fn run() -> bool { true }
+        };
+        config.prune_file(&mut file);
+
+        let attrs = |item: &syn::Item| match item {
+            syn::Item::Mod(item_mod) => item_mod.attrs.clone(),
+            syn::Item::Fn(item_fn) => item_fn.attrs.clone(),
+            _ => panic!("expected a mod or a fn"),
+        };
+        let mod_attrs = attrs(&file.items[0]);
+        assert_eq!(mod_attrs.len(), 1);
+        match mod_attrs[0].parse_meta().unwrap() {
+            syn::Meta::NameValue(syn::MetaNameValue{path, lit: syn::Lit::Str(lit), ..}) => {
+                assert!(path.is_ident("path"));
+                assert_eq!(lit.value(), "unix.rs");
+            }
+            _ => panic!("expected #[path = ..]"),
+        }
+        let fn_attrs = attrs(&file.items[1]);
+        assert_eq!(fn_attrs.len(), 2);
+        assert!(fn_attrs[0].path.is_ident("doc"));
+        assert!(fn_attrs[1].path.is_ident("must_use"));
+    }
+}
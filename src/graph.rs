@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Value};
+
+
+/// A string in a quoted DOT id, backslashes and quotes escaped.
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+
+/// A directed graph over named nodes, used for module dependencies and
+/// call graphs. Nodes can carry a mark like `"unresolved"`.
+#[derive(Debug, Default, Clone)]
+pub struct Graph {
+    edges: BTreeMap<String, BTreeSet<String>>,
+    marks: BTreeMap<String, String>,
+}
+
+
+impl Graph {
+
+    pub fn new() -> Self {
+        Graph::default()
+    }
+
+    pub fn add_node(&mut self, node: &str) {
+        self.edges.entry(node.to_owned()).or_default();
+    }
+
+    pub fn add_marked_node(&mut self, node: &str, mark: &str) {
+        self.add_node(node);
+        self.marks.insert(node.to_owned(), mark.to_owned());
+    }
+
+    pub fn mark(&self, node: &str) -> Option<&str> {
+        self.marks.get(node).map(String::as_str)
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str) {
+        self.add_node(to);
+        self.edges.entry(from.to_owned())
+            .or_default()
+            .insert(to.to_owned());
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &String> {
+        self.edges.keys()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(BTreeSet::len).sum()
+    }
+
+    pub fn successors(&self, node: &str) -> impl Iterator<Item = &String> {
+        self.edges.get(node).into_iter().flatten()
+    }
+
+    pub fn predecessors<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a String> {
+        self.edges.iter()
+            .filter(move |(_, succs)| succs.contains(node))
+            .map(|(from, _)| from)
+    }
+
+    /// The successors which are not marked, an unresolved callee is not
+    /// a flow out of its caller.
+    pub fn fan_out(&self, node: &str) -> usize {
+        self.successors(node).filter(|succ| self.mark(succ).is_none()).count()
+    }
+
+    pub fn fan_in(&self, node: &str) -> usize {
+        self.predecessors(node).count()
+    }
+
+    /// Strongly connected components in Tarjan's algorithm order
+    /// (a component comes before the components depending on it).
+    pub fn strongly_connected_components(&self) -> Vec<Vec<String>> {
+        let mut tarjan = Tarjan::default();
+        for node in self.nodes() {
+            if !tarjan.index.contains_key(node.as_str()) {
+                tarjan.connect(self, node);
+            }
+        }
+        tarjan.components
+    }
+
+    /// Components with more than one node, or a node depending on itself.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|scc| {
+                scc.len() > 1 || self.successors(&scc[0]).any(|succ| *succ == scc[0])
+            })
+            .collect()
+    }
+
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", dot_escape(name));
+        for node in self.nodes() {
+            match self.mark(node) {
+                Some(mark) => dot += &format!("    \"{}\" [style=dashed, tooltip=\"{}\"];\n", dot_escape(node), dot_escape(mark)),
+                None => dot += &format!("    \"{}\";\n", dot_escape(node)),
+            }
+        }
+        for (from, succs) in &self.edges {
+            for to in succs {
+                dot += &format!("    \"{}\" -> \"{}\";\n", dot_escape(from), dot_escape(to));
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    pub fn to_json(&self) -> Value {
+        let nodes: Vec<Value> = self.nodes()
+            .map(|node| {
+                let mut value = json!({
+                    "id": node,
+                    "fan_in": self.fan_in(node),
+                    "fan_out": self.fan_out(node),
+                });
+                if let Some(mark) = self.mark(node) {
+                    value["mark"] = json!(mark);
+                }
+                value
+            })
+            .collect();
+        let edges: Vec<Value> = self.edges.iter()
+            .flat_map(|(from, succs)| succs.iter().map(move |to| json!({"from": from, "to": to})))
+            .collect();
+        json!({"nodes": nodes, "edges": edges, "cycles": self.cycles()})
+    }
+}
+
+
+#[derive(Default)]
+struct Tarjan<'a> {
+    next_index: usize,
+    index: BTreeMap<&'a str, usize>,
+    lowlink: BTreeMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: BTreeSet<&'a str>,
+    components: Vec<Vec<String>>,
+}
+
+
+impl<'a> Tarjan<'a> {
+
+    fn connect(&mut self, graph: &'a Graph, node: &'a str) {
+        self.index.insert(node, self.next_index);
+        self.lowlink.insert(node, self.next_index);
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        for succ in graph.successors(node) {
+            if !self.index.contains_key(succ.as_str()) {
+                self.connect(graph, succ);
+                let lowlink = self.lowlink[node].min(self.lowlink[succ.as_str()]);
+                self.lowlink.insert(node, lowlink);
+            } else if self.on_stack.contains(succ.as_str()) {
+                let lowlink = self.lowlink[node].min(self.index[succ.as_str()]);
+                self.lowlink.insert(node, lowlink);
+            }
+        }
+
+        if self.lowlink[node] == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member.to_owned());
+                if member == node {
+                    break;
+                }
+            }
+            component.reverse();
+            self.components.push(component);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::Graph;
+
+    #[test]
+    fn test_fan_in_out() {
+        let mut graph = Graph::new();
+        graph.add_edge("a", "b");
+        graph.add_edge("a", "c");
+        graph.add_edge("b", "c");
+
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.fan_out("a"), 2);
+        assert_eq!(graph.fan_in("c"), 2);
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let mut graph = Graph::new();
+        graph.add_edge("a", "b");
+        graph.add_edge("b", "c");
+        graph.add_edge("c", "a");
+        graph.add_edge("c", "d");
+        graph.add_edge("e", "e");
+
+        let cycles = graph.cycles();
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0], vec!["a", "b", "c"]);
+        assert_eq!(cycles[1], vec!["e"]);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut graph = Graph::new();
+        graph.add_edge("main", "format!(\"{}\\n\")");
+        graph.add_marked_node(".run()", "unresolved");
+
+        let dot = graph.to_dot("calls");
+        assert!(dot.contains("    \"main\" -> \"format!(\\\"{}\\\\n\\\")\";\n"));
+        assert!(dot.contains("    \".run()\" [style=dashed, tooltip=\"unresolved\"];\n"));
+
+        graph.add_edge("main", ".run()");
+        assert_eq!(graph.fan_out("main"), 1);
+
+        let json = graph.to_json();
+        assert_eq!(json["nodes"][0]["id"], ".run()");
+        assert_eq!(json["nodes"][0]["mark"], "unresolved");
+        assert_eq!(json["nodes"][2]["fan_out"], 1);
+        assert_eq!(json["edges"][1]["to"], "format!(\"{}\\n\")");
+    }
+}
@@ -1,8 +1,26 @@
 
+pub mod options;
+
 pub mod metrics;
 
 pub mod utils;
 
+pub mod graph;
+
+pub mod modules;
+
+pub mod references;
+
+pub mod api;
+
+pub mod cfg;
+
+pub mod stats;
+
+pub mod tools;
+
+pub mod cargo;
+
 #[cfg(test)]
 mod tests {
 
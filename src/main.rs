@@ -1,8 +1,19 @@
 use clap::{Arg, App, SubCommand, ArgMatches};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use happ::cfg::CfgConfig;
+use happ::stats::ReportOptions;
+use happ::utils::find_features;
+use happ::metrics::{BlockingApi, MetricRegistry};
 
-mod tools;
-use tools::{process_cc, process_loc, process_locf};
-use tools::{process_large_dir, process_file_duplicate};
+use happ::tools::{process_watch, CCFunction, LocFunction, Options};
+use happ::tools::{default_cache_dir, process_cache_clean};
+use happ::tools::{analyze_cc, analyze_loc, process_locf, process_sig, process_exits};
+use happ::tools::{process_metrics, process_abc, process_macros};
+use happ::tools::{process_unsafe, process_panics, process_async, process_deps, process_calls};
+use happ::tools::{process_dead_code, process_types, process_coupling};
+use happ::tools::{process_api, process_api_diff, process_doc_coverage, process_tests};
+use happ::tools::{process_large_dir, process_file_duplicate};
 
 
 fn main() {
@@ -10,6 +21,75 @@ fn main() {
         .version("0.1")
         .author("yancong")
         .about("A tool measure Rust code")
+        .arg(
+            Arg::with_name("top")
+            .long("top")
+            .takes_value(true)
+            .global(true)
+            .help("Lists this many items in the rankings, 5 by default")
+        )
+        .arg(
+            Arg::with_name("sort")
+            .long("sort")
+            .takes_value(true)
+            .possible_values(&["asc", "desc"])
+            .global(true)
+            .help("Ranks the items in this order, desc by default")
+        )
+        .arg(
+            Arg::with_name("buckets")
+            .long("buckets")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .require_delimiter(true)
+            .global(true)
+            .help("Prints a histogram with buckets starting at these values, like `1,5,10,20`")
+        )
+        .arg(
+            Arg::with_name("features")
+            .long("features")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .require_delimiter(true)
+            .global(true)
+            .help("Prunes the code of the features not listed, as cargo would")
+        )
+        .arg(
+            Arg::with_name("all-features")
+            .long("all-features")
+            .global(true)
+            .help("Keeps the code of every feature")
+        )
+        .arg(
+            Arg::with_name("no-default-features")
+            .long("no-default-features")
+            .global(true)
+            .help("Prunes the code of the default features unless listed in --features")
+        )
+        .arg(
+            Arg::with_name("cfg")
+            .long("cfg")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .global(true)
+            .help("Sets a cfg option, `name` or `key=\"value\"`, as rustc --cfg does")
+        )
+        .arg(
+            Arg::with_name("target-os")
+            .long("target-os")
+            .takes_value(true)
+            .global(true)
+            .help("Evaluates target_os, target_family, unix and windows for this OS")
+        )
+        .arg(
+            Arg::with_name("no-cache")
+            .long("no-cache")
+            .global(true)
+            .help("Analyzes every file again instead of reusing the results in target/happ-cache")
+        )
         .subcommand(
             SubCommand::with_name("cc")
             .about("Compute Complexity of the given Rust code")
@@ -19,6 +99,51 @@ fn main() {
                 .required(true)
                 .default_value("./")
             )
+            .arg(
+                Arg::with_name("max-nesting")
+                .long("max-nesting")
+                .takes_value(true)
+                .help("Fails if any function nests blocks deeper than this")
+            )
+            .arg(
+                Arg::with_name("expand-macros")
+                .long("expand-macros")
+                .help("Counts the code inside macro invocations like `vec![..]` or `println!(..)`")
+            )
+            .arg(
+                Arg::with_name("include-closures")
+                .long("include-closures")
+                .help("Counts the complexity of closures and async blocks in their parent instead of reporting them as `outer::{closure#N}`")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("metrics")
+            .about("Compute the registered metrics (cc, loc, lloc) per function and per file")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+            .arg(
+                Arg::with_name("metric")
+                .long("metric")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .require_delimiter(true)
+                .help("Computes only these metrics")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("abc")
+            .about("Compute the ABC (Assignments, Branches, Conditions) size per function")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
         )
         .subcommand(
             SubCommand::with_name("loc")
@@ -29,6 +154,32 @@ fn main() {
                 .required(true)
                 .default_value("./")
             )
+            .arg(
+                Arg::with_name("max-nesting")
+                .long("max-nesting")
+                .takes_value(true)
+                .help("Fails if any function nests blocks deeper than this")
+            )
+            .arg(
+                Arg::with_name("expand-macros")
+                .long("expand-macros")
+                .help("Counts the statements inside macro invocations like `vec![..]` or `println!(..)` as logic lines (LLOC)")
+            )
+            .arg(
+                Arg::with_name("include-closures")
+                .long("include-closures")
+                .help("Counts the lines of closures and async blocks in their parent instead of reporting them as `outer::{closure#N}`")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("macros")
+            .about("Compute arms, repetition nesting and tokens of macro_rules! definitions")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
         )
         .subcommand(
             SubCommand::with_name("locf")
@@ -40,6 +191,181 @@ fn main() {
                 .default_value("./")
             )
         )
+        .subcommand(
+            SubCommand::with_name("sig")
+            .about("Rank functions by signature size (arguments, generics, type depth)")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("exits")
+            .about("Count exit points (NEXITS) per function of the given Rust code")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("unsafe")
+            .about("List every unsafe usage and the unsafe density per crate and module")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("panics")
+            .about("Count unwrap, expect, panic macros, indexing and division per function")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("async")
+            .about("Count await points, select!/join!, spawned tasks and blocking calls in async code")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+            .arg(
+                Arg::with_name("blocking-apis")
+                .long("blocking-apis")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .require_delimiter(true)
+                .help("Replaces the blocking APIs looked for, like `std::fs::*,Mutex::lock`")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("deps")
+            .about("Build the module dependency graph and find cycles")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input crate directory or root file to use")
+                .required(true)
+                .default_value("./")
+            )
+            .arg(
+                Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["text", "dot", "json"])
+                .default_value("text")
+                .help("Output format")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("coupling")
+            .about("Compute afferent/efferent coupling, instability and abstractness per module")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input crate directory or root file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("calls")
+            .about("Build the call graph and compute fan-in, fan-out and Henry-Kafura complexity")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+            .arg(
+                Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["text", "dot", "json"])
+                .default_value("text")
+                .help("Output format")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("dead")
+            .about("List private items never referenced, or referenced from tests only")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input crate directory or root file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("api")
+            .about("List the public items reachable from the crate root")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input crate directory or root file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("api-diff")
+            .about("Classify the public API changes between two versions of a crate")
+            .arg(
+                Arg::with_name("old")
+                .help("Sets the old crate directory")
+                .required(true)
+            )
+            .arg(
+                Arg::with_name("new")
+                .help("Sets the new crate directory")
+                .required(true)
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("docs")
+            .about("Compute the documentation coverage of pub items")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input crate directory or root file to use")
+                .required(true)
+                .default_value("./")
+            )
+            .arg(
+                Arg::with_name("min-doc-coverage")
+                .long("min-doc-coverage")
+                .takes_value(true)
+                .help("Fails if a crate documents less than this percentage of its pub items")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("tests")
+            .about("Count test functions and test LOC per module, find untested pub fns")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input crate directory to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("types")
+            .about("Rank structs, enums, unions and traits by methods, fields and LCOM4")
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input file to use")
+                .required(true)
+                .default_value("./")
+            )
+        )
         .subcommand(
             SubCommand::with_name("ldir")
             .about("Find the directory with most file")
@@ -60,31 +386,306 @@ fn main() {
                 .default_value("./")
             )
         )
+        .subcommand(
+            SubCommand::with_name("cache")
+            .about("Manages the results cached by cc, loc and locf")
+            .subcommand(
+                SubCommand::with_name("clean")
+                .about("Removes the cache of the crate of the given path")
+                .arg(
+                    Arg::with_name("input")
+                    .help("Sets the input directory to use")
+                    .required(true)
+                    .default_value("./")
+                )
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+            .about("Re-runs cc or loc on the files saved under the given path, printing the functions changed")
+            .arg(
+                Arg::with_name("metric")
+                .help("The subcommand to re-run")
+                .required(true)
+                .possible_values(&["cc", "loc"])
+            )
+            .arg(
+                Arg::with_name("input")
+                .help("Sets the input directory to use")
+                .required(true)
+                .default_value("./")
+            )
+            .arg(
+                Arg::with_name("debounce")
+                .long("debounce")
+                .takes_value(true)
+                .default_value("300")
+                .help("Waits for this many milliseconds without saves before re-running")
+            )
+            .arg(
+                Arg::with_name("expand-macros")
+                .long("expand-macros")
+                .help("Counts the code inside macro invocations like `vec![..]` or `println!(..)`")
+            )
+            .arg(
+                Arg::with_name("include-closures")
+                .long("include-closures")
+                .help("Counts closures and async blocks in their parent instead of reporting them as `outer::{closure#N}`")
+            )
+        )
         .get_matches();
-    
+
+    let report = match matches.subcommand() {
+        (_, Some(matches)) => report_options(matches),
+        _ => ReportOptions::default(),
+    };
+
     if let Some(matches) = matches.subcommand_matches("cc") {
         let path_str = matches.value_of("input").unwrap();
-        process_cc(path_str)
+        let max_nesting = matches.value_of("max-nesting")
+            .map(|s| s.parse::<usize>().expect("--max-nesting expects an integer"));
+        let options = options(matches, path_str);
+        let mut stats = analyze_cc(&[path_str], &options);
+        stats.summary(&report);
+        if !max_nesting.is_none_or(|max_nesting| stats.nesting_gate(max_nesting)) {
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("metrics") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        let mut registry = MetricRegistry::builtin(&options);
+        if let Some(names) = matches.values_of("metric") {
+            let names: Vec<String> = names.map(String::from).collect();
+            if let Err(err) = registry.select(&names) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        process_metrics(path_str, &registry, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("abc") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_abc(path_str, &options, &report)
     }
 
     if let Some(matches) = matches.subcommand_matches("loc") {
         let path_str = matches.value_of("input").unwrap();
-        process_loc(path_str)
+        let max_nesting = matches.value_of("max-nesting")
+            .map(|s| s.parse::<usize>().expect("--max-nesting expects an integer"));
+        let options = options(matches, path_str);
+        let mut stats = analyze_loc(&[path_str], &options);
+        stats.summary(&report);
+        if !max_nesting.is_none_or(|max_nesting| stats.nesting_gate(max_nesting)) {
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("macros") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_macros(path_str, &options, &report)
     }
     
     if let Some(matches) = matches.subcommand_matches("locf") {
         let path_str = matches.value_of("input").unwrap();
-        process_locf(path_str)
+        let options = options(matches, path_str);
+        process_locf(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sig") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_sig(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("exits") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_exits(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("unsafe") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_unsafe(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("panics") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_panics(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("async") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        let blocking_apis = match matches.values_of("blocking-apis") {
+            Some(apis) => apis.map(BlockingApi::new).collect(),
+            None => BlockingApi::defaults(),
+        };
+        process_async(path_str, &blocking_apis, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("deps") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        let format = matches.value_of("format").unwrap();
+        process_deps(path_str, format, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("coupling") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_coupling(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("calls") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        let format = matches.value_of("format").unwrap();
+        process_calls(path_str, format, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("dead") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_dead_code(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("api") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_api(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("api-diff") {
+        let old_path_str = matches.value_of("old").unwrap();
+        let new_path_str = matches.value_of("new").unwrap();
+        let options = options(matches, new_path_str);
+        process_api_diff(old_path_str, new_path_str, &options)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("docs") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        let min_coverage = matches.value_of("min-doc-coverage")
+            .map(|s| s.parse::<f64>().expect("--min-doc-coverage expects a percentage"));
+        if !process_doc_coverage(path_str, &options, &report, min_coverage) {
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("tests") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_tests(path_str, &options, &report)
+    }
+
+    if let Some(matches) = matches.subcommand_matches("types") {
+        let path_str = matches.value_of("input").unwrap();
+        let options = options(matches, path_str);
+        process_types(path_str, &options, &report)
     }
 
     if let Some(matches) = matches.subcommand_matches("ldir") {
         let path_str = matches.value_of("input").unwrap();
-        process_large_dir(path_str)
+        process_large_dir(path_str, &report)
     }
 
     if let Some(matches) = matches.subcommand_matches("fdupl") {
         let path_str = matches.value_of("input").unwrap();
         process_file_duplicate(path_str)
     }
+
+    if let Some(matches) = matches.subcommand_matches("cache") {
+        if let Some(matches) = matches.subcommand_matches("clean") {
+            let path_str = matches.value_of("input").unwrap();
+            process_cache_clean(path_str)
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("watch") {
+        let path_str = matches.value_of("input").unwrap();
+        let debounce = matches.value_of("debounce").unwrap()
+            .parse::<u64>().expect("--debounce expects milliseconds");
+        let options = options(matches, path_str);
+        match matches.value_of("metric").unwrap() {
+            "cc" => process_watch::<CCFunction>(path_str, &options, &report, Duration::from_millis(debounce)),
+            _ => process_watch::<LocFunction>(path_str, &options, &report, Duration::from_millis(debounce)),
+        }
+    }
+}
+
+
+
+/// The build configuration given by `--features`, `--cfg` and friends, or
+/// `None` to count the code of every configuration.
+fn cfg_config(matches: &ArgMatches) -> Option<CfgConfig> {
+    let options = ["features", "all-features", "no-default-features", "cfg", "target-os"];
+    if !options.iter().any(|option| matches.is_present(option)) {
+        return None
+    }
+
+    let path_str = matches.value_of("input").or_else(|| matches.value_of("new")).unwrap_or("./");
+    let table = find_features(Path::new(path_str));
+    let mut config = CfgConfig{
+        all_features: matches.is_present("all-features"),
+        target_os: matches.value_of("target-os").map(String::from),
+        ..CfgConfig::default()
+    };
+    if !matches.is_present("no-default-features") {
+        config.enable_feature("default", &table);
+    }
+    for feature in matches.values_of("features").into_iter().flatten() {
+        config.enable_feature(feature, &table);
+    }
+    for cfg in matches.values_of("cfg").into_iter().flatten() {
+        config.add_cfg(cfg);
+    }
+    Some(config)
+}
+
+
+/// The options of a subcommand: its `--expand-macros` and
+/// `--include-closures` flags, where to cache and the build configuration.
+fn options(matches: &ArgMatches, path_str: &str) -> Options {
+    Options{
+        expand_macros: matches.is_present("expand-macros"),
+        include_closures: matches.is_present("include-closures"),
+        cache_dir: cache_dir(matches, path_str),
+        cfg: cfg_config(matches),
+    }
+}
+
+
+/// Where `cc`, `loc` and `locf` cache their results, none with `--no-cache`.
+fn cache_dir(matches: &ArgMatches, path_str: &str) -> Option<PathBuf> {
+    match matches.is_present("no-cache") {
+        true => None,
+        false => Some(default_cache_dir(Path::new(path_str))),
+    }
 }
 
+
+/// The `--top`, `--sort` and `--buckets` options of every subcommand.
+fn report_options(matches: &ArgMatches) -> ReportOptions {
+    let mut options = ReportOptions::default();
+    if let Some(top) = matches.value_of("top") {
+        options.top = top.parse::<usize>().expect("--top expects an integer");
+    }
+    if let Some(order) = matches.value_of("sort") {
+        options.order = order.parse().unwrap();
+    }
+    if let Some(buckets) = matches.values_of("buckets") {
+        let mut bounds: Vec<f64> = buckets
+            .map(|b| b.parse::<f64>().ok().filter(|b| b.is_finite()).expect("--buckets expects numbers"))
+            .collect();
+        bounds.sort_by(f64::total_cmp);
+        options.buckets = Some(bounds);
+    }
+    options
+}
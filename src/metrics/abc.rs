@@ -0,0 +1,163 @@
+use std::fmt;
+use syn::visit::{self, Visit};
+
+use crate::metrics::loc::{classify_expr, ExprClass};
+
+
+/// ABC size of a function: assignments (`let`, `=`, `op=`, `&mut`
+/// borrows), branches (calls, method calls, macros) and conditions
+/// (comparisons, `if`, `else`, match arms, `while`, `?`).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AbcVisitor {
+    pub assignments: usize,
+    pub branches: usize,
+    pub conditions: usize,
+}
+
+
+impl fmt::Display for AbcVisitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}, {}, {}>", self.assignments, self.branches, self.conditions)
+    }
+}
+
+
+impl AbcVisitor {
+
+    /// Magnitude of the ABC vector, `sqrt(A^2 + B^2 + C^2)`.
+    pub fn magnitude(&self) -> f64 {
+        let (a, b, c) = (self.assignments as f64, self.branches as f64, self.conditions as f64);
+        (a * a + b * b + c * c).sqrt()
+    }
+}
+
+
+impl<'ast> Visit<'ast> for AbcVisitor {
+
+    fn visit_local(&mut self, i: &'ast syn::Local) {
+        self.assignments += 1;
+        visit::visit_local(self, i);
+    }
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        match classify_expr(i) {
+            Some(ExprClass::Assignment) => self.assignments += 1,
+            Some(ExprClass::Call) => self.branches += 1,
+            Some(ExprClass::Condition) => self.conditions += 1,
+            _ => ()
+        }
+        visit::visit_expr(self, i);
+    }
+
+    fn visit_expr_reference(&mut self, i: &'ast syn::ExprReference) {
+        if i.mutability.is_some() {
+            self.assignments += 1;
+        }
+        visit::visit_expr_reference(self, i);
+    }
+
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        // `else if` is counted as the nested `if`
+        if let Some((_, else_branch)) = &i.else_branch {
+            if let syn::Expr::Block(_) = &**else_branch {
+                self.conditions += 1;
+            }
+        }
+        visit::visit_expr_if(self, i);
+    }
+
+    fn visit_arm(&mut self, i: &'ast syn::Arm) {
+        self.conditions += 1;
+        visit::visit_arm(self, i);
+    }
+
+    fn visit_bin_op(&mut self, i: &'ast syn::BinOp) {
+        match i {
+            syn::BinOp::Eq(_) | syn::BinOp::Ne(_)
+            | syn::BinOp::Lt(_) | syn::BinOp::Le(_)
+            | syn::BinOp::Gt(_) | syn::BinOp::Ge(_)
+            => self.conditions += 1,
+            _ => ()
+        }
+        visit::visit_bin_op(self, i);
+    }
+
+    fn visit_stmt(&mut self, i: &'ast syn::Stmt) {
+        // `name! { .. }` statements are items, not expressions
+        if let syn::Stmt::Item(syn::Item::Macro(_)) = i {
+            self.branches += 1;
+        }
+        visit::visit_stmt(self, i);
+    }
+
+}
+
+
+#[allow(dead_code)]
+pub fn compute_itemfn_abc(itemfn: &syn::ItemFn) -> AbcVisitor {
+    let mut abc_visitor = AbcVisitor::default();
+    abc_visitor.visit_block(&itemfn.block);
+    abc_visitor
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_abc(impl_method: &syn::ImplItemMethod) -> AbcVisitor {
+    let mut abc_visitor = AbcVisitor::default();
+    abc_visitor.visit_block(&impl_method.block);
+    abc_visitor
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::compute_itemfn_abc;
+
+    #[test]
+    fn test_abc() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn mean(values: &mut Vec<f64>) -> Option<f64> {
+                let mut sum = 0.0;
+                for value in values.iter() {
+                    sum += value;
+                }
+                if values.len() == 0 {
+                    return None;
+                } else {
+                    sort(&mut values);
+                }
+                let first = values.first()?;
+                Some(sum / values.len() as f64)
+            }
+        };
+
+        let abc = compute_itemfn_abc(&fn_body);
+        // `let` x2, `+=`, `&mut`
+        assert_eq!(abc.assignments, 4);
+        // `iter`, `len` x2, `sort`, `first`, `Some`
+        assert_eq!(abc.branches, 6);
+        // `if`, `==`, `else`, `?`
+        assert_eq!(abc.conditions, 4);
+        assert!((abc.magnitude() - (16.0f64 + 36.0 + 16.0).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_abc_match() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn kind(x: i32) -> &'static str {
+                match x {
+                    0 => "zero",
+                    x if x < 0 => "negative",
+                    _ => "positive",
+                }
+            }
+        };
+
+        let abc = compute_itemfn_abc(&fn_body);
+        // three arms and `<`
+        assert_eq!(abc.conditions, 4);
+        assert_eq!(abc.branches, 0);
+    }
+}
@@ -0,0 +1,307 @@
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use crate::metrics::macros::visit_macro_body;
+
+
+/// APIs blocking the thread they run on, which stall every task of an async
+/// executor thread. `*` matches any last segment, `Type::method` also
+/// matches `.method()` calls on any receiver.
+pub const DEFAULT_BLOCKING_APIS: [&str; 8] = [
+    "std::thread::sleep",
+    "std::fs::*",
+    "std::fs::File::open",
+    "std::fs::File::create",
+    "std::io::stdin",
+    "std::process::Command::output",
+    "std::net::TcpStream::connect",
+    "Mutex::lock",
+];
+
+
+const SELECT_MACROS: [&str; 3] = ["select", "select_biased", "try_select"];
+const JOIN_MACROS: [&str; 2] = ["join", "try_join"];
+const SPAWN_FNS: [&str; 3] = ["spawn", "spawn_local", "spawn_blocking"];
+
+
+/// An entry of the blocking API list, like `std::fs::*` or `Mutex::lock`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockingApi {
+    segments: Vec<String>,
+}
+
+
+impl BlockingApi {
+
+    pub fn new(api: &str) -> Self {
+        BlockingApi{segments: api.split("::").map(|s| s.trim().to_owned()).collect()}
+    }
+
+    pub fn defaults() -> Vec<BlockingApi> {
+        DEFAULT_BLOCKING_APIS.iter().map(|api| BlockingApi::new(api)).collect()
+    }
+
+    /// Whether a called path like `fs::read_to_string` is this API. The path
+    /// must name at least a module or type, a bare `sleep(..)` is not enough.
+    fn matches_path(&self, path: &[String]) -> bool {
+        if path.len() < 2 || path.len() > self.segments.len() {
+            return false
+        }
+        self.segments.iter().rev().zip(path.iter().rev())
+            .enumerate()
+            .all(|(idx, (api, called))| api == called || (idx == 0 && api == "*"))
+    }
+
+    /// Whether a `.method()` call is this API, for the `Type::method` entries.
+    fn matches_method(&self, method: &str) -> bool {
+        let n = self.segments.len();
+        let is_type = n >= 2 && self.segments[n - 2].starts_with(char::is_uppercase);
+        is_type && self.segments[n - 1] == method
+    }
+}
+
+
+impl std::fmt::Display for BlockingApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.segments.join("::"))
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct BlockingCall {
+    pub call: String,  // the called path or `.method()`
+    pub line: usize,
+}
+
+
+/// Await points, concurrency macros, spawned tasks and blocking calls of an
+/// async fn or async block. Closures which are not async are not async
+/// contexts, a blocking call inside `spawn_blocking(|| ..)` is fine.
+#[derive(Debug, Clone)]
+pub struct AsyncVisitor<'a> {
+    pub awaits: usize,
+    pub selects: usize,
+    pub joins: usize,
+    pub spawns: usize,
+    pub blocking: Vec<BlockingCall>,
+    blocking_apis: &'a [BlockingApi],
+    in_async: bool,
+    awaited: bool,  // the call being visited is awaited, an async API
+}
+
+
+fn path_segments(path: &syn::Path) -> Vec<String> {
+    path.segments.iter().map(|s| s.ident.to_string()).collect()
+}
+
+
+impl<'a> AsyncVisitor<'a> {
+
+    pub fn new(blocking_apis: &'a [BlockingApi]) -> Self {
+        AsyncVisitor{
+            awaits: 0, selects: 0, joins: 0, spawns: 0, blocking: vec![],
+            blocking_apis, in_async: true, awaited: false,
+        }
+    }
+
+    fn add_blocking(&mut self, call: String, line: usize) {
+        if self.in_async {
+            self.blocking.push(BlockingCall{call, line});
+        }
+    }
+
+    fn visit_context(&mut self, in_async: bool, f: impl FnOnce(&mut Self)) {
+        let outer = self.in_async;
+        self.in_async = in_async;
+        f(self);
+        self.in_async = outer;
+    }
+}
+
+
+impl<'ast, 'a> Visit<'ast> for AsyncVisitor<'a> {
+
+    fn visit_expr_await(&mut self, i: &'ast syn::ExprAwait) {
+        if self.in_async {
+            self.awaits += 1;
+        }
+        self.awaited = matches!(&*i.base, syn::Expr::Call(_) | syn::Expr::MethodCall(_));
+        visit::visit_expr_await(self, i);
+    }
+
+    fn visit_expr_call(&mut self, i: &'ast syn::ExprCall) {
+        let awaited = std::mem::take(&mut self.awaited);
+        if let syn::Expr::Path(func) = &*i.func {
+            let path = path_segments(&func.path);
+            let last = path.last().map(String::as_str).unwrap_or("");
+            if self.in_async && SPAWN_FNS.contains(&last) {
+                self.spawns += 1;
+            }
+            if !awaited && self.blocking_apis.iter().any(|api| api.matches_path(&path)) {
+                self.add_blocking(path.join("::"), i.span().start().line);
+            }
+        }
+        visit::visit_expr_call(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        let awaited = std::mem::take(&mut self.awaited);
+        let method = i.method.to_string();
+        if self.in_async && SPAWN_FNS.contains(&method.as_str()) {
+            self.spawns += 1;
+        }
+        if !awaited && self.blocking_apis.iter().any(|api| api.matches_method(&method)) {
+            self.add_blocking(format!(".{}()", method), i.method.span().start().line);
+        }
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if let Some(segment) = i.path.segments.last() {
+            let name = segment.ident.to_string();
+            if self.in_async && SELECT_MACROS.contains(&name.as_str()) {
+                self.selects += 1;
+            }
+            if self.in_async && JOIN_MACROS.contains(&name.as_str()) {
+                self.joins += 1;
+            }
+        }
+        visit_macro_body(self, i);
+        visit::visit_macro(self, i);
+    }
+
+    fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+        let in_async = i.asyncness.is_some();
+        self.visit_context(in_async, |visitor| visit::visit_expr_closure(visitor, i));
+    }
+
+    fn visit_expr_async(&mut self, i: &'ast syn::ExprAsync) {
+        self.visit_context(true, |visitor| visit::visit_expr_async(visitor, i));
+    }
+
+    fn visit_item(&mut self, i: &'ast syn::Item) {
+        // nested fns are not part of the enclosing async context, statement
+        // macros like `select! { .. }` are
+        if let syn::Item::Macro(item_macro) = i {
+            self.visit_item_macro(item_macro);
+        }
+    }
+
+}
+
+
+/// Async metrics of an `async fn` body, `None` for other functions.
+pub fn compute_async_fn<'a>(sig: &syn::Signature, block: &syn::Block, blocking_apis: &'a [BlockingApi]) -> Option<AsyncVisitor<'a>> {
+    sig.asyncness?;
+    let mut async_visitor = AsyncVisitor::new(blocking_apis);
+    async_visitor.visit_block(block);
+    Some(async_visitor)
+}
+
+
+/// Async metrics of an async block or async closure.
+pub fn compute_async_block<'a>(expr: &syn::Expr, blocking_apis: &'a [BlockingApi]) -> AsyncVisitor<'a> {
+    let mut async_visitor = AsyncVisitor::new(blocking_apis);
+    match expr {
+        syn::Expr::Async(async_block) => async_visitor.visit_block(&async_block.block),
+        syn::Expr::Closure(closure) => async_visitor.visit_expr(&closure.body),
+        _ => async_visitor.visit_expr(expr),
+    }
+    async_visitor
+}
+
+
+/// Collects the outermost async blocks and async closures of a function
+/// which is not async itself.
+struct AsyncBlockCollector<'ast> {
+    outer: String,
+    blocks: Vec<(String, &'ast syn::Expr)>,
+}
+
+
+impl<'ast> Visit<'ast> for AsyncBlockCollector<'ast> {
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        let is_async = match i {
+            syn::Expr::Async(_) => true,
+            syn::Expr::Closure(closure) => closure.asyncness.is_some(),
+            _ => false
+        };
+        if is_async {
+            let name = format!("{}::{{async_block#{}}}", self.outer, self.blocks.len());
+            self.blocks.push((name, i));
+        } else {
+            visit::visit_expr(self, i);
+        }
+    }
+
+    fn visit_item(&mut self, _i: &'ast syn::Item) {
+        // nested fns are not part of the enclosing function
+    }
+
+}
+
+
+/// The outermost async blocks of a function body, named
+/// `outer::{async_block#N}`.
+pub fn find_async_blocks<'ast>(outer: &str, block: &'ast syn::Block) -> Vec<(String, &'ast syn::Expr)> {
+    let mut collector = AsyncBlockCollector{outer: outer.to_owned(), blocks: vec![]};
+    collector.visit_block(block);
+    collector.blocks
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{compute_async_fn, find_async_blocks, BlockingApi};
+
+    #[test]
+    fn test_async_fn() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            async fn handle(state: Arc<Mutex<State>>, path: PathBuf) -> Result<()> {
+                let config = fs::read_to_string(&path)?;
+                let data = tokio::fs::read(&path).await?;
+                let cached = state.lock().unwrap().get(&config);
+                let (a, b) = tokio::join!(fetch(&config), fetch(&data));
+                tokio::spawn(async move { notify(a).await });
+                let parsed = tokio::task::spawn_blocking(move || std::fs::read(path)).await?;
+                tokio::select! {
+                    _ = shutdown() => {},
+                    _ = tokio::time::sleep(TIMEOUT) => {},
+                }
+                Ok(())
+            }
+        };
+
+        let apis = BlockingApi::defaults();
+        let metrics = compute_async_fn(&fn_body.sig, &fn_body.block, &apis).unwrap();
+        assert_eq!(metrics.awaits, 3);
+        assert_eq!(metrics.joins, 1);
+        assert_eq!(metrics.selects, 1);
+        assert_eq!(metrics.spawns, 2);
+        let calls: Vec<&str> = metrics.blocking.iter().map(|c| c.call.as_str()).collect();
+        assert_eq!(calls, vec!["fs::read_to_string", ".lock()"]);
+    }
+
+    #[test]
+    fn test_async_blocks() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn main() {
+                let rt = Runtime::new().unwrap();
+                rt.block_on(async {
+                    std::thread::sleep(Duration::from_secs(1));
+                    serve().await
+                });
+            }
+        };
+
+        let apis = BlockingApi::defaults();
+        assert!(compute_async_fn(&fn_body.sig, &fn_body.block, &apis).is_none());
+        let blocks = find_async_blocks("main", &fn_body.block);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].0, "main::{async_block#0}");
+    }
+}
@@ -0,0 +1,87 @@
+use syn::visit::{self, Visit};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    // `foo()`, `Type::foo()` or `module::foo()`, the path segments
+    Path(Vec<String>),
+    // `recv.foo()`, the method name and whether `recv` is `self`
+    Method(String, bool),
+}
+
+
+/// Calls made by a function, including the ones in its closures.
+#[derive(Debug, Default, Clone)]
+pub struct CallVisitor {
+    pub calls: Vec<Call>,
+}
+
+
+impl<'ast> Visit<'ast> for CallVisitor {
+
+    fn visit_expr_call(&mut self, i: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(expr_path) = &*i.func {
+            let segments = expr_path.path.segments.iter()
+                .map(|s| s.ident.to_string())
+                .collect();
+            self.calls.push(Call::Path(segments));
+        }
+        visit::visit_expr_call(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        let on_self = match &*i.receiver {
+            syn::Expr::Path(expr_path) => expr_path.path.is_ident("self"),
+            _ => false
+        };
+        self.calls.push(Call::Method(i.method.to_string(), on_self));
+        visit::visit_expr_method_call(self, i);
+    }
+
+}
+
+
+#[allow(dead_code)]
+pub fn compute_itemfn_calls(itemfn: &syn::ItemFn) -> Vec<Call> {
+    let mut call_visitor = CallVisitor::default();
+    call_visitor.visit_block(&itemfn.block);
+    call_visitor.calls
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_calls(impl_method: &syn::ImplItemMethod) -> Vec<Call> {
+    let mut call_visitor = CallVisitor::default();
+    call_visitor.visit_block(&impl_method.block);
+    call_visitor.calls
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{compute_itemfn_calls, Call};
+
+    #[test]
+    fn test_calls() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_calls(path_str: &str) {
+                let mut stats = CCStats::new();
+                for rs_fpath in iter_rs_fpath(path_str) {
+                    stats.add_funcs(process_cc_file(&rs_fpath));
+                }
+                stats.summary();
+            }
+        };
+
+        let calls = compute_itemfn_calls(&fn_body);
+        assert_eq!(calls, vec![
+            Call::Path(vec!["CCStats".to_owned(), "new".to_owned()]),
+            Call::Path(vec!["iter_rs_fpath".to_owned()]),
+            Call::Method("add_funcs".to_owned(), false),
+            Call::Path(vec!["process_cc_file".to_owned()]),
+            Call::Method("summary".to_owned(), false),
+        ]);
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+use syn::visit::{self, Visit};
+
+use crate::metrics::cyclomatic_complexity::decision_points;
+
+
+/// The statements of a closure or async block body, `None` for other
+/// expressions.
+fn closure_body(expr: &syn::Expr) -> Option<&[syn::Stmt]> {
+    match expr {
+        syn::Expr::Closure(closure) => match &*closure.body {
+            syn::Expr::Block(body) => Some(&body.block.stmts),
+            _ => Some(&[]),
+        },
+        syn::Expr::Async(async_block) => Some(&async_block.block.stmts),
+        _ => None
+    }
+}
+
+
+/// A reported closure and the reported closures nested in it.
+struct ClosureNode<'ast> {
+    expr: &'ast syn::Expr,
+    children: Vec<ClosureNode<'ast>>,
+}
+
+
+/// The decision points and reported closures found so far in a function
+/// body or in a closure.
+#[derive(Default)]
+struct Scope<'ast> {
+    decisions: usize,
+    children: Vec<ClosureNode<'ast>>,
+}
+
+
+/// Finds the reported closures and async blocks in one bottom-up pass:
+/// whether a closure is reported is known once its body has been visited.
+struct ClosureFinder<'ast> {
+    scopes: Vec<Scope<'ast>>,
+}
+
+
+impl<'ast> Visit<'ast> for ClosureFinder<'ast> {
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        self.scopes.last_mut().unwrap().decisions += decision_points(i);
+        let stmts = match closure_body(i) {
+            Some(stmts) => stmts,
+            None => return visit::visit_expr(self, i),
+        };
+        self.scopes.push(Scope::default());
+        visit::visit_expr(self, i);
+        let scope = self.scopes.pop().unwrap();
+        let parent = self.scopes.last_mut().unwrap();
+        parent.decisions += scope.decisions;
+        // `|x| x + 1` is not reported, the closures in it are
+        if stmts.len() > 1 || scope.decisions > 0 {
+            parent.children.push(ClosureNode{expr: i, children: scope.children});
+        } else {
+            parent.children.extend(scope.children);
+        }
+    }
+
+    fn visit_item(&mut self, _i: &'ast syn::Item) {
+        // nested fns are not part of the enclosing function
+    }
+
+}
+
+
+/// The closures a visitor leaves to their own pseudo-functions, shared by
+/// the visitors of a function and of its closures.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludedClosures(Rc<HashSet<*const syn::Expr>>);
+
+
+impl ExcludedClosures {
+
+    pub fn contains(&self, expr: &syn::Expr) -> bool {
+        self.0.contains(&(expr as *const syn::Expr))
+    }
+}
+
+
+/// The reported closures and async blocks of a function body, nested ones
+/// included, named after the enclosing function or closure like rustc
+/// does: `outer::{closure#0}`, `outer::{closure#0}::{closure#1}`.
+///
+/// A closure is reported as a pseudo-function when it has more than one
+/// statement or a decision point, those of its nested closures included.
+/// `|x| x + 1` is not.
+pub struct ClosureIndex<'ast> {
+    closures: Vec<(String, &'ast syn::Expr)>,
+    excluded: ExcludedClosures,
+}
+
+
+impl<'ast> ClosureIndex<'ast> {
+
+    pub fn new(outer: &str, block: &'ast syn::Block) -> Self {
+        let mut finder = ClosureFinder{scopes: vec![Scope::default()]};
+        finder.visit_block(block);
+        let mut closures = vec![];
+        name_closures(outer, finder.scopes.pop().unwrap().children, &mut closures);
+        let excluded = closures.iter().map(|(_, expr)| *expr as *const syn::Expr).collect();
+        ClosureIndex{closures, excluded: ExcludedClosures(Rc::new(excluded))}
+    }
+
+    pub fn closures(&self) -> &[(String, &'ast syn::Expr)] {
+        &self.closures
+    }
+
+    pub fn excluded(&self) -> &ExcludedClosures {
+        &self.excluded
+    }
+}
+
+
+fn name_closures<'ast>(outer: &str, nodes: Vec<ClosureNode<'ast>>, closures: &mut Vec<(String, &'ast syn::Expr)>) {
+    for (count, node) in nodes.into_iter().enumerate() {
+        let name = format!("{}::{{closure#{}}}", outer, count);
+        closures.push((name.clone(), node.expr));
+        name_closures(&name, node.children, closures);
+    }
+}
+
+
+/// The reported closures and async blocks of a function body with their
+/// pseudo-function names, see `ClosureIndex`.
+pub fn find_closures<'ast>(outer: &str, block: &'ast syn::Block) -> Vec<(String, &'ast syn::Expr)> {
+    ClosureIndex::new(outer, block).closures
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::find_closures;
+
+    #[test]
+    fn test_find_closures() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn outer(v: Vec<usize>) {
+                let w: Vec<usize> = v.iter().map(|x| x + 1).collect();
+                w.iter().for_each(|x| {
+                    let fut = async move {
+                        if *x > 0 {
+                            println!("{}", x);
+                        }
+                    };
+                    spawn(fut);
+                });
+                let check = |x: usize| x > 0 && x < 10;
+                let nested = v.iter().map(|x| x.checked_sub(1).map(|y| {
+                    let z = y * 2;
+                    z + 1
+                }));
+            }
+        };
+
+        let names: Vec<String> = find_closures("outer", &fn_body.block).into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec![
+            "outer::{closure#0}",
+            "outer::{closure#0}::{closure#0}",
+            "outer::{closure#1}",
+            "outer::{closure#2}",
+        ]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeSet;
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+
+use crate::graph::Graph;
+
+
+/// Fields of `self` a method accesses and methods it calls on `self`.
+#[derive(Debug, Default, Clone)]
+pub struct CohesionVisitor {
+    pub fields: BTreeSet<String>,
+    pub calls: BTreeSet<String>,
+}
+
+
+fn is_self(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Path(expr_path) => expr_path.path.is_ident("self"),
+        _ => false
+    }
+}
+
+
+impl<'ast> Visit<'ast> for CohesionVisitor {
+
+    fn visit_expr_field(&mut self, i: &'ast syn::ExprField) {
+        if is_self(&i.base) {
+            let field = match &i.member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+            self.fields.insert(field);
+        }
+        visit::visit_expr_field(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        if is_self(&i.receiver) {
+            self.calls.insert(i.method.to_string());
+        }
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        // arguments of `format!`, `assert!` and the like
+        let parser = Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated;
+        if let Ok(args) = i.parse_body_with(parser) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+        visit::visit_macro(self, i);
+    }
+
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_cohesion(impl_method: &syn::ImplItemMethod) -> CohesionVisitor {
+    let mut cohesion_visitor = CohesionVisitor::default();
+    cohesion_visitor.visit_block(&impl_method.block);
+    cohesion_visitor
+}
+
+
+/// LCOM4, the groups of methods connected by a shared field or a call
+/// between them. A cohesive type has a single group.
+pub fn lcom4_groups(methods: &[(String, CohesionVisitor)]) -> Vec<Vec<String>> {
+    let mut graph = Graph::new();
+    for (name, usage) in methods {
+        graph.add_node(name);
+        for callee in &usage.calls {
+            if methods.iter().any(|(other, _)| other == callee) {
+                graph.add_edge(name, callee);
+                graph.add_edge(callee, name);
+            }
+        }
+    }
+    for (idx, (name, usage)) in methods.iter().enumerate() {
+        for (other, other_usage) in &methods[idx + 1..] {
+            if !usage.fields.is_disjoint(&other_usage.fields) {
+                graph.add_edge(name, other);
+                graph.add_edge(other, name);
+            }
+        }
+    }
+    graph.strongly_connected_components()
+}
+
+
+/// Henderson-Sellers LCOM, `(m - mean(m_a)) / (m - 1)` where `m_a` is the
+/// number of methods accessing field `a`. 0 is cohesive, 1 and above is not.
+pub fn lcom_hs(methods: &[(String, CohesionVisitor)], fields: &[String]) -> Option<f64> {
+    let m = methods.len() as f64;
+    if methods.len() < 2 || fields.is_empty() {
+        return None
+    }
+    let accesses: usize = fields.iter()
+        .map(|field| methods.iter().filter(|(_, usage)| usage.fields.contains(field)).count())
+        .sum();
+    let mean_accesses = accesses as f64 / (fields.len() as f64);
+    Some((m - mean_accesses) / (m - 1.0))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{compute_impl_method_cohesion, lcom4_groups, lcom_hs};
+
+    #[test]
+    fn test_lcom() {
+        let item_impl: syn::ItemImpl = parse_quote! {
+            impl Stats {
+                fn add(&mut self, x: usize) { self.values.push(x); }
+                fn mean(&self) -> f64 { self.sum() as f64 / self.values.len() as f64 }
+                fn sum(&self) -> usize { self.values.iter().sum() }
+                fn rename(&mut self, name: &str) { self.name = name.to_owned(); }
+            }
+        };
+        let methods: Vec<_> = item_impl.items.iter()
+            .filter_map(|item| match item {
+                syn::ImplItem::Method(method) => Some(
+                    (method.sig.ident.to_string(), compute_impl_method_cohesion(method))
+                ),
+                _ => None
+            })
+            .collect();
+
+        let groups = lcom4_groups(&methods);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.contains(&vec!["rename".to_owned()]));
+
+        let fields = vec!["values".to_owned(), "name".to_owned()];
+        // (4 - (3 + 1) / 2) / 3
+        assert!((lcom_hs(&methods, &fields).unwrap() - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
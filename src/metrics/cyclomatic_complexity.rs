@@ -1,14 +1,57 @@
 use syn;
 use syn::visit::{self, Visit};
 
+use crate::metrics::closures::{ClosureIndex, ExcludedClosures};
+use crate::metrics::macros::visit_macro_body;
+use crate::metrics::metric::{Aggregation, FnItem, Metric};
+use crate::options::Options;
+
+
+/// The decision points of an expression itself, its sub-expressions aside:
+/// `if`, `for`, `while`, a `match` of several arms and each guard, `&&`
+/// and `||`.
+pub fn decision_points(expr: &syn::Expr) -> usize {
+    match expr {
+        syn::Expr::If(_) | syn::Expr::ForLoop(_) | syn::Expr::While(_) => 1,
+        syn::Expr::Match(expr_match) => {
+            // Match pattern arm does not increases the cc, guard arm does.
+            let guards = expr_match.arms.iter().filter(|arm| arm.guard.is_some()).count();
+            (expr_match.arms.len() > 1) as usize + guards
+        }
+        syn::Expr::Binary(expr_binary) => match expr_binary.op {
+            syn::BinOp::Or(_) | syn::BinOp::And(_) => 1,
+            _ => 0,
+        },
+        _ => 0
+    }
+}
+
 
 struct CComplexityVisitor {
-    cc: usize
+    cc: usize,
+    expand_macros: bool,  // count the code inside macro invocations too
+    excluded: ExcludedClosures,  // the reported closures, left to themselves
+}
+
+
+impl CComplexityVisitor {
+
+    fn new(cc: usize, excluded: ExcludedClosures, options: &Options) -> Self {
+        CComplexityVisitor{cc, expand_macros: options.expand_macros, excluded}
+    }
 }
 
 
 impl<'ast> Visit<'ast> for CComplexityVisitor {
 
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        if self.excluded.contains(i) {
+            return
+        }
+        self.cc += decision_points(i);
+        visit::visit_expr(self, i);
+    }
+
     fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
         self.cc += 1;
         visit::visit_item_fn(self, node);
@@ -19,69 +62,116 @@ impl<'ast> Visit<'ast> for CComplexityVisitor {
         visit::visit_impl_item_method(self, i);
     }
 
-    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
-        self.cc += 1;
-        visit::visit_expr_if(self, i);
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if self.expand_macros {
+            visit_macro_body(self, i);
+        }
+        visit::visit_macro(self, i);
     }
 
-    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
-        self.cc += 1;
-        visit::visit_expr_for_loop(self, i);
-    }
+}
 
-    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
-        self.cc += 1;
-        visit::visit_expr_while(self, i);
-    }
 
-    fn visit_expr_match(&mut self, i: &'ast syn::ExprMatch) {
-        if i.arms.len() > 1 {
-            self.cc += 1;
-        }
-        visit::visit_expr_match(self, i);
+/// The closures of a function body its metrics leave out, none if the
+/// options count them in the function.
+pub fn excluded_closures(block: &syn::Block, options: &Options) -> ExcludedClosures {
+    match options.include_closures {
+        true => ExcludedClosures::default(),
+        false => ClosureIndex::new("", block).excluded().clone(),
     }
+}
 
-    fn visit_arm(&mut self, i: &'ast syn::Arm) {
-        // Match pattern arm does not increases the cc, guard arm does.
-        match i.guard {
-            Some(_) => self.cc += 1,
-            _ => {}
-        }
-        visit::visit_arm(self, i);
-    }
 
-    fn visit_bin_op(&mut self, i: &'ast syn::BinOp) {
-        match i {
-            syn::BinOp::Or(_) | syn::BinOp::And(_) => self.cc += 1,
-            _ => (),
-        }
-        visit::visit_bin_op(self, i);
-    }
+#[allow(dead_code)]
+pub fn compute_itemfn_cc(itemfn: &syn::ItemFn) -> usize {
+    compute_itemfn_cc_with(itemfn, &Options::default())
+}
+
 
+#[allow(dead_code)]
+pub fn compute_impl_method_cc(impl_method: &syn::ImplItemMethod) -> usize {
+    compute_impl_method_cc_with(impl_method, &Options::default())
 }
 
 
+/// CC of a function, with the code of its macro invocations as the options
+/// say, and without the decision points of its reported closures unless
+/// they are included.
 #[allow(dead_code)]
-pub fn compute_itemfn_cc(itemfn: &syn::ItemFn) -> usize {
-    let mut cc_visitor = CComplexityVisitor{cc: 0};
+pub fn compute_itemfn_cc_with(itemfn: &syn::ItemFn, options: &Options) -> usize {
+    let mut cc_visitor = CComplexityVisitor::new(0, excluded_closures(&itemfn.block, options), options);
     cc_visitor.visit_item_fn(itemfn);
     cc_visitor.cc
 }
 
 
 #[allow(dead_code)]
-pub fn compute_impl_method_cc(impl_method: &syn::ImplItemMethod) -> usize {
-    let mut cc_visitor = CComplexityVisitor{cc: 0};
+pub fn compute_impl_method_cc_with(impl_method: &syn::ImplItemMethod, options: &Options) -> usize {
+    let mut cc_visitor = CComplexityVisitor::new(0, excluded_closures(&impl_method.block, options), options);
     cc_visitor.visit_impl_item_method(impl_method);
     cc_visitor.cc
 }
 
 
+/// CC of a closure or async block of the index as a pseudo-function, the
+/// closures nested in it are left to themselves.
+#[allow(dead_code)]
+pub fn compute_closure_cc(expr: &syn::Expr, index: &ClosureIndex, options: &Options) -> usize {
+    let mut cc_visitor = CComplexityVisitor::new(1, index.excluded().clone(), options);
+    match expr {
+        syn::Expr::Closure(closure) => visit::visit_expr_closure(&mut cc_visitor, closure),
+        syn::Expr::Async(async_block) => visit::visit_expr_async(&mut cc_visitor, async_block),
+        _ => visit::visit_expr(&mut cc_visitor, expr),
+    }
+    cc_visitor.cc
+}
+
+
+/// Cyclomatic complexity as a `Metric`, the mean over the functions of a
+/// file. `happ cc` computes its functions with it too.
+#[derive(Debug, Clone, Default)]
+pub struct CcMetric {
+    options: Options,
+}
+
+
+impl CcMetric {
+
+    pub fn new(options: &Options) -> Self {
+        CcMetric{options: options.clone()}
+    }
+}
+
+
+impl Metric for CcMetric {
+
+    fn name(&self) -> &str {
+        "cc"
+    }
+
+    fn unit(&self) -> &str {
+        "paths"
+    }
+
+    fn compute_fn(&self, func: FnItem) -> f64 {
+        match func {
+            FnItem::Fn(item_fn) => compute_itemfn_cc_with(item_fn, &self.options) as f64,
+            FnItem::Method(method) => compute_impl_method_cc_with(method, &self.options) as f64,
+        }
+    }
+
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Mean
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
 
     use syn::parse_quote;
-    use super::compute_itemfn_cc;
+    use crate::options::Options;
+    use super::{compute_itemfn_cc, compute_itemfn_cc_with};
 
     #[test]
     fn test_if_expr() {
@@ -187,5 +277,34 @@ mod tests {
 
         assert_eq!(compute_itemfn_cc(&fn_body), 3);
     }
-}
 
+    #[test]
+    fn test_expand_macros() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_cc(v: &[i32]) {  // +1
+                println!("{}", if v.is_empty() { 0 } else { v[0] });  // +1 expanded
+                let w = vec![v.iter().filter(|x| **x > 0 && **x < 10).count()];  // +1 expanded
+            }
+        };
+
+        assert_eq!(compute_itemfn_cc(&fn_body), 1);
+        let options = Options{expand_macros: true, ..Options::default()};
+        assert_eq!(compute_itemfn_cc_with(&fn_body, &options), 3);
+    }
+
+    #[test]
+    fn test_closures() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_cc(v: &[i32]) {  // +1
+                if v.is_empty() {  // +1
+                    return;
+                }
+                let n = v.iter().filter(|x| **x > 0 && **x < 10).count();  // +1 in closure
+            }
+        };
+
+        assert_eq!(compute_itemfn_cc(&fn_body), 2);
+        let options = Options{include_closures: true, ..Options::default()};
+        assert_eq!(compute_itemfn_cc_with(&fn_body, &options), 3);
+    }
+}
@@ -0,0 +1,91 @@
+/// Text of the doc comments (`///`, `//!` and `#[doc = ".."]`) of an
+/// item, one line per attribute, `None` when there is none.
+pub fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs.iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(lit), .. })) => Some(lit.value()),
+            _ => None
+        })
+        .collect();
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return None
+    }
+    Some(lines.join("\n"))
+}
+
+
+/// Whether a doc comment has a heading like `# Examples`, at any level.
+pub fn has_doc_section(doc: &str, section: &str) -> bool {
+    doc.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('#'))
+        .any(|line| line.trim_start_matches('#').trim() == section)
+}
+
+
+/// Code blocks of a doc comment rustdoc runs as doc-tests, the fenced
+/// ones without a language or marked `rust`, `should_panic`, `no_run`, ...
+/// Blocks marked `ignore` or with another language are not counted.
+pub fn count_doc_tests(doc: &str) -> usize {
+    let mut in_block = false;
+    let mut count = 0;
+    for line in doc.lines().map(str::trim) {
+        if !line.starts_with("```") {
+            continue;
+        }
+        if in_block {
+            in_block = false;
+            continue;
+        }
+        in_block = true;
+        let is_rust = line.trim_start_matches('`')
+            .split([',', ' '])
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .all(|tag| matches!(
+                tag, "rust" | "should_panic" | "no_run" | "compile_fail" | "test_harness"
+            ) || tag.starts_with("edition"));
+        if is_rust {
+            count += 1;
+        }
+    }
+    count
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{doc_comment, has_doc_section, count_doc_tests};
+
+    #[test]
+    fn test_doc_sections() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            /// Reads a value behind a raw pointer.
+            ///
+            /// # Safety
+            ///
+            /// `ptr` has to be valid.
+            pub unsafe fn read(ptr: *const u8) -> u8 {
+                *ptr
+            }
+        };
+        let doc = doc_comment(&item_fn.attrs).unwrap();
+        assert!(has_doc_section(&doc, "Safety"));
+        assert!(!has_doc_section(&doc, "Examples"));
+
+        let item_fn: syn::ItemFn = parse_quote! {
+            #[inline]
+            pub fn undocumented() {}
+        };
+        assert!(doc_comment(&item_fn.attrs).is_none());
+    }
+
+    #[test]
+    fn test_count_doc_tests() {
+        let doc = "Example\n```\nlet x = 1;\n```\n```text\noutput\n```\n```no_run\nrun();\n```\n";
+        assert_eq!(count_doc_tests(doc), 2);
+    }
+}
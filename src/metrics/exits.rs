@@ -0,0 +1,340 @@
+use syn::visit::{self, Visit};
+
+
+/// Macros which never return to the caller.
+pub const DIVERGING_MACROS: [&str; 4] = ["panic", "unreachable", "todo", "unimplemented"];
+
+
+/// Exit points of a function. `return`s and `?`s inside closures, async
+/// blocks and nested items leave those, not the function, so they are
+/// not counted.
+#[derive(Debug, Default, Clone)]
+pub struct ExitVisitor {
+    pub returns: usize,
+    pub tries: usize,
+    pub tails: usize,
+    pub panics: usize,
+}
+
+
+impl ExitVisitor {
+
+    /// NEXITS, the number of ways the function can exit.
+    pub fn nexits(&self) -> usize {
+        self.returns + self.tries + self.tails + self.panics
+    }
+
+}
+
+
+pub fn is_diverging_macro(mac: &syn::Macro) -> bool {
+    match mac.path.segments.last() {
+        Some(segment) => DIVERGING_MACROS.iter().any(|name| segment.ident == name),
+        None => false
+    }
+}
+
+
+impl<'ast> Visit<'ast> for ExitVisitor {
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        match i {
+            syn::Expr::Return(_) => self.returns += 1,
+            syn::Expr::Try(_) => self.tries += 1,
+            syn::Expr::Closure(_) | syn::Expr::Async(_) => return,
+            _ => ()
+        }
+        visit::visit_expr(self, i);
+    }
+
+    fn visit_item(&mut self, i: &'ast syn::Item) {
+        // Nested fns, impls and mods have their own exit points,
+        // only brace-style statement macros like `panic!{..}` are visited
+        if let syn::Item::Macro(item_macro) = i {
+            visit::visit_item_macro(self, item_macro);
+        }
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if is_diverging_macro(i) {
+            self.panics += 1;
+        }
+        visit::visit_macro(self, i);
+    }
+
+}
+
+
+/// Whether a function returns a value through its tail expression, not for
+/// `()` and `!`.
+fn returns_value(sig: &syn::Signature) -> bool {
+    match &sig.output {
+        syn::ReturnType::Default => false,
+        syn::ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Never(_) => false,
+            syn::Type::Tuple(tuple) => !tuple.elems.is_empty(),
+            _ => true
+        }
+    }
+}
+
+
+/// Finds the `break`s leaving a `loop`, not those of the inner loops.
+#[derive(Default)]
+struct BreakFinder {
+    found: bool,
+}
+
+
+impl<'ast> Visit<'ast> for BreakFinder {
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        match i {
+            syn::Expr::Break(_) => self.found = true,
+            syn::Expr::Loop(_) | syn::Expr::ForLoop(_) | syn::Expr::While(_) => {
+                visit::visit_expr(&mut LabeledBreakFinder{finder: self}, i);
+                return
+            }
+            syn::Expr::Closure(_) | syn::Expr::Async(_) => return,
+            _ => ()
+        }
+        visit::visit_expr(self, i);
+    }
+
+    fn visit_item(&mut self, _i: &'ast syn::Item) {}
+
+}
+
+
+/// Inside an inner loop only the labeled `break`s may leave the outer one.
+struct LabeledBreakFinder<'a> {
+    finder: &'a mut BreakFinder,
+}
+
+
+impl<'a, 'ast> Visit<'ast> for LabeledBreakFinder<'a> {
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        match i {
+            syn::Expr::Break(expr_break) if expr_break.label.is_some() => self.finder.found = true,
+            syn::Expr::Closure(_) | syn::Expr::Async(_) => return,
+            _ => ()
+        }
+        visit::visit_expr(self, i);
+    }
+
+    fn visit_item(&mut self, _i: &'ast syn::Item) {}
+
+}
+
+
+/// Whether an expression never completes: it returns, panics, or all its
+/// branches do.
+fn is_diverging(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Return(_) | syn::Expr::Break(_) | syn::Expr::Continue(_) => true,
+        syn::Expr::Macro(expr_macro) => is_diverging_macro(&expr_macro.mac),
+        syn::Expr::If(expr_if) => match &expr_if.else_branch {
+            Some((_, else_branch)) => is_diverging_block(&expr_if.then_branch) && is_diverging(else_branch),
+            None => false
+        },
+        syn::Expr::Match(expr_match) => {
+            !expr_match.arms.is_empty() && expr_match.arms.iter().all(|arm| is_diverging(&arm.body))
+        }
+        syn::Expr::Block(expr_block) => is_diverging_block(&expr_block.block),
+        syn::Expr::Unsafe(expr_unsafe) => is_diverging_block(&expr_unsafe.block),
+        syn::Expr::Loop(expr_loop) => {
+            let mut finder = BreakFinder::default();
+            finder.visit_block(&expr_loop.body);
+            !finder.found
+        }
+        syn::Expr::Paren(expr_paren) => is_diverging(&expr_paren.expr),
+        _ => false
+    }
+}
+
+
+fn is_diverging_block(block: &syn::Block) -> bool {
+    match block.stmts.last() {
+        Some(syn::Stmt::Expr(expr)) | Some(syn::Stmt::Semi(expr, _)) => is_diverging(expr),
+        _ => false
+    }
+}
+
+
+/// The tail expression of a function returning a value is an exit, unless
+/// it never completes, like a `match` whose arms all panic. The tail of a
+/// unit function is not counted, whether it is `a()`, `a();` or a loop.
+fn has_tail(sig: &syn::Signature, block: &syn::Block) -> bool {
+    match block.stmts.last() {
+        Some(syn::Stmt::Expr(expr)) => returns_value(sig) && !is_diverging(expr),
+        _ => false
+    }
+}
+
+
+fn compute_block_exits(sig: &syn::Signature, block: &syn::Block) -> ExitVisitor {
+    let mut exit_visitor = ExitVisitor::default();
+    exit_visitor.visit_block(block);
+    if has_tail(sig, block) {
+        exit_visitor.tails += 1;
+    }
+    exit_visitor
+}
+
+
+#[allow(dead_code)]
+pub fn compute_itemfn_exits(itemfn: &syn::ItemFn) -> ExitVisitor {
+    compute_block_exits(&itemfn.sig, &itemfn.block)
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_exits(impl_method: &syn::ImplItemMethod) -> ExitVisitor {
+    compute_block_exits(&impl_method.sig, &impl_method.block)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::compute_itemfn_exits;
+
+    #[test]
+    fn test_no_exit() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_exits() {
+                println!("hello");
+            }
+        };
+
+        assert_eq!(compute_itemfn_exits(&fn_body).nexits(), 0);
+    }
+
+    #[test]
+    fn test_return_try_tail() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_exits(s: &str) -> Result<usize, Error> {
+                if s.is_empty() {
+                    return Ok(0);
+                }
+                let n = s.parse::<usize>()?;
+                Ok(n)
+            }
+        };
+
+        let exits = compute_itemfn_exits(&fn_body);
+        assert_eq!(exits.returns, 1);
+        assert_eq!(exits.tries, 1);
+        assert_eq!(exits.tails, 1);
+        assert_eq!(exits.nexits(), 3);
+    }
+
+    #[test]
+    fn test_diverging_macros() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_exits(i: usize) -> usize {
+                match i {
+                    0 => panic!("zero"),
+                    1 => unreachable!(),
+                    _ => todo!()
+                }
+            }
+        };
+
+        // the match never completes, its arms are the exits
+        let exits = compute_itemfn_exits(&fn_body);
+        assert_eq!(exits.panics, 3);
+        assert_eq!(exits.tails, 0);
+    }
+
+    #[test]
+    fn test_unit_fn_tail() {
+        let ends_with_call: syn::ItemFn = parse_quote! {
+            fn test_exits() {
+                a();
+            }
+        };
+        let ends_with_loop: syn::ItemFn = parse_quote! {
+            fn test_exits(v: Vec<usize>) {
+                for x in v {
+                    a(x);
+                }
+            }
+        };
+        let ends_with_if: syn::ItemFn = parse_quote! {
+            fn test_exits(x: bool) -> () {
+                if x {
+                    a()
+                }
+            }
+        };
+
+        assert_eq!(compute_itemfn_exits(&ends_with_call).tails, 0);
+        assert_eq!(compute_itemfn_exits(&ends_with_loop).tails, 0);
+        assert_eq!(compute_itemfn_exits(&ends_with_if).tails, 0);
+    }
+
+    #[test]
+    fn test_value_tail() {
+        let partly_diverging: syn::ItemFn = parse_quote! {
+            fn test_exits(i: usize) -> usize {
+                match i {
+                    0 => panic!("zero"),
+                    _ => i - 1,
+                }
+            }
+        };
+        let breaking_loop: syn::ItemFn = parse_quote! {
+            fn test_exits(mut i: usize) -> usize {
+                loop {
+                    for _ in 0..i {
+                        break;
+                    }
+                    if i > 10 {
+                        break i;
+                    }
+                    i += 1;
+                }
+            }
+        };
+        let endless_loop: syn::ItemFn = parse_quote! {
+            fn test_exits(mut i: usize) -> usize {
+                loop {
+                    while i < 10 {
+                        break;
+                    }
+                    if i > 10 {
+                        return i;
+                    }
+                    i += 1;
+                }
+            }
+        };
+
+        let exits = compute_itemfn_exits(&partly_diverging);
+        assert_eq!(exits.tails, 1);
+        assert_eq!(exits.panics, 1);
+        assert_eq!(compute_itemfn_exits(&breaking_loop).tails, 1);
+        let exits = compute_itemfn_exits(&endless_loop);
+        assert_eq!(exits.tails, 0);
+        assert_eq!(exits.returns, 1);
+    }
+
+    #[test]
+    fn test_closure_exits_not_counted() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_exits(v: Vec<&str>) {
+                let parsed: Vec<usize> = v.iter().map(|s| {
+                    if s.is_empty() {
+                        return 0;
+                    }
+                    s.parse().unwrap()
+                }).collect();
+            }
+        };
+
+        assert_eq!(compute_itemfn_exits(&fn_body).nexits(), 0);
+    }
+}
@@ -7,6 +7,12 @@ use syn::__private::quote::__private::ext::RepToTokensExt;
 use syn::spanned::Spanned;
 use syn::visit::{self, Visit};
 
+use crate::metrics::closures::{ClosureIndex, ExcludedClosures};
+use crate::metrics::cyclomatic_complexity::excluded_closures;
+use crate::metrics::macros::visit_macro_body;
+use crate::metrics::metric::{Aggregation, FnItem, Metric};
+use crate::options::Options;
+
 
 #[derive(Default, Clone)]
 pub struct LocVisitor {
@@ -17,6 +23,8 @@ pub struct LocVisitor {
     pub lloc: usize,  // Logic Lines Of Code
     // pub cloc: usize,  // Comment Lines Of Code TODO
     // pub blank: usize  // blank lines TODO
+    pub expand_macros: bool,  // count the logic lines inside macro invocations
+    pub excluded: ExcludedClosures,  // the reported closures, left to themselves
 }
 
 
@@ -63,34 +71,64 @@ impl<'ast> Visit<'ast> for LocVisitor {
     }
 
     fn visit_expr(&mut self, i: &'ast syn::Expr) {
-        match i {
-            syn::Expr::Assign(_) | syn::Expr::AssignOp(_) 
-            | syn::Expr::Continue(_) | syn::Expr::Break(_) 
-            | syn::Expr::ForLoop(_) | syn::Expr::Loop(_)
-            | syn::Expr::If(_) | syn::Expr::While(_)
-            | syn::Expr::Await(_) | syn::Expr::Let(_) 
-            | syn::Expr::Return(_) | syn::Expr::Yield(_)
-            | syn::Expr::Unsafe(_) | syn::Expr::Match(_)
-            | syn::Expr::Try(_) | syn::Expr::Repeat(_)
-            | syn::Expr::Struct(_) | syn::Expr::Field(_)
-            => {
-                self.lloc += 1;
-            }
-            syn::Expr::Call(_) | syn::Expr::MethodCall(_)
-            | syn::Expr::Macro(_)
-            => {
-                // TODO
-                // `let x = foo();` or `foo();`
-                self.lloc += 1;
+        if self.excluded.contains(i) {
+            // the line opening the closure belongs to the enclosing code
+            let span = i.span();
+            for line in span.start().line + 1..=span.end().line {
+                self.code_lines.remove(&line);
             }
-            _ => ()
+            return
+        }
+        // `let x = foo();` or `foo();`
+        if classify_expr(i).is_some() {
+            self.lloc += 1;
         }
         visit::visit_expr(self, i);
     }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if self.expand_macros {
+            visit_macro_body(self, i);
+        }
+        visit::visit_macro(self, i);
+    }
     
 }
 
 
+/// Expressions making a logical line, also the assignments, branches and
+/// conditions of the ABC metric.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExprClass {
+    Assignment,  // `a = b` and `a += b`
+    Call,  // function calls, method calls and macros
+    Condition,  // `if`, `while` and `?`
+    Statement,  // the other control flow and compound expressions
+}
+
+
+pub fn classify_expr(expr: &syn::Expr) -> Option<ExprClass> {
+    match expr {
+        syn::Expr::Assign(_) | syn::Expr::AssignOp(_) => Some(ExprClass::Assignment),
+        syn::Expr::Call(_) | syn::Expr::MethodCall(_)
+        | syn::Expr::Macro(_)
+        => Some(ExprClass::Call),
+        syn::Expr::If(_) | syn::Expr::While(_)
+        | syn::Expr::Try(_)
+        => Some(ExprClass::Condition),
+        syn::Expr::Continue(_) | syn::Expr::Break(_) 
+        | syn::Expr::ForLoop(_) | syn::Expr::Loop(_)
+        | syn::Expr::Await(_) | syn::Expr::Let(_) 
+        | syn::Expr::Return(_) | syn::Expr::Yield(_)
+        | syn::Expr::Unsafe(_) | syn::Expr::Match(_)
+        | syn::Expr::Repeat(_)
+        | syn::Expr::Struct(_) | syn::Expr::Field(_)
+        => Some(ExprClass::Statement),
+        _ => None
+    }
+}
+
+
 fn parse_token_tree(node: &TokenTree, loc_visitor: &mut LocVisitor) {
     match node {
         TokenTree::Group(group) => {
@@ -142,24 +180,143 @@ pub fn compute_file_loc(ast_file: &syn::File) -> LocVisitor {
 
 #[allow(dead_code)]
 pub fn compute_itemfn_loc(itemfn: &syn::ItemFn) -> LocVisitor {
-    let tokens = itemfn.to_token_stream();
+    compute_itemfn_loc_with(itemfn, &Options::default())
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_loc(impl_method: &syn::ImplItemMethod) -> LocVisitor {
+    compute_impl_method_loc_with(impl_method, &Options::default())
+}
+
+
+fn loc_visitor_with(tokens: TokenStream, excluded: ExcludedClosures, options: &Options) -> LocVisitor {
     let mut loc_visitor = parse_loc_from_token_stream(tokens);
+    loc_visitor.expand_macros = options.expand_macros;
+    loc_visitor.excluded = excluded;
     loc_visitor.lloc += 1;
+    loc_visitor
+}
+
+
+/// LOC of a function, with the logic lines of its macro invocations as the
+/// options say, and without the lines of its reported closures unless they
+/// are included.
+#[allow(dead_code)]
+pub fn compute_itemfn_loc_with(itemfn: &syn::ItemFn, options: &Options) -> LocVisitor {
+    let excluded = excluded_closures(&itemfn.block, options);
+    let mut loc_visitor = loc_visitor_with(itemfn.to_token_stream(), excluded, options);
     loc_visitor.visit_item_fn(itemfn);
     loc_visitor
 }
 
 
 #[allow(dead_code)]
-pub fn compute_impl_method_loc(impl_method: &syn::ImplItemMethod) -> LocVisitor {
-    let tokens = impl_method.to_token_stream();
-    let mut loc_visitor = parse_loc_from_token_stream(tokens);
-    loc_visitor.lloc += 1;
+pub fn compute_impl_method_loc_with(impl_method: &syn::ImplItemMethod, options: &Options) -> LocVisitor {
+    let excluded = excluded_closures(&impl_method.block, options);
+    let mut loc_visitor = loc_visitor_with(impl_method.to_token_stream(), excluded, options);
     loc_visitor.visit_impl_item_method(impl_method);
     loc_visitor
 }
 
 
+/// LOC of a closure or async block of the index as a pseudo-function, the
+/// closures nested in it are left to themselves.
+#[allow(dead_code)]
+pub fn compute_closure_loc(expr: &syn::Expr, index: &ClosureIndex, options: &Options) -> LocVisitor {
+    let mut loc_visitor = loc_visitor_with(expr.to_token_stream(), index.excluded().clone(), options);
+    match expr {
+        syn::Expr::Closure(closure) => visit::visit_expr_closure(&mut loc_visitor, closure),
+        syn::Expr::Async(async_block) => visit::visit_expr_async(&mut loc_visitor, async_block),
+        _ => visit::visit_expr(&mut loc_visitor, expr),
+    }
+    loc_visitor
+}
+
+
+/// Physical lines of code as a `Metric`, a file counts all its lines and
+/// not only those of its functions. `happ loc` computes its functions with
+/// it too.
+#[derive(Debug, Clone, Default)]
+pub struct LocMetric {
+    options: Options,
+}
+
+
+impl LocMetric {
+
+    pub fn new(options: &Options) -> Self {
+        LocMetric{options: options.clone()}
+    }
+}
+
+
+impl Metric for LocMetric {
+
+    fn name(&self) -> &str {
+        "loc"
+    }
+
+    fn unit(&self) -> &str {
+        "lines"
+    }
+
+    fn compute_fn(&self, func: FnItem) -> f64 {
+        match func {
+            FnItem::Fn(item_fn) => compute_itemfn_loc_with(item_fn, &self.options).ploc() as f64,
+            FnItem::Method(method) => compute_impl_method_loc_with(method, &self.options).ploc() as f64,
+        }
+    }
+
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Sum
+    }
+
+    fn compute_file(&self, ast: &syn::File) -> f64 {
+        compute_file_loc(ast).ploc() as f64
+    }
+}
+
+
+/// Logic lines of code as a `Metric`, summed over the functions of a file.
+/// Unlike the physical lines they change with `--expand-macros`.
+#[derive(Debug, Clone, Default)]
+pub struct LlocMetric {
+    options: Options,
+}
+
+
+impl LlocMetric {
+
+    pub fn new(options: &Options) -> Self {
+        LlocMetric{options: options.clone()}
+    }
+}
+
+
+impl Metric for LlocMetric {
+
+    fn name(&self) -> &str {
+        "lloc"
+    }
+
+    fn unit(&self) -> &str {
+        "logic lines"
+    }
+
+    fn compute_fn(&self, func: FnItem) -> f64 {
+        match func {
+            FnItem::Fn(item_fn) => compute_itemfn_loc_with(item_fn, &self.options).lloc as f64,
+            FnItem::Method(method) => compute_impl_method_loc_with(method, &self.options).lloc as f64,
+        }
+    }
+
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Sum
+    }
+}
+
+
 #[cfg(test)]
 mod loctests {
 
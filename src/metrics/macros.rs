@@ -0,0 +1,171 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use syn::punctuated::Punctuated;
+use syn::visit::Visit;
+
+
+/// Size of a `macro_rules!` definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroRulesMetrics {
+    pub name: String,
+    pub arms: usize,
+    pub max_repetition_depth: usize,  // `$( .. )*` inside `$( .. )*`
+    pub tokens: usize,
+}
+
+
+fn count_tokens(tokens: TokenStream) -> usize {
+    tokens.into_iter()
+        .map(|token| match token {
+            TokenTree::Group(group) => count_tokens(group.stream()),
+            _ => 1
+        })
+        .sum()
+}
+
+
+fn repetition_depth(tokens: TokenStream) -> usize {
+    let mut max_depth = 0;
+    let mut after_dollar = false;
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let depth = repetition_depth(group.stream());
+                let is_repetition = after_dollar && group.delimiter() == Delimiter::Parenthesis;
+                max_depth = max_depth.max(depth + is_repetition as usize);
+                after_dollar = false;
+            }
+            TokenTree::Punct(punct) => after_dollar = punct.as_char() == '$',
+            _ => after_dollar = false,
+        }
+    }
+    max_depth
+}
+
+
+/// Metrics of a `macro_rules! name { (matcher) => { transcriber }; .. }`
+/// item, `None` for other macro invocations.
+pub fn compute_macro_rules(item_macro: &syn::ItemMacro) -> Option<MacroRulesMetrics> {
+    if !item_macro.mac.path.is_ident("macro_rules") {
+        return None
+    }
+    let name = item_macro.ident.as_ref()?.to_string();
+    let tokens: Vec<TokenTree> = item_macro.mac.tokens.clone().into_iter().collect();
+    // an arm is a group followed by `=>`
+    let arms = tokens.windows(2)
+        .filter(|pair| match pair {
+            [TokenTree::Group(_), TokenTree::Punct(punct)] => punct.as_char() == '=',
+            _ => false
+        })
+        .count();
+    Some(MacroRulesMetrics{
+        name,
+        arms,
+        max_repetition_depth: repetition_depth(item_macro.mac.tokens.clone()),
+        tokens: count_tokens(item_macro.mac.tokens.clone()),
+    })
+}
+
+
+/// Body of a macro invocation parsed as Rust code.
+pub enum MacroBody {
+    Exprs(Vec<syn::Expr>),  // `vec![..]`, `println!(..)`, `assert!(..)`
+    Stmts(Vec<syn::Stmt>),
+    Items(Vec<syn::Item>),  // `lazy_static! { .. }`, `thread_local! { .. }`
+}
+
+
+/// Drops the `ref` of `static ref NAME: T = ..;` as in `lazy_static!`.
+fn strip_static_ref(tokens: TokenStream) -> TokenStream {
+    let mut stripped = vec![];
+    let mut after_static = false;
+    for token in tokens {
+        let is_ref = matches!(&token, TokenTree::Ident(ident) if ident == "ref");
+        if !(after_static && is_ref) {
+            stripped.push(token.clone());
+        }
+        after_static = matches!(&token, TokenTree::Ident(ident) if ident == "static");
+    }
+    stripped.into_iter().collect()
+}
+
+
+/// Parses the body of a macro invocation that looks like comma separated
+/// expressions, statements or items. `macro_rules!` definitions and bodies
+/// in a custom syntax give `None`.
+pub fn parse_macro_body(mac: &syn::Macro) -> Option<MacroBody> {
+    if mac.path.is_ident("macro_rules") || mac.tokens.is_empty() {
+        return None
+    }
+    let exprs = Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated;
+    if let Ok(exprs) = mac.parse_body_with(exprs) {
+        return Some(MacroBody::Exprs(exprs.into_iter().collect()))
+    }
+    if let Ok(stmts) = mac.parse_body_with(syn::Block::parse_within) {
+        return Some(MacroBody::Stmts(stmts))
+    }
+    let items = |tokens: TokenStream| syn::parse2::<syn::File>(tokens).map(|file| file.items);
+    match items(mac.tokens.clone()).or_else(|_| items(strip_static_ref(mac.tokens.clone()))) {
+        Ok(items) => Some(MacroBody::Items(items)),
+        Err(_) => None
+    }
+}
+
+
+/// Visits the parsed body of a macro invocation, for the visitors counting
+/// the code inside macros too.
+pub fn visit_macro_body<V>(visitor: &mut V, mac: &syn::Macro)
+where
+    V: for<'ast> Visit<'ast>
+{
+    match parse_macro_body(mac) {
+        Some(MacroBody::Exprs(exprs)) => exprs.iter().for_each(|expr| visitor.visit_expr(expr)),
+        Some(MacroBody::Stmts(stmts)) => stmts.iter().for_each(|stmt| visitor.visit_stmt(stmt)),
+        Some(MacroBody::Items(items)) => items.iter().for_each(|item| visitor.visit_item(item)),
+        None => ()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{compute_macro_rules, parse_macro_body, MacroBody};
+
+    #[test]
+    fn test_macro_rules() {
+        let item_macro: syn::ItemMacro = parse_quote! {
+            macro_rules! hashmap {
+                () => { HashMap::new() };
+                ($($key:expr => $value:expr),* $(,)?) => {{
+                    let mut map = HashMap::new();
+                    $( map.insert($key, $value); )*
+                    map
+                }};
+            }
+        };
+
+        let metrics = compute_macro_rules(&item_macro).unwrap();
+        assert_eq!(metrics.name, "hashmap");
+        assert_eq!(metrics.arms, 2);
+        assert_eq!(metrics.max_repetition_depth, 1);
+    }
+
+    #[test]
+    fn test_parse_macro_body() {
+        let expr: syn::ExprMacro = parse_quote! { println!("{} {}", a.len(), if b { 1 } else { 2 }) };
+        assert!(matches!(parse_macro_body(&expr.mac), Some(MacroBody::Exprs(exprs)) if exprs.len() == 3));
+
+        let item_macro: syn::ItemMacro = parse_quote! {
+            lazy_static! {
+                static ref NAMES: Vec<String> = load_names();
+            }
+        };
+        assert!(matches!(parse_macro_body(&item_macro.mac), Some(MacroBody::Items(items)) if items.len() == 1));
+
+        let item_macro: syn::ItemMacro = parse_quote! {
+            macro_rules! noop { () => {} }
+        };
+        assert!(parse_macro_body(&item_macro.mac).is_none());
+    }
+}
@@ -0,0 +1,384 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::metrics::{CcMetric, LlocMetric, LocMetric};
+use crate::options::Options;
+use crate::stats::{Measured, Stats};
+use crate::utils::{is_cfg_test, is_test_fn, parse_rs_files};
+
+
+/// A function the metrics are computed on, a bare `fn` or an impl method.
+#[derive(Debug, Clone, Copy)]
+pub enum FnItem<'a> {
+    Fn(&'a syn::ItemFn),
+    Method(&'a syn::ImplItemMethod),
+}
+
+
+impl<'a> FnItem<'a> {
+
+    pub fn sig(&self) -> &'a syn::Signature {
+        match self {
+            FnItem::Fn(item_fn) => &item_fn.sig,
+            FnItem::Method(method) => &method.sig,
+        }
+    }
+
+    pub fn block(&self) -> &'a syn::Block {
+        match self {
+            FnItem::Fn(item_fn) => &item_fn.block,
+            FnItem::Method(method) => &method.block,
+        }
+    }
+
+    pub fn attrs(&self) -> &'a [syn::Attribute] {
+        match self {
+            FnItem::Fn(item_fn) => &item_fn.attrs,
+            FnItem::Method(method) => &method.attrs,
+        }
+    }
+}
+
+
+/// A function of a file, with the name it is reported under.
+#[derive(Debug, Clone)]
+pub struct FileFunction<'a> {
+    pub name: String,  // `function`, `Type::method`, `module::Type::method`
+    pub item: FnItem<'a>,
+    pub is_test: bool,  // a `#[test]` fn, or in a `#[cfg(test)]` module or impl
+}
+
+
+fn collect_functions<'a>(items: &'a [syn::Item], prefix: &str, in_test: bool, functions: &mut Vec<FileFunction<'a>>) {
+    for item in items {
+        match item {
+            syn::Item::Impl(item_impl) => {
+                if let syn::Type::Path(syn::TypePath { qself: None, path }) = &*item_impl.self_ty {
+                    let in_test = in_test || is_cfg_test(&item_impl.attrs);
+                    for impl_item in &item_impl.items {
+                        if let syn::ImplItem::Method(method) = impl_item {
+                            let name = format!("{}{}::{}", prefix, path.segments.last().unwrap().ident, method.sig.ident);
+                            let is_test = in_test || is_test_fn(&method.attrs);
+                            functions.push(FileFunction{name, item: FnItem::Method(method), is_test});
+                        }
+                    }
+                }
+            }
+            // A bare function like `fn function(arg: Arg) -> Result { .. }`
+            syn::Item::Fn(item_fn) => {
+                let name = format!("{}{}", prefix, item_fn.sig.ident);
+                let is_test = in_test || is_test_fn(&item_fn.attrs) || is_cfg_test(&item_fn.attrs);
+                functions.push(FileFunction{name, item: FnItem::Fn(item_fn), is_test});
+            }
+            // An inline module like `#[cfg(test)] mod tests { .. }`
+            syn::Item::Mod(item_mod) => {
+                if let Some((_, items)) = &item_mod.content {
+                    let prefix = format!("{}{}::", prefix, item_mod.ident);
+                    collect_functions(items, &prefix, in_test || is_cfg_test(&item_mod.attrs), functions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+
+/// The functions and impl methods of a file, those of its inline modules
+/// included, in the order of the source. The tools all go through it.
+pub fn file_functions(ast: &syn::File) -> Vec<FileFunction<'_>> {
+    let mut functions = vec![];
+    collect_functions(&ast.items, "", false, &mut functions);
+    functions
+}
+
+
+/// How the values of the functions of a file, or of the files of a crate,
+/// add up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Sum,
+    Mean,
+    Max,
+}
+
+
+impl Aggregation {
+
+    pub fn aggregate(&self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0
+        }
+        match self {
+            Aggregation::Sum => values.iter().sum(),
+            Aggregation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregation::Max => values.iter().cloned().fold(f64::MIN, f64::max),
+        }
+    }
+}
+
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Aggregation::Sum => "sum",
+            Aggregation::Mean => "mean",
+            Aggregation::Max => "max",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+/// A code metric, computed per function and per file.
+///
+/// ```ignore
+/// struct Params;
+///
+/// impl Metric for Params {
+///     fn name(&self) -> &str { "params" }
+///     fn unit(&self) -> &str { "arguments" }
+///     fn compute_fn(&self, func: FnItem) -> f64 { func.sig().inputs.len() as f64 }
+/// }
+///
+/// let mut registry = MetricRegistry::builtin(&Options::default());
+/// registry.register(Box::new(Params));
+/// ```
+pub trait Metric {
+
+    /// Short name, as given to `happ metrics --metric`.
+    fn name(&self) -> &str;
+
+    fn unit(&self) -> &str;
+
+    fn compute_fn(&self, func: FnItem) -> f64;
+
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Mean
+    }
+
+    /// Value of a whole file, by default the aggregation of its functions
+    /// which are not test code.
+    fn compute_file(&self, ast: &syn::File) -> f64 {
+        let values: Vec<f64> = file_functions(ast).into_iter()
+            .filter(|func| !func.is_test)
+            .map(|func| self.compute_fn(func.item))
+            .collect();
+        self.aggregation().aggregate(&values)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub name: String,  // the function, or the file for file values
+    pub file: String,
+    pub value: f64,
+}
+
+
+impl Measured for Measurement {
+    fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value.fract() == 0.0 {
+            true => write!(f, "{}, {}", self.name, self.value),
+            false => write!(f, "{}, {:.2}", self.name, self.value),
+        }
+    }
+}
+
+
+/// The values of one metric over a set of files.
+#[derive(Debug, Clone)]
+pub struct MetricSummary {
+    pub metric: String,
+    pub unit: String,
+    pub aggregation: Aggregation,
+    pub functions: Stats<Measurement>,
+    pub files: Stats<Measurement>,
+}
+
+
+impl MetricSummary {
+
+    /// The value of all files: the sum of the file values for a sum, the
+    /// mean or max of all functions otherwise.
+    pub fn total(&self) -> f64 {
+        match self.aggregation {
+            Aggregation::Sum => self.files.sum(),
+            Aggregation::Mean => self.functions.mean(),
+            Aggregation::Max => self.functions.iter().map(|m| m.value).fold(0.0, f64::max),
+        }
+    }
+}
+
+
+/// The metrics `happ metrics` computes. Downstream crates add theirs with
+/// `register` and run them with `measure_path`.
+#[derive(Default)]
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn Metric>>,
+}
+
+
+impl MetricRegistry {
+
+    pub fn new() -> Self {
+        MetricRegistry::default()
+    }
+
+    /// The registry with the metrics of happ itself, computed with the
+    /// options.
+    pub fn builtin(options: &Options) -> Self {
+        let mut registry = MetricRegistry::new();
+        registry.register(Box::new(CcMetric::new(options)));
+        registry.register(Box::new(LocMetric::new(options)));
+        registry.register(Box::new(LlocMetric::new(options)));
+        registry
+    }
+
+    /// Adds a metric, replacing the one with the same name if any.
+    pub fn register(&mut self, metric: Box<dyn Metric>) {
+        self.metrics.retain(|m| m.name() != metric.name());
+        self.metrics.push(metric);
+    }
+
+    /// Keeps only the metrics for which `keep` holds.
+    pub fn retain(&mut self, keep: impl Fn(&dyn Metric) -> bool) {
+        self.metrics.retain(|m| keep(m.as_ref()));
+    }
+
+    /// Keeps only the named metrics, an error if one is not registered.
+    pub fn select(&mut self, names: &[String]) -> Result<(), String> {
+        if let Some(name) = names.iter().find(|name| self.get(name).is_none()) {
+            return Err(format!("Unknown metric {}, known metrics: {}", name, self.names().join(", ")))
+        }
+        self.retain(|metric| names.iter().any(|name| name == metric.name()));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Metric> {
+        self.metrics.iter().find(|m| m.name() == name).map(|m| m.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.metrics.iter().map(|m| m.name()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Metric> {
+        self.metrics.iter().map(|m| m.as_ref())
+    }
+
+    /// Computes every metric over the parsed files, given with their paths.
+    /// Test code is left out.
+    pub fn measure(&self, files: &[(String, syn::File)]) -> Vec<MetricSummary> {
+        self.iter()
+            .map(|metric| {
+                let mut summary = MetricSummary{
+                    metric: metric.name().to_owned(),
+                    unit: metric.unit().to_owned(),
+                    aggregation: metric.aggregation(),
+                    functions: Stats::new(),
+                    files: Stats::new(),
+                };
+                for (file, ast) in files {
+                    for func in file_functions(ast).into_iter().filter(|func| !func.is_test) {
+                        let value = metric.compute_fn(func.item);
+                        summary.functions.add(Measurement{name: func.name, file: file.clone(), value});
+                    }
+                    let value = metric.compute_file(ast);
+                    summary.files.add(Measurement{name: file.clone(), file: file.clone(), value});
+                }
+                summary
+            })
+            .collect()
+    }
+
+    /// Computes every metric over the `.rs` files under a path, skipping the
+    /// files which do not parse.
+    pub fn measure_path(&self, path: &Path, options: &Options) -> Vec<MetricSummary> {
+        self.measure(&parse_rs_files(&[path], options))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use crate::options::Options;
+    use super::{file_functions, Aggregation, FnItem, Metric, MetricRegistry};
+
+    struct Params;
+
+    impl Metric for Params {
+        fn name(&self) -> &str { "params" }
+        fn unit(&self) -> &str { "arguments" }
+        fn compute_fn(&self, func: FnItem) -> f64 { func.sig().inputs.len() as f64 }
+        fn aggregation(&self) -> Aggregation { Aggregation::Max }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = MetricRegistry::builtin(&Options::default());
+        registry.register(Box::new(Params));
+        assert_eq!(registry.names(), vec!["cc", "loc", "lloc", "params"]);
+
+        let ast: syn::File = parse_quote! {
+            fn one(a: usize) -> usize {
+                if a > 0 { a } else { 0 }
+            }
+
+            impl Point {
+                fn dist(&self, other: &Point, norm: Norm) -> f64 {
+                    0.0
+                }
+            }
+        };
+        let summaries = registry.measure(&[("point.rs".to_owned(), ast)]);
+
+        let cc = &summaries[0];
+        assert_eq!(cc.functions.len(), 2);
+        assert_eq!(cc.functions.iter().nth(1).unwrap().name, "Point::dist");
+        assert_eq!(cc.total(), 1.5);
+        let params = &summaries[3];
+        assert_eq!(params.files.iter().next().unwrap().value, 3.0);
+
+        assert!(registry.select(&["cc".to_owned(), "size".to_owned()]).is_err());
+        registry.select(&["cc".to_owned(), "params".to_owned()]).unwrap();
+        assert_eq!(registry.names(), vec!["cc", "params"]);
+    }
+
+    #[test]
+    fn test_file_functions() {
+        let ast: syn::File = parse_quote! {
+            fn run() {}
+
+            mod imp {
+                impl Point {
+                    fn norm(&self) -> f64 { 0.0 }
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                #[test]
+                fn test_run() {}
+            }
+        };
+
+        let functions: Vec<(String, bool)> = file_functions(&ast).into_iter()
+            .map(|func| (func.name, func.is_test))
+            .collect();
+        assert_eq!(functions, vec![
+            ("run".to_owned(), false),
+            ("imp::Point::norm".to_owned(), false),
+            ("tests::test_run".to_owned(), true),
+        ]);
+    }
+}
@@ -1,6 +1,48 @@
 
+pub mod metric;
+pub use metric::*;
+
 pub mod cyclomatic_complexity;
 pub use cyclomatic_complexity::*;
 
 pub mod loc;
-pub use loc::*;
\ No newline at end of file
+pub use loc::*;
+
+pub mod nesting;
+pub use nesting::*;
+
+pub mod signature;
+pub use signature::*;
+
+pub mod exits;
+pub use exits::*;
+
+pub mod unsafe_code;
+pub use unsafe_code::*;
+
+pub mod panics;
+pub use panics::*;
+
+pub mod calls;
+pub use calls::*;
+
+pub mod types;
+pub use types::*;
+
+pub mod cohesion;
+pub use cohesion::*;
+
+pub mod docs;
+pub use docs::*;
+
+pub mod abc;
+pub use abc::*;
+
+pub mod macros;
+pub use macros::*;
+
+pub mod closures;
+pub use closures::*;
+
+pub mod async_code;
+pub use async_code::*;
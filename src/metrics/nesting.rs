@@ -0,0 +1,299 @@
+use syn::visit::{self, Visit};
+
+use crate::metrics::closures::{ClosureIndex, ExcludedClosures};
+use crate::metrics::cyclomatic_complexity::excluded_closures;
+use crate::metrics::metric::FnItem;
+use crate::options::Options;
+
+
+/// Block nesting depth of a function.
+///
+/// The function body itself is depth 0, every `if`/`match`/loop body,
+/// closure, async block, unsafe block or bare block adds one level.
+/// An `else if` chain stays at the depth of the first `if`.
+#[derive(Debug, Default, Clone)]
+pub struct NestingVisitor {
+    depth: usize,
+    pub max_depth: usize,
+    depth_sum: usize,
+    stmt_count: usize,
+    excluded: ExcludedClosures,  // the reported closures, left to themselves
+}
+
+
+impl NestingVisitor {
+
+    fn new(excluded: ExcludedClosures) -> Self {
+        NestingVisitor{excluded, ..NestingVisitor::default()}
+    }
+
+    /// Average nesting depth over all statements of the function.
+    pub fn avg_depth(&self) -> f64 {
+        if self.stmt_count == 0 {
+            return 0.0
+        }
+        self.depth_sum as f64 / self.stmt_count as f64
+    }
+
+    fn enter(&mut self) {
+        self.depth += 1;
+        if self.depth > self.max_depth {
+            self.max_depth = self.depth;
+        }
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn visit_nested_block(&mut self, i: &syn::Block) {
+        self.enter();
+        self.visit_block(i);
+        self.leave();
+    }
+
+    fn visit_nested_expr(&mut self, i: &syn::Expr) {
+        self.enter();
+        self.visit_expr(i);
+        self.leave();
+    }
+}
+
+
+impl<'ast> Visit<'ast> for NestingVisitor {
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        if self.excluded.contains(i) {
+            return
+        }
+        visit::visit_expr(self, i);
+    }
+
+    fn visit_stmt(&mut self, i: &'ast syn::Stmt) {
+        self.depth_sum += self.depth;
+        self.stmt_count += 1;
+        visit::visit_stmt(self, i);
+    }
+
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        self.visit_expr(&i.cond);
+        self.visit_nested_block(&i.then_branch);
+        if let Some((_, else_branch)) = &i.else_branch {
+            match &**else_branch {
+                syn::Expr::Block(block) => self.visit_nested_block(&block.block),
+                // `else if` does not open a new level
+                _ => self.visit_expr(else_branch),
+            }
+        }
+    }
+
+    fn visit_expr_match(&mut self, i: &'ast syn::ExprMatch) {
+        self.visit_expr(&i.expr);
+        self.enter();
+        for arm in &i.arms {
+            self.visit_arm(arm);
+        }
+        self.leave();
+    }
+
+    fn visit_arm(&mut self, i: &'ast syn::Arm) {
+        if let Some((_, guard)) = &i.guard {
+            self.visit_expr(guard);
+        }
+        match &*i.body {
+            // `pat => { .. }` is already nested by the match itself
+            syn::Expr::Block(body) => self.visit_block(&body.block),
+            body => self.visit_expr(body),
+        }
+    }
+
+    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+        self.visit_expr(&i.expr);
+        self.visit_nested_block(&i.body);
+    }
+
+    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+        self.visit_expr(&i.cond);
+        self.visit_nested_block(&i.body);
+    }
+
+    fn visit_expr_loop(&mut self, i: &'ast syn::ExprLoop) {
+        self.visit_nested_block(&i.body);
+    }
+
+    fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+        match &*i.body {
+            syn::Expr::Block(body) => self.visit_nested_block(&body.block),
+            body => self.visit_nested_expr(body),
+        }
+    }
+
+    fn visit_expr_async(&mut self, i: &'ast syn::ExprAsync) {
+        self.visit_nested_block(&i.block);
+    }
+
+    fn visit_expr_unsafe(&mut self, i: &'ast syn::ExprUnsafe) {
+        self.visit_nested_block(&i.block);
+    }
+
+    fn visit_expr_block(&mut self, i: &'ast syn::ExprBlock) {
+        self.visit_nested_block(&i.block);
+    }
+
+}
+
+
+#[allow(dead_code)]
+pub fn compute_itemfn_nesting(itemfn: &syn::ItemFn) -> NestingVisitor {
+    compute_itemfn_nesting_with(itemfn, &Options::default())
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_nesting(impl_method: &syn::ImplItemMethod) -> NestingVisitor {
+    compute_impl_method_nesting_with(impl_method, &Options::default())
+}
+
+
+/// Nesting of a function, without the blocks of its reported closures and
+/// async blocks unless the options include them.
+#[allow(dead_code)]
+pub fn compute_itemfn_nesting_with(itemfn: &syn::ItemFn, options: &Options) -> NestingVisitor {
+    let mut nesting_visitor = NestingVisitor::new(excluded_closures(&itemfn.block, options));
+    nesting_visitor.visit_block(&itemfn.block);
+    nesting_visitor
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_nesting_with(impl_method: &syn::ImplItemMethod, options: &Options) -> NestingVisitor {
+    let mut nesting_visitor = NestingVisitor::new(excluded_closures(&impl_method.block, options));
+    nesting_visitor.visit_block(&impl_method.block);
+    nesting_visitor
+}
+
+
+/// Nesting of a function or impl method of the shared item loop.
+pub fn compute_fn_nesting(func: FnItem, options: &Options) -> NestingVisitor {
+    let mut nesting_visitor = NestingVisitor::new(excluded_closures(func.block(), options));
+    nesting_visitor.visit_block(func.block());
+    nesting_visitor
+}
+
+
+/// Nesting of a closure or async block of the index as a pseudo-function,
+/// its body is depth 0 and the closures nested in it are left to themselves.
+#[allow(dead_code)]
+pub fn compute_closure_nesting(expr: &syn::Expr, index: &ClosureIndex) -> NestingVisitor {
+    let mut nesting_visitor = NestingVisitor::new(index.excluded().clone());
+    match expr {
+        syn::Expr::Closure(closure) => match &*closure.body {
+            syn::Expr::Block(body) => nesting_visitor.visit_block(&body.block),
+            body => nesting_visitor.visit_expr(body),
+        },
+        syn::Expr::Async(async_block) => nesting_visitor.visit_block(&async_block.block),
+        _ => nesting_visitor.visit_expr(expr),
+    }
+    nesting_visitor
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use crate::options::Options;
+    use super::{compute_itemfn_nesting, compute_itemfn_nesting_with};
+
+    #[test]
+    fn test_flat_fn() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_nesting() {
+                let i = 1;
+                println!("{}", i);
+            }
+        };
+
+        let nesting = compute_itemfn_nesting(&fn_body);
+        assert_eq!(nesting.max_depth, 0);
+        assert_eq!(nesting.avg_depth(), 0.0);
+    }
+
+    #[test]
+    fn test_nesting_if_loop_match() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_nesting() {
+                let i = 1;                      // 0
+                if i == 1 {
+                    for j in 0..i {             // 1
+                        match j {               // 2
+                            0 => {
+                                println!("0");  // 3
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+            }
+        };
+
+        let nesting = compute_itemfn_nesting(&fn_body);
+        assert_eq!(nesting.max_depth, 3);
+        assert!((nesting.avg_depth() - 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_nesting(i: usize) {
+                if i == 1 {
+                    println!("1");
+                } else if i == 2 {
+                    println!("2");
+                } else {
+                    println!("3");
+                }
+            }
+        };
+
+        assert_eq!(compute_itemfn_nesting(&fn_body).max_depth, 1);
+    }
+
+    #[test]
+    fn test_closure_and_async() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_nesting(v: Vec<usize>) {
+                v.iter().for_each(|x| {
+                    let fut = async move {
+                        if *x > 0 {
+                            println!("{}", x);
+                        }
+                    };
+                });
+            }
+        };
+
+        let options = Options{include_closures: true, ..Options::default()};
+        assert_eq!(compute_itemfn_nesting_with(&fn_body, &options).max_depth, 3);
+    }
+
+    #[test]
+    fn test_closures() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_nesting(v: Vec<usize>) {
+                for x in v {
+                    println!("{}", x);
+                }
+                v.iter().for_each(|x| {
+                    if *x > 0 {
+                        println!("{}", x);
+                    }
+                });
+            }
+        };
+
+        let options = Options{include_closures: true, ..Options::default()};
+        assert_eq!(compute_itemfn_nesting(&fn_body).max_depth, 1);
+        assert_eq!(compute_itemfn_nesting_with(&fn_body, &options).max_depth, 2);
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+
+/// Macros which panic, either always or when their assertion fails.
+pub const PANIC_MACROS: [&str; 10] = [
+    "panic", "unreachable", "todo", "unimplemented",
+    "assert", "assert_eq", "assert_ne",
+    "debug_assert", "debug_assert_eq", "debug_assert_ne",
+];
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PanicKind {
+    Unwrap,
+    Expect,
+    Macro,
+    Index,
+    // `/` and `%` whose operands are not float literals or casts, they
+    // panic on a zero divisor when the operands are integers
+    Division,
+}
+
+
+impl fmt::Display for PanicKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PanicKind::Unwrap => "unwrap",
+            PanicKind::Expect => "expect",
+            PanicKind::Macro => "macro",
+            PanicKind::Index => "index",
+            PanicKind::Division => "division",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct PanicSite {
+    pub kind: PanicKind,
+    pub line: usize,
+}
+
+
+#[derive(Debug, Default, Clone)]
+pub struct PanicVisitor {
+    pub sites: Vec<PanicSite>,
+}
+
+
+impl PanicVisitor {
+
+    pub fn count(&self, kind: PanicKind) -> usize {
+        self.sites.iter().filter(|s| s.kind == kind).count()
+    }
+
+    fn add_site(&mut self, kind: PanicKind, line: usize) {
+        self.sites.push(PanicSite{kind, line});
+    }
+}
+
+
+/// Whether an operand is a float, a float literal or a cast to `f32` or
+/// `f64`, parenthesized or not.
+fn is_float(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Float(_), .. }) => true,
+        syn::Expr::Cast(cast) => match &*cast.ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => path.is_ident("f32") || path.is_ident("f64"),
+            _ => false
+        },
+        syn::Expr::Paren(paren) => is_float(&paren.expr),
+        _ => false
+    }
+}
+
+
+impl<'ast> Visit<'ast> for PanicVisitor {
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        let line = i.method.span().start().line;
+        if i.method == "unwrap" && i.args.is_empty() {
+            self.add_site(PanicKind::Unwrap, line);
+        } else if i.method == "expect" && i.args.len() == 1 {
+            self.add_site(PanicKind::Expect, line);
+        }
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if let Some(segment) = i.path.segments.last() {
+            if PANIC_MACROS.iter().any(|name| segment.ident == name) {
+                self.add_site(PanicKind::Macro, segment.ident.span().start().line);
+            }
+        }
+        visit::visit_macro(self, i);
+    }
+
+    fn visit_expr_index(&mut self, i: &'ast syn::ExprIndex) {
+        self.add_site(PanicKind::Index, i.bracket_token.span.start().line);
+        visit::visit_expr_index(self, i);
+    }
+
+    fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+        if let syn::BinOp::Div(_) | syn::BinOp::Rem(_) = i.op {
+            if !is_float(&i.left) && !is_float(&i.right) {
+                self.add_site(PanicKind::Division, i.op.span().start().line);
+            }
+        }
+        visit::visit_expr_binary(self, i);
+    }
+
+    fn visit_expr_assign_op(&mut self, i: &'ast syn::ExprAssignOp) {
+        if let syn::BinOp::DivEq(_) | syn::BinOp::RemEq(_) = i.op {
+            if !is_float(&i.right) {
+                self.add_site(PanicKind::Division, i.op.span().start().line);
+            }
+        }
+        visit::visit_expr_assign_op(self, i);
+    }
+
+}
+
+
+#[allow(dead_code)]
+pub fn compute_itemfn_panics(itemfn: &syn::ItemFn) -> PanicVisitor {
+    let mut panic_visitor = PanicVisitor::default();
+    panic_visitor.visit_block(&itemfn.block);
+    panic_visitor
+}
+
+
+#[allow(dead_code)]
+pub fn compute_impl_method_panics(impl_method: &syn::ImplItemMethod) -> PanicVisitor {
+    let mut panic_visitor = PanicVisitor::default();
+    panic_visitor.visit_block(&impl_method.block);
+    panic_visitor
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{compute_itemfn_panics, PanicKind};
+
+    #[test]
+    fn test_unwrap_expect() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_panics(path: &str) {
+                let mut file = File::open(path).unwrap();
+                let mut content = String::new();
+                file.read_to_string(&mut content).expect("read failed");
+                let n = content.parse::<usize>().unwrap_or(0);
+            }
+        };
+
+        let panics = compute_itemfn_panics(&fn_body);
+        assert_eq!(panics.count(PanicKind::Unwrap), 1);
+        assert_eq!(panics.count(PanicKind::Expect), 1);
+        assert_eq!(panics.sites.len(), 2);
+    }
+
+    #[test]
+    fn test_macros_index_division() {
+        let fn_body: syn::ItemFn = parse_quote! {
+            fn test_panics(v: Vec<usize>, n: usize) -> f64 {
+                assert!(n > 0);
+                if v.is_empty() {
+                    unreachable!();
+                }
+                let first = v[0] / n;
+                let ratio = first as f64 / 2.0;
+                let mean = v.iter().sum::<usize>() as f64 / (v.len() as f64);
+                ratio / (n as f32) as f64 + mean
+            }
+        };
+
+        let panics = compute_itemfn_panics(&fn_body);
+        assert_eq!(panics.count(PanicKind::Macro), 2);
+        assert_eq!(panics.count(PanicKind::Index), 1);
+        assert_eq!(panics.count(PanicKind::Division), 1);
+    }
+}
@@ -0,0 +1,213 @@
+use syn::visit::{self, Visit};
+
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SignatureMetrics {
+    pub inputs: usize,  // including `self`
+    pub generic_types: usize,
+    pub lifetimes: usize,
+    pub where_predicates: usize,
+    pub impl_trait_args: usize,
+    pub max_arg_type_depth: usize,
+    pub return_type_depth: usize,
+}
+
+
+impl SignatureMetrics {
+
+    pub fn generics(&self) -> usize {
+        self.generic_types + self.lifetimes
+    }
+
+}
+
+
+struct ImplTraitVisitor {
+    found: bool
+}
+
+
+impl<'ast> Visit<'ast> for ImplTraitVisitor {
+
+    fn visit_type_impl_trait(&mut self, i: &'ast syn::TypeImplTrait) {
+        self.found = true;
+        visit::visit_type_impl_trait(self, i);
+    }
+
+}
+
+
+fn contains_impl_trait(ty: &syn::Type) -> bool {
+    let mut visitor = ImplTraitVisitor{found: false};
+    visitor.visit_type(ty);
+    visitor.found
+}
+
+
+fn path_args_depth(path: &syn::Path) -> usize {
+    let mut depth = 0;
+    for segment in &path.segments {
+        match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => {
+                for arg in &args.args {
+                    let arg_depth = match arg {
+                        syn::GenericArgument::Type(ty) => type_depth(ty),
+                        syn::GenericArgument::Binding(binding) => type_depth(&binding.ty),
+                        _ => 0
+                    };
+                    depth = depth.max(arg_depth);
+                }
+            }
+            syn::PathArguments::Parenthesized(args) => {
+                for input in &args.inputs {
+                    depth = depth.max(type_depth(input));
+                }
+                if let syn::ReturnType::Type(_, ty) = &args.output {
+                    depth = depth.max(type_depth(ty));
+                }
+            }
+            syn::PathArguments::None => {}
+        }
+    }
+    depth
+}
+
+
+fn bounds_depth<'a, I>(bounds: I) -> usize
+where
+    I: IntoIterator<Item = &'a syn::TypeParamBound>
+{
+    bounds.into_iter()
+        .map(|bound| match bound {
+            syn::TypeParamBound::Trait(trait_bound) => path_args_depth(&trait_bound.path),
+            syn::TypeParamBound::Lifetime(_) => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+
+/// Nesting depth of a type, every type constructor adds one level:
+/// `usize` is 1, `Vec<usize>` and `&usize` are 2, `()` is 0.
+pub fn type_depth(ty: &syn::Type) -> usize {
+    match ty {
+        syn::Type::Path(type_path) => 1 + path_args_depth(&type_path.path),
+        syn::Type::Reference(reference) => 1 + type_depth(&reference.elem),
+        syn::Type::Ptr(ptr) => 1 + type_depth(&ptr.elem),
+        syn::Type::Slice(slice) => 1 + type_depth(&slice.elem),
+        syn::Type::Array(array) => 1 + type_depth(&array.elem),
+        syn::Type::Tuple(tuple) => {
+            if tuple.elems.is_empty() {
+                return 0
+            }
+            1 + tuple.elems.iter().map(type_depth).max().unwrap_or(0)
+        }
+        syn::Type::Paren(paren) => type_depth(&paren.elem),
+        syn::Type::Group(group) => type_depth(&group.elem),
+        syn::Type::ImplTrait(impl_trait) => 1 + bounds_depth(&impl_trait.bounds),
+        syn::Type::TraitObject(trait_object) => 1 + bounds_depth(&trait_object.bounds),
+        syn::Type::BareFn(bare_fn) => {
+            let inputs_depth = bare_fn.inputs.iter()
+                .map(|arg| type_depth(&arg.ty))
+                .max()
+                .unwrap_or(0);
+            let output_depth = match &bare_fn.output {
+                syn::ReturnType::Type(_, ty) => type_depth(ty),
+                syn::ReturnType::Default => 0
+            };
+            1 + inputs_depth.max(output_depth)
+        }
+        _ => 1
+    }
+}
+
+
+pub fn compute_signature(sig: &syn::Signature) -> SignatureMetrics {
+    let mut metrics = SignatureMetrics{inputs: sig.inputs.len(), ..Default::default()};
+
+    for param in &sig.generics.params {
+        match param {
+            syn::GenericParam::Type(_) => metrics.generic_types += 1,
+            syn::GenericParam::Lifetime(_) => metrics.lifetimes += 1,
+            syn::GenericParam::Const(_) => {}
+        }
+    }
+
+    if let Some(where_clause) = &sig.generics.where_clause {
+        metrics.where_predicates = where_clause.predicates.len();
+    }
+
+    for input in &sig.inputs {
+        match input {
+            syn::FnArg::Typed(pat_type) => {
+                if contains_impl_trait(&pat_type.ty) {
+                    metrics.impl_trait_args += 1;
+                }
+                metrics.max_arg_type_depth = metrics.max_arg_type_depth
+                    .max(type_depth(&pat_type.ty));
+            }
+            // `self`, `&self` and `&mut self`, typed receivers like
+            // `self: Box<Self>` are parsed as `FnArg::Typed`
+            syn::FnArg::Receiver(_) => {}
+        }
+    }
+
+    if let syn::ReturnType::Type(_, ty) = &sig.output {
+        metrics.return_type_depth = type_depth(ty);
+    }
+
+    metrics
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::compute_signature;
+
+    #[test]
+    fn test_plain_signature() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn test_sig(a: usize, b: &str) {}
+        };
+
+        let metrics = compute_signature(&item_fn.sig);
+        assert_eq!(metrics.inputs, 2);
+        assert_eq!(metrics.generics(), 0);
+        assert_eq!(metrics.max_arg_type_depth, 2);
+        assert_eq!(metrics.return_type_depth, 0);
+    }
+
+    #[test]
+    fn test_method_signature() {
+        let method: syn::ImplItemMethod = parse_quote! {
+            fn test_sig(&mut self, k: usize) -> Option<&Vec<String>> {}
+        };
+
+        let metrics = compute_signature(&method.sig);
+        assert_eq!(metrics.inputs, 2);
+        assert_eq!(metrics.max_arg_type_depth, 1);
+        assert_eq!(metrics.return_type_depth, 4);
+    }
+
+    #[test]
+    fn test_generic_signature() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn test_sig<'a, T, U: Clone>(t: &'a T, f: impl Fn(U) -> Vec<T>) -> T
+            where
+                T: Default,
+                U: Send,
+            {}
+        };
+
+        let metrics = compute_signature(&item_fn.sig);
+        assert_eq!(metrics.inputs, 2);
+        assert_eq!(metrics.generic_types, 2);
+        assert_eq!(metrics.lifetimes, 1);
+        assert_eq!(metrics.where_predicates, 2);
+        assert_eq!(metrics.impl_trait_args, 1);
+        assert_eq!(metrics.max_arg_type_depth, 3);
+        assert_eq!(metrics.return_type_depth, 1);
+    }
+}
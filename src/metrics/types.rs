@@ -0,0 +1,148 @@
+use std::fmt;
+use syn::spanned::Spanned;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeKind {
+    Struct,
+    Enum,
+    Union,
+    Trait,
+}
+
+
+impl fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TypeKind::Struct => "struct",
+            TypeKind::Enum => "enum",
+            TypeKind::Union => "union",
+            TypeKind::Trait => "trait",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+/// Shape of a type definition. `members` are the fields of a struct or
+/// union, the variants of an enum and the items of a trait.
+#[derive(Debug, Clone)]
+pub struct TypeMetrics {
+    pub kind: TypeKind,
+    pub name: String,
+    pub members: usize,
+    pub generics: usize,
+    pub derives: usize,
+    pub lines: usize,
+}
+
+
+fn count_derives(attrs: &[syn::Attribute]) -> usize {
+    attrs.iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .map(|meta| match meta {
+            syn::Meta::List(list) => list.nested.len(),
+            _ => 0
+        })
+        .sum()
+}
+
+
+fn count_lines<T: Spanned>(node: &T) -> usize {
+    let span = node.span();
+    span.end().line - span.start().line + 1
+}
+
+
+pub fn compute_struct_metrics(item_struct: &syn::ItemStruct) -> TypeMetrics {
+    TypeMetrics{
+        kind: TypeKind::Struct,
+        name: item_struct.ident.to_string(),
+        members: item_struct.fields.len(),
+        generics: item_struct.generics.params.len(),
+        derives: count_derives(&item_struct.attrs),
+        lines: count_lines(item_struct),
+    }
+}
+
+
+pub fn compute_enum_metrics(item_enum: &syn::ItemEnum) -> TypeMetrics {
+    TypeMetrics{
+        kind: TypeKind::Enum,
+        name: item_enum.ident.to_string(),
+        members: item_enum.variants.len(),
+        generics: item_enum.generics.params.len(),
+        derives: count_derives(&item_enum.attrs),
+        lines: count_lines(item_enum),
+    }
+}
+
+
+pub fn compute_union_metrics(item_union: &syn::ItemUnion) -> TypeMetrics {
+    TypeMetrics{
+        kind: TypeKind::Union,
+        name: item_union.ident.to_string(),
+        members: item_union.fields.named.len(),
+        generics: item_union.generics.params.len(),
+        derives: count_derives(&item_union.attrs),
+        lines: count_lines(item_union),
+    }
+}
+
+
+pub fn compute_trait_metrics(item_trait: &syn::ItemTrait) -> TypeMetrics {
+    TypeMetrics{
+        kind: TypeKind::Trait,
+        name: item_trait.ident.to_string(),
+        members: item_trait.items.len(),
+        generics: item_trait.generics.params.len(),
+        derives: 0,
+        lines: count_lines(item_trait),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{compute_struct_metrics, compute_enum_metrics, compute_trait_metrics};
+
+    #[test]
+    fn test_struct_metrics() {
+        let item_struct: syn::ItemStruct = parse_quote! {
+            #[derive(Debug, Clone)]
+            #[derive(Default)]
+            pub struct CCStats<'a, T> {
+                functions: Vec<&'a T>,
+                is_sorted: bool
+            }
+        };
+
+        let metrics = compute_struct_metrics(&item_struct);
+        assert_eq!(metrics.name, "CCStats");
+        assert_eq!(metrics.members, 2);
+        assert_eq!(metrics.generics, 2);
+        assert_eq!(metrics.derives, 3);
+    }
+
+    #[test]
+    fn test_enum_trait_metrics() {
+        let item_enum: syn::ItemEnum = parse_quote! {
+            enum Call {
+                Path(Vec<String>),
+                Method(String, bool),
+            }
+        };
+        let item_trait: syn::ItemTrait = parse_quote! {
+            trait Measured {
+                fn value(&self) -> f64;
+                fn name(&self) -> &str;
+            }
+        };
+
+        assert_eq!(compute_enum_metrics(&item_enum).members, 2);
+        assert_eq!(compute_trait_metrics(&item_trait).members, 2);
+    }
+}
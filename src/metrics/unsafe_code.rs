@@ -0,0 +1,314 @@
+use std::fmt;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnsafeKind {
+    Block,
+    Fn,
+    Impl,
+    Trait,
+    ExternBlock,
+    StaticMut,
+    // Any `*expr` inside an unsafe context. Without type information we
+    // cannot tell raw pointers from references, so this over-approximates.
+    RawDeref,
+}
+
+
+impl fmt::Display for UnsafeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            UnsafeKind::Block => "unsafe block",
+            UnsafeKind::Fn => "unsafe fn",
+            UnsafeKind::Impl => "unsafe impl",
+            UnsafeKind::Trait => "unsafe trait",
+            UnsafeKind::ExternBlock => "extern block",
+            UnsafeKind::StaticMut => "static mut",
+            UnsafeKind::RawDeref => "deref in unsafe",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct UnsafeUsage {
+    pub kind: UnsafeKind,
+    pub line: usize,
+    pub func: Option<String>,
+    pub stmts: usize,
+    pub has_safety_comment: bool,
+}
+
+
+pub struct UnsafeVisitor<'a> {
+    lines: Vec<&'a str>,
+    prefix: String,  // `module::` of the enclosing inline modules
+    self_ty: Option<String>,
+    func: Option<String>,
+    // lines of the enclosing unsafe blocks and fns
+    unsafe_scopes: Vec<usize>,
+    pub usages: Vec<UnsafeUsage>,
+}
+
+
+impl<'a> UnsafeVisitor<'a> {
+
+    pub fn new(content: &'a str) -> Self {
+        UnsafeVisitor{
+            lines: content.lines().collect(),
+            prefix: String::new(),
+            self_ty: None,
+            func: None,
+            unsafe_scopes: Vec::new(),
+            usages: Vec::new(),
+        }
+    }
+
+    /// Looks for a `// SAFETY:` comment (or a `# Safety` doc section) on
+    /// the given line or in the comments and attributes right above it.
+    fn has_safety_comment(&self, line: usize) -> bool {
+        let is_safety = |s: &str| s.contains("SAFETY:") || s.contains("# Safety");
+        if line == 0 || line > self.lines.len() {
+            return false
+        }
+        if let Some(pos) = self.lines[line - 1].find("//") {
+            if is_safety(&self.lines[line - 1][pos..]) {
+                return true
+            }
+        }
+        for prev in self.lines[..line - 1].iter().rev() {
+            let prev = prev.trim_start();
+            if !(prev.starts_with("//") || prev.starts_with("/*")
+                || prev.starts_with('*') || prev.starts_with("#[")) {
+                break
+            }
+            if is_safety(prev) {
+                return true
+            }
+        }
+        false
+    }
+
+    fn add_usage(&mut self, kind: UnsafeKind, line: usize, stmts: usize) {
+        let anchor = match kind {
+            UnsafeKind::RawDeref => *self.unsafe_scopes.last().unwrap_or(&line),
+            _ => line
+        };
+        let has_safety_comment = self.has_safety_comment(anchor);
+        self.usages.push(UnsafeUsage{
+            kind, line, func: self.func.clone(), stmts, has_safety_comment
+        });
+    }
+
+    fn visit_fn_body(&mut self, name: String, sig: &syn::Signature, block: &syn::Block) {
+        let outer = self.func.replace(name);
+        match &sig.unsafety {
+            Some(unsafety) => {
+                let line = unsafety.span.start().line;
+                self.add_usage(UnsafeKind::Fn, line, block.stmts.len());
+                self.unsafe_scopes.push(line);
+                self.visit_block(block);
+                self.unsafe_scopes.pop();
+            }
+            None => {
+                // a safe fn nested in an unsafe block is not unsafe context
+                let scopes = std::mem::take(&mut self.unsafe_scopes);
+                self.visit_block(block);
+                self.unsafe_scopes = scopes;
+            }
+        }
+        self.func = outer;
+    }
+}
+
+
+impl<'ast, 'a> Visit<'ast> for UnsafeVisitor<'a> {
+
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        self.visit_fn_body(format!("{}{}", self.prefix, i.sig.ident), &i.sig, &i.block);
+    }
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        let outer = self.prefix.clone();
+        self.prefix = format!("{}{}::", outer, i.ident);
+        visit::visit_item_mod(self, i);
+        self.prefix = outer;
+    }
+
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        if let Some(unsafety) = &i.unsafety {
+            self.add_usage(UnsafeKind::Impl, unsafety.span.start().line, i.items.len());
+        }
+        let self_ty = match &*i.self_ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                path.segments.last().map(|s| s.ident.to_string())
+            }
+            _ => None
+        };
+        let outer = std::mem::replace(&mut self.self_ty, self_ty);
+        visit::visit_item_impl(self, i);
+        self.self_ty = outer;
+    }
+
+    fn visit_impl_item_method(&mut self, i: &'ast syn::ImplItemMethod) {
+        let name = match &self.self_ty {
+            Some(self_ty) => format!("{}{}::{}", self.prefix, self_ty, i.sig.ident),
+            None => format!("{}{}", self.prefix, i.sig.ident)
+        };
+        self.visit_fn_body(name, &i.sig, &i.block);
+    }
+
+    fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
+        if let Some(unsafety) = &i.unsafety {
+            self.add_usage(UnsafeKind::Trait, unsafety.span.start().line, i.items.len());
+        }
+        let outer = self.self_ty.replace(i.ident.to_string());
+        visit::visit_item_trait(self, i);
+        self.self_ty = outer;
+    }
+
+    fn visit_trait_item_method(&mut self, i: &'ast syn::TraitItemMethod) {
+        let name = match &self.self_ty {
+            Some(self_ty) => format!("{}{}::{}", self.prefix, self_ty, i.sig.ident),
+            None => format!("{}{}", self.prefix, i.sig.ident)
+        };
+        match &i.default {
+            Some(block) => self.visit_fn_body(name, &i.sig, block),
+            None => {
+                // required `unsafe fn` declaration without a body
+                if let Some(unsafety) = &i.sig.unsafety {
+                    let outer = self.func.replace(name);
+                    self.add_usage(UnsafeKind::Fn, unsafety.span.start().line, 0);
+                    self.func = outer;
+                }
+            }
+        }
+    }
+
+    fn visit_item_foreign_mod(&mut self, i: &'ast syn::ItemForeignMod) {
+        self.add_usage(UnsafeKind::ExternBlock, i.abi.extern_token.span.start().line, i.items.len());
+        visit::visit_item_foreign_mod(self, i);
+    }
+
+    fn visit_item_static(&mut self, i: &'ast syn::ItemStatic) {
+        if i.mutability.is_some() {
+            self.add_usage(UnsafeKind::StaticMut, i.static_token.span.start().line, 0);
+        }
+        visit::visit_item_static(self, i);
+    }
+
+    fn visit_expr_unsafe(&mut self, i: &'ast syn::ExprUnsafe) {
+        let line = i.unsafe_token.span.start().line;
+        self.add_usage(UnsafeKind::Block, line, i.block.stmts.len());
+        self.unsafe_scopes.push(line);
+        visit::visit_expr_unsafe(self, i);
+        self.unsafe_scopes.pop();
+    }
+
+    fn visit_expr_unary(&mut self, i: &'ast syn::ExprUnary) {
+        if let syn::UnOp::Deref(star) = i.op {
+            if !self.unsafe_scopes.is_empty() {
+                self.add_usage(UnsafeKind::RawDeref, star.span().start().line, 0);
+            }
+        }
+        visit::visit_expr_unary(self, i);
+    }
+
+}
+
+
+pub fn compute_file_unsafe(ast_file: &syn::File, content: &str) -> Vec<UnsafeUsage> {
+    let mut unsafe_visitor = UnsafeVisitor::new(content);
+    unsafe_visitor.visit_file(ast_file);
+    unsafe_visitor.usages
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{compute_file_unsafe, UnsafeKind};
+
+    fn usages_of(content: &str) -> Vec<super::UnsafeUsage> {
+        let ast = syn::parse_file(content).unwrap();
+        compute_file_unsafe(&ast, content)
+    }
+
+    #[test]
+    fn test_unsafe_block() {
+        let content = "
+fn read(p: *const u8) -> u8 {
+    let a = 1;
+    // SAFETY: the caller guarantees `p` is valid
+    let v = unsafe { *p };
+    v
+}
+";
+        let usages = usages_of(content);
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].kind, UnsafeKind::Block);
+        assert_eq!(usages[0].line, 5);
+        assert_eq!(usages[0].func.as_deref(), Some("read"));
+        assert_eq!(usages[0].stmts, 1);
+        assert!(usages[0].has_safety_comment);
+        assert_eq!(usages[1].kind, UnsafeKind::RawDeref);
+        assert!(usages[1].has_safety_comment);
+    }
+
+    #[test]
+    fn test_unsafe_items() {
+        let content = "
+static mut COUNTER: usize = 0;
+
+extern \"C\" {
+    fn abs(input: i32) -> i32;
+}
+
+unsafe trait Zeroable {}
+
+unsafe impl Zeroable for Foo {}
+
+impl Foo {
+    /// # Safety
+    /// `self` must be initialized
+    unsafe fn get(&self) -> usize {
+        COUNTER
+    }
+}
+";
+        let kinds: Vec<UnsafeKind> = usages_of(content).iter().map(|u| u.kind).collect();
+        assert_eq!(kinds, vec![
+            UnsafeKind::StaticMut,
+            UnsafeKind::ExternBlock,
+            UnsafeKind::Trait,
+            UnsafeKind::Impl,
+            UnsafeKind::Fn,
+        ]);
+
+        let usages = usages_of(content);
+        assert!(!usages[2].has_safety_comment);
+        assert_eq!(usages[4].func.as_deref(), Some("Foo::get"));
+        assert!(usages[4].has_safety_comment);
+    }
+
+    #[test]
+    fn test_unsafe_inline_mod() {
+        let content = "
+mod ffi {
+    impl Buf {
+        fn first(&self) -> u8 {
+            // SAFETY: a `Buf` is never empty
+            unsafe { *self.ptr }
+        }
+    }
+}
+";
+        let usages = usages_of(content);
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].func.as_deref(), Some("ffi::Buf::first"));
+        assert!(usages[0].has_safety_comment);
+    }
+}
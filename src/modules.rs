@@ -0,0 +1,462 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::visit::{self, Visit};
+
+use crate::graph::Graph;
+use crate::options::Options;
+use crate::utils::{find_crate_name, is_cfg_test, parse_rs_file};
+
+
+/// A module of a crate, either a file or an inline `mod name { .. }`.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,  // full path like `happ::metrics::loc`
+    pub file: PathBuf,
+    pub items: Vec<syn::Item>,
+    pub is_test: bool,  // declared under `#[cfg(test)]`
+    pub attrs: Vec<syn::Attribute>,  // inner attributes, or the ones of an inline `mod`
+}
+
+
+impl Module {
+
+    pub fn parent_name(&self) -> Option<&str> {
+        self.name.rfind("::").map(|pos| &self.name[..pos])
+    }
+
+}
+
+
+/// The modules of one or more crates, found by following `mod` declarations
+/// from the crate roots.
+#[derive(Debug, Default)]
+pub struct ModuleTree {
+    pub modules: Vec<Module>,
+    crates: Vec<String>,
+    index: BTreeMap<String, usize>,
+    options: Options,  // the cfg the files are pruned with
+}
+
+
+fn crate_ident(fpath: &Path) -> String {
+    match find_crate_name(fpath).as_str() {
+        "<unknown>" => "crate".to_owned(),
+        name => name.replace('-', "_"),
+    }
+}
+
+
+/// Crate roots under `path` and their names: `src/lib.rs` is named after the
+/// package, `src/main.rs` too unless there is also a library, then it is
+/// `main`, and `src/bin/*.rs` are named after their file.
+pub fn find_crate_roots(path: &Path) -> Vec<(String, PathBuf)> {
+    if path.is_file() {
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some("lib") | Some("main") => crate_ident(path),
+            Some(stem) => stem.to_owned(),
+            None => "crate".to_owned(),
+        };
+        return vec![(name, path.to_owned())];
+    }
+
+    let src_dir = if path.join("src").is_dir() { path.join("src") } else { path.to_owned() };
+    let lib = src_dir.join("lib.rs");
+    let main = src_dir.join("main.rs");
+    let mut roots = vec![];
+    if lib.is_file() {
+        roots.push((crate_ident(&lib), lib.clone()));
+    }
+    if main.is_file() {
+        let name = if lib.is_file() { "main".to_owned() } else { crate_ident(&main) };
+        roots.push((name, main));
+    }
+    if let Ok(entries) = fs::read_dir(src_dir.join("bin")) {
+        let mut bins: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|s| s == "rs").unwrap_or(false))
+            .collect();
+        bins.sort();
+        for bin in bins {
+            let name = bin.file_stem().unwrap().to_string_lossy().replace('-', "_");
+            roots.push((name, bin));
+        }
+    }
+    roots
+}
+
+
+/// Integration test crates under the `tests/` directory of the crate at
+/// `path`, `tests/x.rs` and `tests/x/main.rs` are named `x`.
+pub fn find_test_roots(path: &Path) -> Vec<(String, PathBuf)> {
+    let crate_dir = match path.file_name().and_then(|s| s.to_str()) {
+        _ if path.is_file() => return vec![],
+        Some("src") => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        _ => path.to_owned(),
+    };
+    let mut roots: Vec<(String, PathBuf)> = match fs::read_dir(crate_dir.join("tests")) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter_map(|p| {
+                let root = if p.is_dir() { p.join("main.rs") } else { p.clone() };
+                match root.extension() {
+                    Some(ext) if ext == "rs" && root.is_file() => {
+                        let name = p.file_stem().unwrap().to_string_lossy().replace('-', "_");
+                        Some((name, root))
+                    }
+                    _ => None
+                }
+            })
+            .collect(),
+        Err(_) => vec![]
+    };
+    roots.sort();
+    roots
+}
+
+
+fn path_attr(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter()
+        .filter(|attr| attr.path.is_ident("path"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .find_map(|meta| match meta {
+            syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. }) => Some(s.value()),
+            _ => None
+        })
+}
+
+
+impl ModuleTree {
+
+    pub fn new(options: &Options) -> Self {
+        ModuleTree{options: options.clone(), ..ModuleTree::default()}
+    }
+
+    pub fn from_path(path: &Path, options: &Options) -> Self {
+        let mut tree = ModuleTree::new(options);
+        for (crate_name, root) in find_crate_roots(path) {
+            tree.load_crate(&crate_name, &root);
+        }
+        tree
+    }
+
+    pub fn load_crate(&mut self, crate_name: &str, root: &Path) {
+        self.crates.push(crate_name.to_owned());
+        let child_dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.load_file(crate_name.to_owned(), root, child_dir, false);
+    }
+
+    /// Loads the integration tests of the crate at `path`, all of their
+    /// modules are test modules.
+    pub fn load_test_crates(&mut self, path: &Path) {
+        for (crate_name, root) in find_test_roots(path) {
+            self.crates.push(crate_name.clone());
+            let child_dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+            self.load_file(crate_name, &root, child_dir, true);
+        }
+    }
+
+    pub fn crates(&self) -> &[String] {
+        &self.crates
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Module> {
+        self.index.get(name).map(|&i| &self.modules[i])
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    fn load_file(&mut self, name: String, file: &Path, child_dir: PathBuf, is_test: bool) {
+        let content = match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Skip module {}: {}: {}", name, file.display(), e);
+                return;
+            }
+        };
+        match parse_rs_file(&content, &self.options) {
+            Ok(ast) => self.load_items(name, file, ast.attrs, ast.items, child_dir, is_test),
+            Err(e) => eprintln!("Skip module {}: {}: {}", name, file.display(), e),
+        }
+    }
+
+    fn load_items(
+        &mut self, name: String, file: &Path, attrs: Vec<syn::Attribute>,
+        items: Vec<syn::Item>, child_dir: PathBuf, is_test: bool
+    ) {
+        let idx = self.modules.len();
+        self.index.insert(name.clone(), idx);
+        self.modules.push(Module{name: name.clone(), file: file.to_owned(), items: vec![], is_test, attrs});
+
+        for item in &items {
+            let item_mod = match item {
+                syn::Item::Mod(item_mod) => item_mod,
+                _ => continue
+            };
+            let ident = item_mod.ident.to_string();
+            let child_name = format!("{}::{}", name, ident);
+            let child_is_test = is_test || is_cfg_test(&item_mod.attrs);
+            match &item_mod.content {
+                Some((_, child_items)) => {
+                    self.load_items(
+                        child_name, file, item_mod.attrs.clone(), child_items.clone(),
+                        child_dir.join(&ident), child_is_test
+                    );
+                }
+                None => {
+                    let candidates = match path_attr(&item_mod.attrs) {
+                        Some(path) => {
+                            vec![file.parent().map(|d| d.join(&path)).unwrap_or_else(|| PathBuf::from(&path))]
+                        }
+                        None => vec![
+                            child_dir.join(format!("{}.rs", ident)),
+                            child_dir.join(&ident).join("mod.rs"),
+                        ]
+                    };
+                    match candidates.into_iter().find(|p| p.is_file()) {
+                        Some(child_file) => {
+                            let grandchild_dir = if child_file.ends_with("mod.rs") {
+                                child_file.parent().unwrap().to_owned()
+                            } else {
+                                child_file.with_extension("")
+                            };
+                            self.load_file(child_name, &child_file, grandchild_dir, child_is_test);
+                        }
+                        None => eprintln!("Skip module {}: file not found", child_name),
+                    }
+                }
+            }
+        }
+
+        self.modules[idx].items = items;
+    }
+
+    fn crate_of<'a>(&self, module: &'a str) -> &'a str {
+        module.split("::").next().unwrap_or(module)
+    }
+
+    /// Resolves a path used inside `module` (`crate::a::b`, `super::c`,
+    /// `other_crate::d` or a path relative to `module`) to the innermost
+    /// module of the tree it points into.
+    pub fn resolve_path(&self, module: &str, segments: &[String]) -> Option<String> {
+        let (base, rest) = match segments.first()?.as_str() {
+            "crate" => (self.crate_of(module).to_owned(), &segments[1..]),
+            "self" => (module.to_owned(), &segments[1..]),
+            "super" => {
+                let mut base = module.to_owned();
+                let mut n = 0;
+                while segments.get(n).map(|s| s == "super").unwrap_or(false) {
+                    base = base[..base.rfind("::")?].to_owned();
+                    n += 1;
+                }
+                (base, &segments[n..])
+            }
+            first => {
+                if self.contains(&format!("{}::{}", module, first)) {
+                    (module.to_owned(), segments)
+                } else if self.crates.iter().any(|c| c == first) {
+                    (first.to_owned(), &segments[1..])
+                } else {
+                    return None
+                }
+            }
+        };
+        Some(self.descend(base, rest))
+    }
+
+    fn descend(&self, mut base: String, segments: &[String]) -> String {
+        for segment in segments {
+            let child = format!("{}::{}", base, segment);
+            if !self.contains(&child) {
+                break;
+            }
+            base = child;
+        }
+        base
+    }
+
+    /// Modules of the tree `module` refers to through `use` declarations
+    /// and qualified paths, not including itself.
+    pub fn dependencies(&self, module: &Module) -> BTreeSet<String> {
+        let mut collector = PathCollector::default();
+        for item in &module.items {
+            collector.visit_item(item);
+        }
+
+        let mut deps = BTreeSet::new();
+        let mut aliases: BTreeMap<String, String> = BTreeMap::new();
+        for (path, alias) in &collector.uses {
+            if let Some(target) = self.resolve_path(&module.name, path) {
+                if let Some(alias) = alias {
+                    aliases.insert(alias.clone(), target.clone());
+                }
+                deps.insert(target);
+            }
+        }
+        for path in &collector.paths {
+            let target = match aliases.get(&path[0]) {
+                Some(alias_target) => Some(self.descend(alias_target.clone(), &path[1..])),
+                None => self.resolve_path(&module.name, path)
+            };
+            if let Some(target) = target {
+                deps.insert(target);
+            }
+        }
+        deps.remove(&module.name);
+        deps
+    }
+
+    pub fn dependency_graph(&self) -> Graph {
+        let mut graph = Graph::new();
+        for module in &self.modules {
+            graph.add_node(&module.name);
+            for dep in self.dependencies(module) {
+                graph.add_edge(&module.name, &dep);
+            }
+        }
+        graph
+    }
+}
+
+
+/// Collects `use` paths and qualified paths of a module, without going
+/// into inline child modules.
+#[derive(Default)]
+struct PathCollector {
+    uses: Vec<(Vec<String>, Option<String>)>,
+    paths: Vec<Vec<String>>,
+}
+
+
+/// Flattens a use tree into the paths it imports and the names they are
+/// imported as, `None` for a glob import of the path.
+pub fn flatten_use_tree(
+    prefix: &mut Vec<String>, tree: &syn::UseTree,
+    uses: &mut Vec<(Vec<String>, Option<String>)>
+) {
+    match tree {
+        syn::UseTree::Path(use_path) => {
+            prefix.push(use_path.ident.to_string());
+            flatten_use_tree(prefix, &use_path.tree, uses);
+            prefix.pop();
+        }
+        syn::UseTree::Name(use_name) => {
+            // `use a::{self, b}` imports `a` itself
+            if use_name.ident == "self" {
+                uses.push((prefix.clone(), prefix.last().cloned()));
+            } else {
+                let mut path = prefix.clone();
+                path.push(use_name.ident.to_string());
+                uses.push((path, Some(use_name.ident.to_string())));
+            }
+        }
+        syn::UseTree::Rename(use_rename) => {
+            let mut path = prefix.clone();
+            path.push(use_rename.ident.to_string());
+            uses.push((path, Some(use_rename.rename.to_string())));
+        }
+        syn::UseTree::Glob(_) => uses.push((prefix.clone(), None)),
+        syn::UseTree::Group(use_group) => {
+            for tree in &use_group.items {
+                flatten_use_tree(prefix, tree, uses);
+            }
+        }
+    }
+}
+
+
+impl<'ast> Visit<'ast> for PathCollector {
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if i.content.is_none() {
+            visit::visit_item_mod(self, i);
+        }
+    }
+
+    fn visit_item_use(&mut self, i: &'ast syn::ItemUse) {
+        flatten_use_tree(&mut Vec::new(), &i.tree, &mut self.uses);
+    }
+
+    fn visit_path(&mut self, i: &'ast syn::Path) {
+        if i.segments.len() >= 2 {
+            self.paths.push(i.segments.iter().map(|s| s.ident.to_string()).collect());
+        }
+        visit::visit_path(self, i);
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::ModuleTree;
+
+    fn fixture(test: &str) -> (PathBuf, ModuleTree) {
+        let dir = fixture_crate(test, &[
+            ("src/lib.rs", "pub mod graph;\npub mod utils;\npub mod metrics;\n"),
+            ("src/graph.rs", "use crate::utils::escape;\n"),
+            ("src/utils.rs", "pub fn escape() {}\n"),
+            ("src/metrics/mod.rs", "pub mod loc;\npub use loc::*;\n"),
+            ("src/metrics/loc.rs", "pub fn compute_loc() {}\n\n#[cfg(test)]\nmod loctests {\n    use super::compute_loc;\n}\n"),
+            ("src/main.rs", "use fixture::metrics::compute_loc;\n\nfn main() {}\n"),
+            ("src/bin/tool.rs", "fn main() {}\n"),
+        ]);
+        let tree = ModuleTree::from_path(&dir, &Options::default());
+        (dir, tree)
+    }
+
+    #[test]
+    fn test_module_tree() {
+        let (dir, tree) = fixture("modules-tree");
+
+        assert_eq!(tree.crates(), ["fixture", "main", "tool"]);
+        assert!(tree.contains("fixture::metrics::loc"));
+        assert!(tree.contains("fixture::metrics::loc::loctests"));
+        assert!(tree.contains("fixture::graph"));
+        assert!(tree.get("fixture::metrics::loc::loctests").unwrap().is_test);
+        assert!(!tree.get("fixture::metrics::loc").unwrap().is_test);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let (dir, tree) = fixture("modules-resolve");
+        let path = |s: &str| -> Vec<String> { s.split("::").map(String::from).collect() };
+
+        assert_eq!(
+            tree.resolve_path("fixture::graph", &path("crate::utils::escape")),
+            Some("fixture::utils".to_owned())
+        );
+        assert_eq!(
+            tree.resolve_path("fixture::metrics::loc::loctests", &path("super::compute_loc")),
+            Some("fixture::metrics::loc".to_owned())
+        );
+        assert_eq!(
+            tree.resolve_path("main", &path("fixture::metrics::compute_loc")),
+            Some("fixture::metrics".to_owned())
+        );
+        assert_eq!(tree.resolve_path("fixture", &path("std::fs::File")), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let (dir, tree) = fixture("modules-deps");
+        let graph = tree.dependency_graph();
+
+        let deps: Vec<&String> = graph.successors("main").collect();
+        assert_eq!(deps, ["fixture::metrics"]);
+        assert!(graph.successors("fixture::graph").any(|d| d == "fixture::utils"));
+        assert!(graph.successors("fixture::metrics").any(|d| d == "fixture::metrics::loc"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use crate::cfg::CfgConfig;
+
+
+/// What the analyses take into account: the library side of the
+/// `--expand-macros`, `--include-closures` and `--features`/`--cfg` flags,
+/// and where `cc`, `loc` and `locf` cache their results. The metrics take
+/// it too, see `compute_itemfn_cc_with`.
+///
+/// ```ignore
+/// let options = Options{expand_macros: true, ..Options::default()};
+/// let report = analyze_cc(&["src"], &options);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    /// Parse the bodies of macro invocations like `vec![..]` or
+    /// `println!(..)` as expressions, statements or items and count their
+    /// code. Macros are not expanded, a `macro_rules!` body is not looked up.
+    pub expand_macros: bool,
+    /// Count the closures and async blocks in their enclosing function
+    /// instead of reporting them as `outer::{closure#N}` pseudo-functions.
+    pub include_closures: bool,
+    /// Reuse the results of the files analyzed before, see `cached_results`.
+    pub cache_dir: Option<PathBuf>,
+    /// Prune the items configured out, as `--features` and `--cfg` say.
+    /// Without one, the code of every configuration is counted.
+    pub cfg: Option<CfgConfig>,
+}
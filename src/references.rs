@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use crate::modules::ModuleTree;
+use crate::utils::{is_cfg_test, is_test_fn};
+
+
+/// An occurrence of an identifier in a path, method call, `use`
+/// declaration or macro invocation.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub file: PathBuf,
+    pub line: usize,
+    pub in_test: bool,
+    pub qualifier: Option<String>,  // `Type` in `Type::name`
+    pub is_method: bool,  // `.name(..)`
+}
+
+
+/// Crate-wide index from identifier names to their references. Names are
+/// not resolved, `foo` in one module and `foo` in another are the same.
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    refs: BTreeMap<String, Vec<Reference>>,
+}
+
+
+impl ReferenceIndex {
+
+    pub fn from_tree(tree: &ModuleTree) -> Self {
+        let mut index = ReferenceIndex::default();
+        for module in &tree.modules {
+            let mut collector = ReferenceCollector{
+                index: &mut index,
+                file: module.file.clone(),
+                in_test: module.is_test,
+            };
+            for item in &module.items {
+                collector.visit_item(item);
+            }
+        }
+        index
+    }
+
+    pub fn references(&self, name: &str) -> &[Reference] {
+        self.refs.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    fn add(&mut self, name: String, reference: Reference) {
+        self.refs.entry(name).or_default().push(reference);
+    }
+}
+
+
+struct ReferenceCollector<'a> {
+    index: &'a mut ReferenceIndex,
+    file: PathBuf,
+    in_test: bool,
+}
+
+
+impl<'a> ReferenceCollector<'a> {
+
+    fn add(&mut self, ident: &proc_macro2::Ident, qualifier: Option<String>, is_method: bool) {
+        let reference = Reference{
+            file: self.file.clone(),
+            line: ident.span().start().line,
+            in_test: self.in_test,
+            qualifier,
+            is_method,
+        };
+        self.index.add(ident.to_string(), reference);
+    }
+
+    fn add_tokens(&mut self, tokens: TokenStream) {
+        // `a.b(..)` and `A::b` are recognized from the surrounding tokens
+        let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+        let mut prev_ident: Option<String> = None;
+        let mut puncts = String::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Ident(ident) => {
+                    let qualifier = if puncts == "::" { prev_ident.take() } else { None };
+                    let is_call = matches!(
+                        tokens.get(i + 1),
+                        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis
+                    );
+                    self.add(ident, qualifier, puncts == "." && is_call);
+                    prev_ident = Some(ident.to_string());
+                    puncts.clear();
+                }
+                TokenTree::Punct(punct) => puncts.push(punct.as_char()),
+                TokenTree::Group(group) => {
+                    self.add_tokens(group.stream());
+                    prev_ident = None;
+                    puncts.clear();
+                }
+                TokenTree::Literal(_) => {
+                    prev_ident = None;
+                    puncts.clear();
+                }
+            }
+        }
+    }
+
+    fn add_use_tree(&mut self, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(use_path) => {
+                self.add(&use_path.ident, None, false);
+                self.add_use_tree(&use_path.tree);
+            }
+            syn::UseTree::Name(use_name) => self.add(&use_name.ident, None, false),
+            syn::UseTree::Rename(use_rename) => self.add(&use_rename.ident, None, false),
+            syn::UseTree::Glob(_) => {}
+            syn::UseTree::Group(use_group) => {
+                for tree in &use_group.items {
+                    self.add_use_tree(tree);
+                }
+            }
+        }
+    }
+}
+
+
+impl<'ast, 'a> Visit<'ast> for ReferenceCollector<'a> {
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        // inline modules are modules of the tree on their own
+        if i.content.is_none() {
+            visit::visit_item_mod(self, i);
+        }
+    }
+
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        let outer = self.in_test;
+        self.in_test = outer || is_test_fn(&i.attrs) || is_cfg_test(&i.attrs);
+        visit::visit_item_fn(self, i);
+        self.in_test = outer;
+    }
+
+    fn visit_impl_item_method(&mut self, i: &'ast syn::ImplItemMethod) {
+        let outer = self.in_test;
+        self.in_test = outer || is_test_fn(&i.attrs) || is_cfg_test(&i.attrs);
+        visit::visit_impl_item_method(self, i);
+        self.in_test = outer;
+    }
+
+    fn visit_item_use(&mut self, i: &'ast syn::ItemUse) {
+        self.add_use_tree(&i.tree);
+    }
+
+    fn visit_path(&mut self, i: &'ast syn::Path) {
+        let mut qualifier = None;
+        for segment in &i.segments {
+            self.add(&segment.ident, qualifier, false);
+            qualifier = Some(segment.ident.to_string());
+        }
+        visit::visit_path(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        self.add(&i.method, None, true);
+        visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        self.add_tokens(i.tokens.clone());
+        visit::visit_macro(self, i);
+    }
+
+}
+
+
+/// Line range of an item, to tell references from its own body apart.
+pub fn line_range<T: Spanned>(node: &T) -> (usize, usize) {
+    let span = node.span();
+    (span.start().line, span.end().line)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::modules::ModuleTree;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::ReferenceIndex;
+
+    #[test]
+    fn test_reference_index() {
+        let dir = fixture_crate("references", &[
+            ("src/lib.rs", "mod stats;
+
+pub fn run() {
+    let total = stats::cached_results();
+    Point::new().norm();
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_run() {
+        super::stats::nesting();
+    }
+}
+"),
+            ("src/stats.rs", "pub fn cached_results() -> usize { nesting() }
+pub fn nesting() -> usize { 0 }
+"),
+        ]);
+        let tree = ModuleTree::from_path(&dir, &Options::default());
+        let index = ReferenceIndex::from_tree(&tree);
+
+        let refs = index.references("cached_results");
+        assert_eq!(refs.len(), 1);
+        assert!(refs[0].file.ends_with("lib.rs") && refs[0].line == 4 && !refs[0].in_test);
+
+        // called from `stats` and the tests of the crate root
+        let refs = index.references("nesting");
+        assert!(refs.iter().any(|r| r.in_test && r.file.ends_with("lib.rs")));
+        assert!(refs.iter().any(|r| !r.in_test && r.file.ends_with("stats.rs")));
+
+        let refs = index.references("new");
+        assert!(refs.iter().all(|r| r.qualifier.as_deref() == Some("Point") && !r.is_method));
+
+        let refs = index.references("norm");
+        assert!(!refs.is_empty() && refs.iter().all(|r| r.is_method));
+
+        assert!(index.references("no_such_identifier").is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,387 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+
+/// Something with a value to rank and aggregate, like the CC of a function
+/// or the file count of a directory.
+pub trait Measured {
+    fn value(&self) -> f64;
+
+    /// Ranks the items of the same value, the first difference decides.
+    fn tie_breaks(&self) -> Vec<f64> {
+        vec![]
+    }
+}
+
+
+/// Ascending comparison of the values, then of the tie breaks.
+fn compare<T: Measured>(a: &T, b: &T) -> Ordering {
+    a.value().total_cmp(&b.value())
+        .then_with(|| {
+            let (a, b) = (a.tie_breaks(), b.tie_breaks());
+            a.iter().zip(&b)
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(format!("unknown sort order {}, expected asc or desc", s)),
+        }
+    }
+}
+
+
+impl SortOrder {
+
+    /// Sorts with an ascending comparison, reversed for `Desc`. The sort is
+    /// stable either way.
+    pub fn sort_by<T>(&self, items: &mut [T], mut compare: impl FnMut(&T, &T) -> Ordering) {
+        match self {
+            SortOrder::Asc => items.sort_by(|a, b| compare(a, b)),
+            SortOrder::Desc => items.sort_by(|a, b| compare(b, a)),
+        }
+    }
+
+    pub fn sort_by_key<T, K: Ord>(&self, items: &mut [T], mut key: impl FnMut(&T) -> K) {
+        self.sort_by(items, |a, b| key(a).cmp(&key(b)));
+    }
+}
+
+
+/// How the subcommands rank their items: `--top N` and `--sort asc|desc`,
+/// and the histogram `--buckets` if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportOptions {
+    pub top: usize,
+    pub order: SortOrder,
+    pub buckets: Option<Vec<f64>>,
+}
+
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions{top: 5, order: SortOrder::Desc, buckets: None}
+    }
+}
+
+
+/// A histogram bucket, `[lower, upper)`, the last one has no upper bound.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bucket {
+    pub lower: f64,
+    pub upper: Option<f64>,
+    pub count: usize,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub buckets: Vec<Bucket>,
+}
+
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let max_count = self.buckets.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+        for bucket in &self.buckets {
+            let range = match bucket.upper {
+                Some(upper) => format!("[{}, {})", bucket.lower, upper),
+                None => format!("[{}, +)", bucket.lower),
+            };
+            let bar = "#".repeat((bucket.count * 40).div_ceil(max_count));
+            writeln!(f, "\t{:>14} {:>6} {}", range, bucket.count, bar)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// The spread of the values of a `Stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    pub sum: f64,
+    pub mean: f64,
+    pub stddev: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub gini: f64,
+}
+
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.sum.fract() == 0.0 {
+            true => write!(f, "SUM: {}, ", self.sum)?,
+            false => write!(f, "SUM: {:.2}, ", self.sum)?,
+        }
+        write!(f, "MEAN: {:.2}, STDDEV: {:.2}, P50: {:.2}, P90: {:.2}, P95: {:.2}, P99: {:.2}, GINI: {:.2}",
+            self.mean, self.stddev, self.p50, self.p90, self.p95, self.p99, self.gini
+        )
+    }
+}
+
+
+/// Items ranked by their value, with the descriptive statistics of the
+/// values.
+#[derive(Debug, Clone)]
+pub struct Stats<T: Measured> {
+    items: Vec<T>,
+    order: SortOrder,
+    is_sorted: bool,
+}
+
+
+impl<T: Measured> Default for Stats<T> {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+
+impl<T: Measured> Stats<T> {
+
+    /// Stats sorted in descending order, the default of the reports.
+    pub fn new() -> Self {
+        Stats::with_order(SortOrder::Desc)
+    }
+
+    pub fn with_order(order: SortOrder) -> Self {
+        Stats{items: Vec::new(), order, is_sorted: false}
+    }
+
+    pub fn add(&mut self, item: T) {
+        self.items.push(item);
+        self.is_sorted = false;
+    }
+
+    pub fn extend(&mut self, items: Vec<T>) {
+        self.items.extend(items);
+        self.is_sorted = false;
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn sort(&mut self) {
+        self.order.sort_by(&mut self.items, compare);
+        self.is_sorted = true;
+    }
+
+    /// All items, in order.
+    pub fn items(&mut self) -> &[T] {
+        if !self.is_sorted { self.sort() }
+        &self.items
+    }
+
+    /// All items, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    /// The first k items, in order.
+    pub fn topk(&mut self, k: usize) -> &[T] {
+        let items = self.items();
+        &items[0..k.min(items.len())]
+    }
+
+    /// All items, in `order` from now on.
+    pub fn ranked(&mut self, order: SortOrder) -> &[T] {
+        if self.order != order {
+            self.order = order;
+            self.is_sorted = false;
+        }
+        self.items()
+    }
+
+    /// The `--top` items, in the `--sort` order.
+    pub fn top(&mut self, report: &ReportOptions) -> &[T] {
+        let items = self.ranked(report.order);
+        &items[0..report.top.min(items.len())]
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        self.items.iter().rev().max_by(|a, b| compare(*a, *b))
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        self.items.iter().rev().min_by(|a, b| compare(*a, *b))
+    }
+
+    fn sorted_values(&self) -> Vec<f64> {
+        let mut values: Vec<f64> = self.items.iter().map(|item| item.value()).collect();
+        values.sort_by(f64::total_cmp);
+        values
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.items.iter().map(|item| item.value()).sum()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.items.is_empty() {
+            return 0.0
+        }
+        self.sum() / self.items.len() as f64
+    }
+
+    /// The p-th percentile, `p` in `0..=100`, interpolated between the two
+    /// closest ranks.
+    pub fn percentile(&self, p: f64) -> f64 {
+        let values = self.sorted_values();
+        if values.is_empty() {
+            return 0.0
+        }
+        let rank = p.clamp(0.0, 100.0) / 100.0 * (values.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+    }
+
+    pub fn median(&self) -> f64 {
+        self.percentile(50.0)
+    }
+
+    /// Population standard deviation.
+    pub fn stddev(&self) -> f64 {
+        if self.items.is_empty() {
+            return 0.0
+        }
+        let mean = self.mean();
+        let variance: f64 = self.items.iter()
+            .map(|item| (item.value() - mean).powi(2))
+            .sum::<f64>() / self.items.len() as f64;
+        variance.sqrt()
+    }
+
+    /// Gini coefficient of the values, 0 when every item has the same value
+    /// and close to 1 when a single item has it all.
+    pub fn gini(&self) -> f64 {
+        let values = self.sorted_values();
+        let sum: f64 = values.iter().sum();
+        if values.is_empty() || sum == 0.0 {
+            return 0.0
+        }
+        let n = values.len() as f64;
+        let weighted: f64 = values.iter().enumerate()
+            .map(|(idx, value)| (idx as f64 + 1.0) * value)
+            .sum();
+        (2.0 * weighted) / (n * sum) - (n + 1.0) / n
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        Distribution{
+            sum: self.sum(),
+            mean: self.mean(),
+            stddev: self.stddev(),
+            p50: self.percentile(50.0),
+            p90: self.percentile(90.0),
+            p95: self.percentile(95.0),
+            p99: self.percentile(99.0),
+            gini: self.gini(),
+        }
+    }
+
+    /// Counts the values per bucket, `bounds` are the lower bounds of the
+    /// buckets in increasing order. Values below the first bound are not
+    /// counted.
+    pub fn histogram(&self, bounds: &[f64]) -> Histogram {
+        let buckets = bounds.iter().enumerate()
+            .map(|(idx, lower)| {
+                let upper = bounds.get(idx + 1).cloned();
+                let count = self.items.iter()
+                    .filter(|item| item.value() >= *lower && upper.map(|u| item.value() < u).unwrap_or(true))
+                    .count();
+                Bucket{lower: *lower, upper, count}
+            })
+            .collect();
+        Histogram{buckets}
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Measured, ReportOptions, SortOrder, Stats};
+
+    impl Measured for usize {
+        fn value(&self) -> f64 {
+            *self as f64
+        }
+    }
+
+    impl Measured for (f64, f64) {
+        fn value(&self) -> f64 {
+            self.0
+        }
+
+        fn tie_breaks(&self) -> Vec<f64> {
+            vec![self.1]
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut stats: Stats<usize> = Stats::with_order(SortOrder::Desc);
+        stats.extend(vec![3, 1, 4, 1, 5, 9, 2, 6]);
+
+        assert_eq!(stats.topk(3), &[9, 6, 5]);
+        assert_eq!(stats.max(), Some(&9));
+        assert_eq!(stats.min(), Some(&1));
+        assert_eq!(stats.sum(), 31.0);
+        assert_eq!(stats.median(), 3.5);
+        assert_eq!(stats.percentile(100.0), 9.0);
+        assert!((stats.stddev() - 2.5709).abs() < 1e-3);
+
+        let histogram = stats.histogram(&[0.0, 2.0, 5.0]);
+        let counts: Vec<usize> = histogram.buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![2, 3, 3]);
+    }
+
+    #[test]
+    fn test_gini() {
+        let mut equal: Stats<usize> = Stats::with_order(SortOrder::Asc);
+        equal.extend(vec![2, 2, 2, 2]);
+        assert_eq!(equal.gini(), 0.0);
+
+        let mut skewed: Stats<usize> = Stats::with_order(SortOrder::Asc);
+        skewed.extend(vec![0, 0, 0, 8]);
+        assert_eq!(skewed.gini(), 0.75);
+        assert_eq!(skewed.topk(2), &[0, 0]);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut stats: Stats<(f64, f64)> = Stats::new();
+        stats.extend(vec![(1.0, 0.0), (2.0, 1.0), (f64::NAN, 0.0), (2.0, 3.0)]);
+        assert_eq!(stats.topk(3)[1..], [(2.0, 3.0), (2.0, 1.0)]);
+        assert!(stats.max().unwrap().0.is_nan());
+
+        let report = ReportOptions{top: 2, order: SortOrder::Asc, buckets: None};
+        assert_eq!(stats.top(&report), &[(1.0, 0.0), (2.0, 1.0)]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::fmt;
+
+use crate::metrics::{compute_itemfn_abc, compute_impl_method_abc, file_functions, FnItem, AbcVisitor};
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::options::Options;
+use crate::utils::parse_rs_files;
+
+
+#[derive(Debug, Clone)]
+pub struct AbcFunction {
+    pub func_name: String,
+    pub func_file: String,
+    pub abc: AbcVisitor,
+}
+
+
+impl fmt::Display for AbcFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {:.2}",
+            self.func_name, self.abc.assignments, self.abc.branches, self.abc.conditions,
+            self.abc.magnitude())
+    }
+}
+
+
+impl AbcFunction {
+
+    pub fn new(func_name: String, func_file: String, abc: AbcVisitor) -> Self {
+        AbcFunction{func_name, func_file, abc}
+    }
+}
+
+
+impl Measured for AbcFunction {
+    fn value(&self) -> f64 {
+        self.abc.magnitude()
+    }
+}
+
+
+/// The ABC metric of the functions of a set of files, see `analyze_abc`.
+#[derive(Debug, Clone)]
+pub struct AbcReport {
+    pub functions: Stats<AbcFunction>,
+}
+
+
+impl AbcReport {
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## ABC Statistic ########");
+        if self.functions.is_empty() {
+            println!("No function or impl method found!");
+            return;
+        }
+        println!("FUNC NUM: {}, MEAN: {:.2}", self.functions.len(), self.functions.mean());
+        println!("{}", self.functions.distribution());
+        if let Some(t) = self.functions.max() {
+            println!("MAX: {:}, {}", t, t.func_file);
+        }
+        if let Some(t) = self.functions.min() {
+            println!("MIN: {:}, {}", t, t.func_file);
+        }
+        println!("TOP {} (NAME, A, B, C, ABC):", report.top);
+        for abcfunc in self.functions.top(report) {
+            println!("\t{:}", abcfunc);
+        }
+        if let Some(bounds) = &report.buckets {
+            println!("HISTOGRAM (ABC, FUNC NUM):");
+            print!("{}", self.functions.histogram(bounds));
+        }
+    }
+
+}
+
+
+/// The ABC metric of the functions and impl methods of a parsed file, those
+/// of its inline modules included. Test code is left out.
+pub fn abc_functions(ast: &syn::File, func_file: &str) -> Vec<AbcFunction> {
+    file_functions(ast).into_iter()
+        .filter(|func| !func.is_test)
+        .map(|func| {
+            let value = match func.item {
+                FnItem::Fn(item_fn) => compute_itemfn_abc(item_fn),
+                FnItem::Method(method) => compute_impl_method_abc(method),
+            };
+            AbcFunction::new(func.name, func_file.to_owned(), value)
+        })
+        .collect()
+}
+
+
+/// The ABC metric of the functions of the `.rs` files under the paths. The
+/// files which do not parse are skipped.
+pub fn analyze_abc<P: AsRef<Path>>(paths: &[P], options: &Options) -> AbcReport {
+    let mut functions = Stats::new();
+    for (func_file, ast) in parse_rs_files(paths, options) {
+        functions.extend(abc_functions(&ast, &func_file));
+    }
+    AbcReport{functions}
+}
+
+
+pub fn process_abc(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_abc(&[path_str], options).summary(report);
+}
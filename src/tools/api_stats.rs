@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::api::{diff_api, Api, ApiChange, ApiKind};
+use crate::modules::ModuleTree;
+use crate::options::Options;
+use crate::stats::ReportOptions;
+
+
+const ITEM_KINDS: [ApiKind; 10] = [
+    ApiKind::Mod, ApiKind::Fn, ApiKind::Struct, ApiKind::Enum, ApiKind::Union,
+    ApiKind::Trait, ApiKind::TypeAlias, ApiKind::Const, ApiKind::Static, ApiKind::Macro,
+];
+
+
+/// The API of the first crate under `path`, the library if there is one.
+fn load_api(path: &Path, options: &Options) -> Option<(String, Api)> {
+    let tree = ModuleTree::from_path(path, options);
+    let crate_name = tree.crates().first()?.clone();
+    let api = Api::from_tree(&tree, &crate_name);
+    Some((crate_name, api))
+}
+
+
+/// The public API of a crate, see `analyze_api`.
+pub struct ApiReport {
+    api: Option<(String, Api)>,  // none without a crate root
+}
+
+
+impl ApiReport {
+
+    pub fn summary(&self, report: &ReportOptions) {
+        println!("######## Public API Statistic ########");
+        let (crate_name, api) = match &self.api {
+            Some(loaded) => loaded,
+            None => {
+                println!("No crate root (lib.rs or main.rs) found!");
+                return;
+            }
+        };
+        let counts: Vec<String> = ITEM_KINDS.iter()
+            .map(|&kind| format!("{}: {}", kind.to_string().to_uppercase(), api.count(kind)))
+            .collect();
+        println!("CRATE: {}, ITEM NUM: {}", crate_name, api.items.len());
+        println!("{}", counts.join(", "));
+        // items per module, fields and methods left out
+        let mut modules: BTreeMap<&str, usize> = BTreeMap::new();
+        for item in api.items.iter().filter(|item| ITEM_KINDS.contains(&item.kind)) {
+            let module = item.path.rsplit_once("::").map(|(module, _)| module).unwrap_or(&item.path);
+            *modules.entry(module).or_default() += 1;
+        }
+        let mut modules: Vec<(&str, usize)> = modules.into_iter().collect();
+        report.order.sort_by_key(&mut modules, |(_, items)| *items);
+        println!("TOP {} MODULES (PATH, ITEM NUM):", report.top);
+        for (module, items) in modules.iter().take(report.top) {
+            println!("\t{}, {}", module, items);
+        }
+        println!("ITEMS (PATH, KIND, SIGNATURE):");
+        for item in &api.items {
+            println!("\t{}, {}, {}", item.path, item.kind, item.signature);
+        }
+    }
+
+}
+
+
+/// The changes between the public APIs of two versions of a crate, see
+/// `analyze_api_diff`.
+pub struct ApiDiffReport {
+    changes: Option<Vec<ApiChange>>,  // none if a version has no crate root
+}
+
+
+impl ApiDiffReport {
+
+    pub fn summary(&self) {
+        println!("######## Public API Diff ########");
+        let changes = match &self.changes {
+            Some(changes) => changes,
+            None => {
+                println!("No crate root (lib.rs or main.rs) found!");
+                return;
+            }
+        };
+        let (breaking, non_breaking): (Vec<&ApiChange>, Vec<&ApiChange>) = changes.iter()
+            .partition(|change| change.is_breaking());
+        println!("BREAKING: {}, NON-BREAKING: {}", breaking.len(), non_breaking.len());
+        println!("BREAKING CHANGES:");
+        for change in &breaking {
+            println!("\t{:}", change);
+        }
+        println!("NON-BREAKING CHANGES:");
+        for change in &non_breaking {
+            println!("\t{:}", change);
+        }
+    }
+
+}
+
+
+/// The public API of the first crate under `path`, the library if there is
+/// one.
+pub fn analyze_api(path: &Path, options: &Options) -> ApiReport {
+    ApiReport{api: load_api(path, options)}
+}
+
+
+/// The changes from the public API of the crate under `old_path` to the one
+/// under `new_path`.
+pub fn analyze_api_diff(old_path: &Path, new_path: &Path, options: &Options) -> ApiDiffReport {
+    let changes = match (load_api(old_path, options), load_api(new_path, options)) {
+        (Some((_, old)), Some((_, new))) => Some(diff_api(&old, &new)),
+        _ => None
+    };
+    ApiDiffReport{changes}
+}
+
+
+pub fn process_api(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_api(Path::new(path_str), options).summary(report);
+}
+
+
+pub fn process_api_diff(old_path_str: &str, new_path_str: &str, options: &Options) {
+    analyze_api_diff(Path::new(old_path_str), Path::new(new_path_str), options).summary();
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::metrics::{compute_async_fn, compute_async_block, find_async_blocks, file_functions, AsyncVisitor, BlockingApi};
+use crate::utils::parse_rs_files;
+use crate::stats::ReportOptions;
+use crate::options::Options;
+
+
+#[derive(Debug, Clone)]
+pub struct AsyncFunction<'a> {
+    func_name: String,
+    func_file: String,
+    is_block: bool,  // an async block or async closure, not an `async fn`
+    metrics: AsyncVisitor<'a>,
+}
+
+
+impl<'a> fmt::Display for AsyncFunction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {}, {}",
+            self.func_name, self.metrics.awaits, self.metrics.selects,
+            self.metrics.joins, self.metrics.spawns, self.metrics.blocking.len()
+        )
+    }
+}
+
+
+impl<'a> AsyncFunction<'a> {
+
+    pub fn new(func_name: String, func_file: String, is_block: bool, metrics: AsyncVisitor<'a>) -> Self {
+        AsyncFunction{func_name, func_file, is_block, metrics}
+    }
+}
+
+
+pub struct AsyncStats<'a> {
+    functions: Vec<AsyncFunction<'a>>,
+    blocking_apis: &'a [BlockingApi],
+}
+
+
+impl<'a> AsyncStats<'a> {
+
+    pub fn new(blocking_apis: &'a [BlockingApi]) -> Self {
+        AsyncStats{functions: Vec::new(), blocking_apis}
+    }
+
+    pub fn add_funcs(&mut self, funcs: Vec<AsyncFunction<'a>>) {
+        self.functions.extend(funcs);
+    }
+
+    fn total(&self, count: impl Fn(&AsyncVisitor) -> usize) -> usize {
+        self.functions.iter().map(|f| count(&f.metrics)).sum()
+    }
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Async Statistic ########");
+        if self.functions.is_empty() {
+            println!("No async fn or async block found!");
+            return;
+        }
+        let blocks = self.functions.iter().filter(|f| f.is_block).count();
+        println!("ASYNC FN NUM: {}, ASYNC BLOCK NUM: {}", self.functions.len() - blocks, blocks);
+        println!("AWAITS: {}, SELECTS: {}, JOINS: {}, SPAWNS: {}, BLOCKING CALLS: {}",
+            self.total(|m| m.awaits), self.total(|m| m.selects), self.total(|m| m.joins),
+            self.total(|m| m.spawns), self.total(|m| m.blocking.len())
+        );
+        let apis: Vec<String> = self.blocking_apis.iter().map(|api| api.to_string()).collect();
+        println!("BLOCKING APIS: {}", apis.join(", "));
+
+        report.order.sort_by_key(&mut self.functions, |f| f.metrics.awaits);
+        println!("TOP {} BY AWAITS (NAME, AWAITS, SELECTS, JOINS, SPAWNS, BLOCKING):", report.top);
+        for asyncfunc in self.functions.iter().take(report.top) {
+            println!("\t{:}", asyncfunc);
+        }
+
+        println!("BLOCKING CALLS IN ASYNC CONTEXT (NAME, CALL, FILE:LINE):");
+        for asyncfunc in &self.functions {
+            for call in &asyncfunc.metrics.blocking {
+                println!("\t{}, {}, {}:{}", asyncfunc.func_name, call.call, asyncfunc.func_file, call.line);
+            }
+        }
+    }
+
+}
+
+
+fn process_async_fn<'a>(
+    name: String, sig: &syn::Signature, block: &syn::Block,
+    func_file: &str, blocking_apis: &'a [BlockingApi], functions: &mut Vec<AsyncFunction<'a>>
+) {
+    match compute_async_fn(sig, block, blocking_apis) {
+        Some(metrics) => functions.push(AsyncFunction::new(name, func_file.to_owned(), false, metrics)),
+        None => {
+            for (block_name, expr) in find_async_blocks(&name, block) {
+                let metrics = compute_async_block(expr, blocking_apis);
+                functions.push(AsyncFunction::new(block_name, func_file.to_owned(), true, metrics));
+            }
+        }
+    }
+}
+
+
+/// The async fns and async blocks of the functions and impl methods of a
+/// parsed file, those of its inline modules included. Test code is left
+/// out.
+pub fn async_functions<'a>(ast: &syn::File, func_file: &str, blocking_apis: &'a [BlockingApi]) -> Vec<AsyncFunction<'a>> {
+    let mut functions: Vec<AsyncFunction> = vec![];
+    for func in file_functions(ast).into_iter().filter(|func| !func.is_test) {
+        process_async_fn(func.name, func.item.sig(), func.item.block(), func_file, blocking_apis, &mut functions);
+    }
+    functions
+}
+
+
+/// The async fns and async blocks of the `.rs` files under the paths, with
+/// the calls to the blocking APIs in them. The files which do not parse are
+/// skipped.
+pub fn analyze_async<'a, P: AsRef<Path>>(paths: &[P], blocking_apis: &'a [BlockingApi], options: &Options) -> AsyncStats<'a> {
+    let mut stats = AsyncStats::new(blocking_apis);
+    for (func_file, ast) in parse_rs_files(paths, options) {
+        stats.add_funcs(async_functions(&ast, &func_file, blocking_apis));
+    }
+    stats
+}
+
+
+pub fn process_async(path_str: &str, blocking_apis: &[BlockingApi], options: &Options, report: &ReportOptions) {
+    analyze_async(&[path_str], blocking_apis, options).summary(report);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use crate::metrics::BlockingApi;
+    use super::async_functions;
+
+    #[test]
+    fn test_async_inline_mods() {
+        let ast: syn::File = parse_quote! {
+            mod net {
+                pub async fn fetch(url: &str) -> String {
+                    std::fs::read_to_string(url).unwrap()
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                #[tokio::test]
+                async fn test_fetch() {}
+            }
+        };
+
+        let blocking_apis = BlockingApi::defaults();
+        let functions = async_functions(&ast, "net.rs", &blocking_apis);
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].func_name, "net::fetch");
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::tools::{CCFunction, LocFile, LocFunction, Options};
+use crate::utils::{iter_rs_fpath, parse_rs_file};
+
+
+/// The results of another happ version are never read.
+const CACHE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// 64-bit FNV-1a, stable across builds and platforms unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+
+/// The cache directory of the crate a path belongs to: `happ-cache` in the
+/// `target` directory next to the nearest `Cargo.toml`, or in
+/// `$CARGO_TARGET_DIR` if set.
+pub fn default_cache_dir(path: &Path) -> PathBuf {
+    if let Ok(target_dir) = std::env::var("CARGO_TARGET_DIR") {
+        return PathBuf::from(target_dir).join("happ-cache")
+    }
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let crate_dir = path.ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .unwrap_or_else(|| Path::new("."));
+    crate_dir.join("target").join("happ-cache")
+}
+
+
+/// A per-file result which can be stored in the cache. The file is not
+/// stored, files of the same content share their entry.
+pub trait Cached: Sized {
+    fn to_json(&self) -> Value;
+    fn from_json(value: &Value, file: &str) -> Option<Self>;
+}
+
+
+impl Cached for CCFunction {
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.func_name,
+            "cc": self.cc,
+            "max_nesting": self.max_nesting,
+            "avg_nesting": self.avg_nesting,
+        })
+    }
+
+    fn from_json(value: &Value, file: &str) -> Option<Self> {
+        let ccfunc = CCFunction::new(value["name"].as_str()?.to_owned(), file.to_owned(), value["cc"].as_u64()? as usize)
+            .with_nesting(value["max_nesting"].as_u64()? as usize, value["avg_nesting"].as_f64()?);
+        Some(ccfunc)
+    }
+}
+
+
+impl Cached for LocFunction {
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.func_name,
+            "loc": self.loc,
+            "lloc": self.lloc,
+            "max_nesting": self.max_nesting,
+        })
+    }
+
+    fn from_json(value: &Value, file: &str) -> Option<Self> {
+        let locfunc = LocFunction::new(value["name"].as_str()?.to_owned(), file.to_owned(), value["loc"].as_u64()? as usize)
+            .with_lloc(value["lloc"].as_u64()? as usize)
+            .with_nesting(value["max_nesting"].as_u64()? as usize);
+        Some(locfunc)
+    }
+}
+
+
+impl Cached for LocFile {
+
+    fn to_json(&self) -> Value {
+        json!({"loc": self.loc})
+    }
+
+    fn from_json(value: &Value, file: &str) -> Option<Self> {
+        Some(LocFile::new(file.to_owned(), value["loc"].as_u64()? as usize))
+    }
+}
+
+
+/// Per-file results of the analyses, stored as `<dir>/<kind>/<key>.json`
+/// and keyed by the content of the file, the happ version and the options
+/// the results depend on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+
+impl AnalysisCache {
+
+    pub fn new(dir: PathBuf) -> Self {
+        AnalysisCache{dir}
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn key(&self, content: &str, options: &Options) -> String {
+        let mut bytes = format!("{}\0{}\0{}\0{:?}\0",
+            CACHE_VERSION, options.expand_macros, options.include_closures, options.cfg
+        ).into_bytes();
+        bytes.extend(content.as_bytes());
+        format!("{:016x}", fnv1a(&bytes))
+    }
+
+    fn entry(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(format!("{}.json", key))
+    }
+
+    /// The results stored for a key, none if missing or unreadable.
+    pub fn get<T: Cached>(&self, kind: &str, key: &str, file: &str) -> Option<Vec<T>> {
+        let content = fs::read_to_string(self.entry(kind, key)).ok()?;
+        let value: Value = serde_json::from_str(&content).ok()?;
+        value.as_array()?.iter().map(|item| T::from_json(item, file)).collect()
+    }
+
+    /// Stores the results of a key. A cache which cannot be written is not
+    /// an error, the results are computed again next time.
+    pub fn put<T: Cached>(&self, kind: &str, key: &str, items: &[T]) {
+        let entry = self.entry(kind, key);
+        let value = Value::Array(items.iter().map(Cached::to_json).collect());
+        if fs::create_dir_all(entry.parent().unwrap()).is_ok() {
+            let _ = fs::write(entry, value.to_string());
+        }
+    }
+
+    pub fn clean(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+
+/// The results of `compute` on each `.rs` file under the paths, read from
+/// the cache in `options.cache_dir` for the files analyzed before. The files
+/// which do not parse are skipped.
+pub fn cached_results<T: Cached, P: AsRef<Path>>(
+    paths: &[P], kind: &str, options: &Options, compute: impl Fn(&syn::File, &str) -> Vec<T>
+) -> Vec<T> {
+    let cache = options.cache_dir.clone().map(AnalysisCache::new);
+    let mut results = vec![];
+    for rs_fpath in paths.iter().flat_map(|path| iter_rs_fpath(path.as_ref().to_str().unwrap())) {
+        let content = match fs::read_to_string(&rs_fpath) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let file = rs_fpath.to_string_lossy();
+        let key = cache.as_ref().map(|cache| cache.key(&content, options));
+        if let (Some(cache), Some(key)) = (&cache, &key) {
+            if let Some(cached) = cache.get(kind, key, &file) {
+                results.extend(cached);
+                continue;
+            }
+        }
+        let ast = match parse_rs_file(&content, options) {
+            Ok(ast) => ast,
+            Err(_) => continue,
+        };
+        let computed = compute(&ast, &file);
+        if let (Some(cache), Some(key)) = (&cache, &key) {
+            cache.put(kind, key, &computed);
+        }
+        results.extend(computed);
+    }
+    results
+}
+
+
+pub fn process_cache_clean(path_str: &str) {
+    let cache = AnalysisCache::new(default_cache_dir(Path::new(path_str)));
+    match cache.dir().is_dir() {
+        true => {
+            cache.clean().unwrap();
+            println!("Removed {}", cache.dir().display());
+        }
+        false => println!("No cache at {}", cache.dir().display()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::tools::{analyze_cc, Options};
+    use super::AnalysisCache;
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("happ-cache-{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(&src).unwrap();
+        let rs_fpath = src.join("lib.rs");
+        fs::write(&rs_fpath, "fn one(x: bool) { if x {} }\n").unwrap();
+        let options = Options{cache_dir: Some(dir.join("cache")), ..Options::default()};
+        let cache = AnalysisCache::new(dir.join("cache"));
+        let key = cache.key(&fs::read_to_string(&rs_fpath).unwrap(), &options);
+
+        let report = analyze_cc(&[&src], &options);
+        assert_eq!(report.functions.sum(), 2.0);
+        let entry = dir.join("cache").join("cc").join(format!("{}.json", key));
+        assert!(entry.is_file());
+
+        // an unchanged file is read from the cache, not parsed again
+        fs::write(&entry, r#"[{"name": "one", "cc": 42, "max_nesting": 1, "avg_nesting": 1.0}]"#).unwrap();
+        assert_eq!(analyze_cc(&[&src], &options).functions.sum(), 42.0);
+        let other = Options{include_closures: true, ..options.clone()};
+        assert_eq!(analyze_cc(&[&src], &other).functions.sum(), 2.0);
+
+        fs::write(&rs_fpath, "fn one(x: bool) { if x {} else if !x {} }\n").unwrap();
+        assert_eq!(analyze_cc(&[&src], &options).functions.sum(), 3.0);
+
+        cache.clean().unwrap();
+        assert!(!dir.join("cache").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::graph::Graph;
+use crate::metrics::{compute_itemfn_calls, compute_impl_method_calls, file_functions, Call, FnItem};
+use crate::metrics::{compute_itemfn_loc, compute_impl_method_loc};
+use crate::utils::parse_rs_files;
+use crate::stats::ReportOptions;
+use crate::options::Options;
+
+
+const UNRESOLVED: &str = "unresolved";
+
+
+#[derive(Debug, Clone)]
+pub struct CallFunction {
+    func_name: String,
+    func_file: String,
+    self_ty: Option<String>,
+    calls: Vec<Call>,
+    loc: usize,
+}
+
+
+impl CallFunction {
+
+    pub fn new(func_name: String, func_file: String, self_ty: Option<String>,
+               calls: Vec<Call>, loc: usize) -> Self {
+        CallFunction{func_name, func_file, self_ty, calls, loc}
+    }
+
+    /// The name calls refer to it by, `function` or `Type::method`,
+    /// without the inline modules.
+    fn call_name(&self) -> String {
+        let name = self.func_name.rsplit("::").next().unwrap();
+        match &self.self_ty {
+            Some(self_ty) => format!("{}::{}", self_ty, name),
+            None => name.to_owned(),
+        }
+    }
+}
+
+
+/// Fan-in, fan-out and Henry-Kafura information flow complexity of a function.
+#[derive(Debug, Clone)]
+pub struct CallFlow {
+    node: String,
+    fan_in: usize,
+    fan_out: usize,
+    loc: usize,
+}
+
+
+impl fmt::Display for CallFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {}", self.node, self.fan_in, self.fan_out, self.loc, self.hk())
+    }
+}
+
+
+impl CallFlow {
+
+    /// Henry-Kafura complexity, `LOC * (fan-in * fan-out)^2`.
+    fn hk(&self) -> usize {
+        self.loc * (self.fan_in * self.fan_out).pow(2)
+    }
+}
+
+
+pub struct CallGraphStats {
+    functions: Vec<CallFunction>,
+    graph: Graph,
+}
+
+
+impl Default for CallGraphStats {
+    fn default() -> Self {
+        CallGraphStats::new()
+    }
+}
+
+
+impl CallGraphStats {
+
+    pub fn new() -> Self {
+        CallGraphStats{functions: Vec::new(), graph: Graph::new()}
+    }
+
+    pub fn add_funcs(&mut self, funcs: Vec<CallFunction>) {
+        self.functions.extend(funcs);
+    }
+
+    /// Graph node of every function, its name, or `name @ file` when
+    /// several functions share the name.
+    fn node_names(&self) -> Vec<String> {
+        let mut name_count: BTreeMap<&str, usize> = BTreeMap::new();
+        for func in &self.functions {
+            *name_count.entry(&func.func_name).or_insert(0) += 1;
+        }
+        self.functions.iter()
+            .map(|func| match name_count[func.func_name.as_str()] {
+                1 => func.func_name.clone(),
+                _ => format!("{} @ {}", func.func_name, func.func_file),
+            })
+            .collect()
+    }
+
+    /// Resolves the calls of every function against the collected ones by
+    /// name, a method call on an unknown receiver goes to every method
+    /// with that name. Calls to unknown functions are marked unresolved.
+    pub fn build_graph(&mut self) {
+        let nodes = self.node_names();
+        let mut by_name: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        let mut by_method: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (idx, func) in self.functions.iter().enumerate() {
+            by_name.entry(func.call_name()).or_default().push(idx);
+            if func.self_ty.is_some() {
+                let method = func.func_name.rsplit("::").next().unwrap().to_owned();
+                by_method.entry(method).or_default().push(idx);
+            }
+        }
+
+        let mut graph = Graph::new();
+        for (caller, func) in self.functions.iter().enumerate() {
+            graph.add_node(&nodes[caller]);
+            for call in &func.calls {
+                let (callees, unresolved) = match call {
+                    Call::Path(segments) => {
+                        let name = segments.last().unwrap();
+                        // tuple structs and enum variants like `Some(..)`
+                        if name.starts_with(char::is_uppercase) {
+                            continue;
+                        }
+                        let mut callees = vec![];
+                        if segments.len() >= 2 {
+                            let ty = match segments[segments.len() - 2].as_str() {
+                                "Self" => func.self_ty.clone().unwrap_or_default(),
+                                ty => ty.to_owned(),
+                            };
+                            callees = by_name.get(format!("{}::{}", ty, name).as_str())
+                                .cloned().unwrap_or_default();
+                        }
+                        if callees.is_empty() {
+                            callees = by_name.get(name).cloned().unwrap_or_default();
+                        }
+                        (callees, segments.join("::"))
+                    }
+                    Call::Method(name, on_self) => {
+                        let mut callees = vec![];
+                        if let (true, Some(self_ty)) = (on_self, &func.self_ty) {
+                            callees = by_name.get(format!("{}::{}", self_ty, name).as_str())
+                                .cloned().unwrap_or_default();
+                        }
+                        if callees.is_empty() {
+                            callees = by_method.get(name).cloned().unwrap_or_default();
+                        }
+                        (callees, format!(".{}()", name))
+                    }
+                };
+                // prefer the callees defined in the caller's file
+                let same_file: Vec<usize> = callees.iter().cloned()
+                    .filter(|&c| self.functions[c].func_file == func.func_file)
+                    .collect();
+                let callees = if same_file.is_empty() { callees } else { same_file };
+                if callees.is_empty() {
+                    graph.add_marked_node(&unresolved, UNRESOLVED);
+                    graph.add_edge(&nodes[caller], &unresolved);
+                }
+                for callee in callees {
+                    graph.add_edge(&nodes[caller], &nodes[callee]);
+                }
+            }
+        }
+        self.graph = graph;
+    }
+
+    /// The flows of the functions, in the order they were added.
+    pub fn flows(&self) -> Vec<CallFlow> {
+        let nodes = self.node_names();
+        self.functions.iter().zip(nodes)
+            .map(|(func, node)| CallFlow{
+                fan_in: self.graph.fan_in(&node),
+                fan_out: self.graph.fan_out(&node),
+                loc: func.loc,
+                node,
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let flows: Vec<Value> = self.flows().iter()
+            .map(|flow| json!({
+                "id": flow.node,
+                "fan_in": flow.fan_in,
+                "fan_out": flow.fan_out,
+                "loc": flow.loc,
+                "hk": flow.hk(),
+            }))
+            .collect();
+        json!({"functions": flows, "graph": self.graph.to_json()})
+    }
+
+    /// Prints the graph in the format, `dot`, `json` or the text summary.
+    pub fn summary(&self, format: &str, report: &ReportOptions) {
+        match format {
+            "dot" => print!("{}", self.graph.to_dot("calls")),
+            "json" => println!("{}", serde_json::to_string_pretty(&self.to_json()).unwrap()),
+            _ => self.print_text(report),
+        }
+    }
+
+    fn print_text(&self, report: &ReportOptions) {
+        println!("######## Call Graph Statistic ########");
+        if self.functions.is_empty() {
+            println!("No function or impl method found!");
+            return;
+        }
+        let unresolved = self.graph.nodes()
+            .filter(|node| self.graph.mark(node) == Some(UNRESOLVED))
+            .count();
+        println!("FUNC NUM: {}, CALL EDGES: {}, UNRESOLVED CALLEES: {}",
+            self.functions.len(), self.graph.edge_count(), unresolved);
+        let mut flows = self.flows();
+        report.order.sort_by_key(&mut flows, |flow| flow.hk());
+        println!("TOP {} (NAME, FAN-IN, FAN-OUT, LOC, HENRY-KAFURA):", report.top);
+        for flow in flows.iter().take(report.top) {
+            println!("\t{:}", flow);
+        }
+    }
+
+}
+
+
+/// The calls of the functions and impl methods of a parsed file, those of
+/// its inline modules included. Test code is left out.
+pub fn call_functions(ast: &syn::File, func_file: &str) -> Vec<CallFunction> {
+    file_functions(ast).into_iter()
+        .filter(|func| !func.is_test)
+        .map(|func| {
+            let (self_ty, calls, loc) = match func.item {
+                FnItem::Fn(item_fn) => (None, compute_itemfn_calls(item_fn), compute_itemfn_loc(item_fn)),
+                FnItem::Method(method) => {
+                    let self_ty = func.name.rsplit("::").nth(1).unwrap().to_owned();
+                    (Some(self_ty), compute_impl_method_calls(method), compute_impl_method_loc(method))
+                }
+            };
+            CallFunction::new(func.name, func_file.to_owned(), self_ty, calls, loc.ploc())
+        })
+        .collect()
+}
+
+
+/// The call graph of the functions of the `.rs` files under the paths. The
+/// files which do not parse are skipped.
+pub fn analyze_calls<P: AsRef<Path>>(paths: &[P], options: &Options) -> CallGraphStats {
+    let mut stats = CallGraphStats::new();
+    for (func_file, ast) in parse_rs_files(paths, options) {
+        stats.add_funcs(call_functions(&ast, &func_file));
+    }
+    stats.build_graph();
+    stats
+}
+
+
+pub fn process_calls(path_str: &str, format: &str, options: &Options, report: &ReportOptions) {
+    analyze_calls(&[path_str], options).summary(format, report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::{call_functions, CallFlow, CallGraphStats, UNRESOLVED};
+
+    #[test]
+    fn test_build_graph() {
+        let main: syn::File = parse_quote! {
+            fn main() {
+                let p = Point::new();
+                p.norm();
+                helper();
+                std::process::exit(0);
+            }
+
+            fn helper() {}
+
+            mod imp {
+                impl Point {
+                    fn new() -> Self { Self::zero() }
+                    fn zero() -> Self { Point }
+                    fn norm(&self) -> f64 { self.dot() }
+                    fn dot(&self) -> f64 { 0.0 }
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                #[test]
+                fn test_main() { super::main(); }
+            }
+        };
+        let other: syn::File = parse_quote! {
+            fn helper() { undefined(); }
+        };
+
+        let mut stats = CallGraphStats::new();
+        stats.add_funcs(call_functions(&main, "main.rs"));
+        stats.add_funcs(call_functions(&other, "other.rs"));
+        stats.build_graph();
+        let graph = &stats.graph;
+
+        let callees: Vec<&String> = graph.successors("main").collect();
+        assert_eq!(callees, [
+            "helper @ main.rs", "imp::Point::new", "imp::Point::norm", "std::process::exit"
+        ]);
+        assert!(graph.successors("imp::Point::new").eq(["imp::Point::zero"].iter()));
+        assert!(graph.successors("imp::Point::norm").eq(["imp::Point::dot"].iter()));
+        assert_eq!(graph.fan_in("helper @ other.rs"), 0);
+        assert!(!graph.nodes().any(|node| node.contains("test_main")));
+
+        // unresolved callees are nodes of their own, out of the fan-out
+        assert_eq!(graph.mark("std::process::exit"), Some(UNRESOLVED));
+        assert_eq!(graph.mark("undefined"), Some(UNRESOLVED));
+        let flows = stats.flows();
+        let main_flow = flows.iter().find(|flow| flow.node == "main").unwrap();
+        assert_eq!((main_flow.fan_in, main_flow.fan_out), (0, 3));
+        let json = stats.to_json();
+        let main_node = json["graph"]["nodes"].as_array().unwrap().iter()
+            .find(|node| node["id"] == "main")
+            .unwrap();
+        assert_eq!(main_node["fan_out"], 3);
+    }
+
+    #[test]
+    fn test_hk() {
+        let flow = CallFlow{node: "run".to_owned(), fan_in: 2, fan_out: 3, loc: 10};
+        assert_eq!(flow.hk(), 360);
+        let leaf = CallFlow{node: "leaf".to_owned(), fan_in: 4, fan_out: 0, loc: 10};
+        assert_eq!(leaf.hk(), 0);
+    }
+}
@@ -1,23 +1,24 @@
-use std::fmt::Display;
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use std::path::Path;
 use std::fmt;
 
-use happ::metrics::{compute_itemfn_cc, compute_impl_method_cc};
-use happ::utils::iter_rs_fpath;
+use crate::metrics::{compute_closure_cc, compute_closure_nesting, compute_fn_nesting};
+use crate::metrics::{file_functions, CcMetric, ClosureIndex, Metric};
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::tools::{cached_results, Options};
 
 #[derive(Debug, Clone)]
 pub struct CCFunction {
-    func_name: String,
-    func_file: String,
-    cc: usize,
+    pub func_name: String,
+    pub func_file: String,
+    pub cc: usize,
+    pub max_nesting: usize,
+    pub avg_nesting: f64,
 }
 
 
 impl fmt::Display for CCFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}, {}", self.func_name, self.cc)
+        write!(f, "{}, {}, {}", self.func_name, self.cc, self.max_nesting)
     }
 }
 
@@ -25,7 +26,13 @@ impl fmt::Display for CCFunction {
 impl CCFunction {
 
     pub fn new(func_name:String, func_file: String, cc: usize) -> Self {
-        CCFunction{func_name, func_file, cc}
+        CCFunction{func_name, func_file, cc, max_nesting: 0, avg_nesting: 0.0}
+    }
+
+    pub fn with_nesting(mut self, max_nesting: usize, avg_nesting: f64) -> Self {
+        self.max_nesting = max_nesting;
+        self.avg_nesting = avg_nesting;
+        self
     }
     
     fn name(&self) -> &str {
@@ -35,150 +42,138 @@ impl CCFunction {
     fn file(&self) -> &str {
         &self.func_file
     }
+}
 
-    fn cc(&self) -> usize {
-        self.cc
+
+impl Measured for CCFunction {
+    fn value(&self) -> f64 {
+        self.cc as f64
     }
 }
 
 
-pub struct CCStats {
-    functions: Vec<CCFunction>,
-    is_sorted: bool
+/// The cyclomatic complexity of the functions of a set of files, see
+/// `analyze_cc`.
+#[derive(Debug, Clone)]
+pub struct CcReport {
+    pub functions: Stats<CCFunction>,
 }
 
-impl CCStats {
 
-    pub fn new() -> Self {
-        CCStats{functions: Vec::new(), is_sorted: false}
+impl CcReport {
+
+    pub fn mean_nesting(&self) -> f64 {
+        if self.functions.is_empty() {
+            return 0.0
+        }
+        let nesting_sum: f64 = self.functions.iter().map(|f| f.avg_nesting).sum();
+        nesting_sum / (self.functions.len() as f64)
     }
 
-    pub fn add_func(&mut self, func: CCFunction) {
-        self.functions.push(func);
-        self.is_sorted = false;
+    /// Functions whose max nesting depth exceeds `max_nesting`.
+    pub fn nesting_violations(&mut self, max_nesting: usize) -> Vec<&CCFunction> {
+        self.functions.items().iter()
+            .filter(|f| f.max_nesting > max_nesting)
+            .collect()
     }
 
-    pub fn add_funcs(&mut self, funcs: Vec<CCFunction>) {
-        self.functions.extend(funcs);
-        self.is_sorted = false;
+    /// Prints the functions nesting deeper than `max_nesting`, false if
+    /// there is any.
+    pub fn nesting_gate(&mut self, max_nesting: usize) -> bool {
+        let violations = self.nesting_violations(max_nesting).into_iter()
+            .map(|f| (f.name(), f.file(), f.max_nesting))
+            .collect();
+        print_nesting_gate(max_nesting, violations)
     }
 
-    pub fn summary(&mut self) {
+    pub fn summary(&mut self, report: &ReportOptions) {
         println!("######## Cyclomatic Complexity Statistic ########");
-        if self.functions.len() == 0 {
+        if self.functions.is_empty() {
             println!("No function or impl method found!");
             return;
         }
-        println!("FUNC NUM: {}, MEAN: {:.2}", self.functions.len(), self.mean());
-        match self.max() {
-            Some(t) => println!("MAX: {:}", t),
-            _ => ()
-        };
-        match self.min() {
-            Some(t) => println!("MIN: {:}", t),
-            _ => ()
-        };
-        println!("TOP 5:");
-        for ccfunc in self.topk(5) {
-            println!("\t{:}", ccfunc);
+        println!("FUNC NUM: {}, MEAN: {:.2}, MEAN NESTING: {:.2}",
+            self.functions.len(), self.functions.mean(), self.mean_nesting());
+        println!("{}", self.functions.distribution());
+        if let Some(t) = self.functions.max() {
+            println!("MAX: {:}", t);
         }
-    }
-
-    pub fn sort(&mut self) {
-        self.functions.sort_by(
-            |a, b| b.cc.cmp(&a.cc)
-        );
-        self.is_sorted = true;
-    }
-
-    pub fn max(&mut self) -> Option<&CCFunction>{
-        if self.functions.len() == 0 {
-            return None
+        if let Some(t) = self.functions.min() {
+            println!("MIN: {:}", t);
         }
-        if !self.is_sorted { self.sort() }
-        Some(&self.functions[0])
-    }
-
-    pub fn min(&mut self) -> Option<&CCFunction> {
-        if self.functions.len() == 0 {
-            return None
-        }
-        if !self.is_sorted { self.sort() }
-        Some(&self.functions[self.functions.len()-1])
-    }
-
-    pub fn mean(&mut self) -> f64 {
-        if self.functions.len() == 0 {
-            return 0.0
-        }
-        let mut cc_sum = 0.0;
-        for cc_func in &self.functions {
-            cc_sum += cc_func.cc as f64;
+        println!("TOP {} (NAME, CC, NESTING):", report.top);
+        for ccfunc in self.functions.top(report) {
+            println!("\t{:}", ccfunc);
         }
-        cc_sum / (self.functions.len() as f64)
-    }
-
-    pub fn topk(&mut self, k: usize) -> &[CCFunction] {
-        if k >= self.functions.len() {
-            &self.functions[0..self.functions.len()]
-        } else {
-            &self.functions[0..k]
+        if let Some(bounds) = &report.buckets {
+            println!("HISTOGRAM (CC, FUNC NUM):");
+            print!("{}", self.functions.histogram(bounds));
         }
     }
 
 }
 
 
-fn process_cc_file(rs_fpath: &PathBuf) -> Vec<CCFunction> {
-    let mut file = File::open(rs_fpath).unwrap();
-    let mut content = String::new();
-    file.read_to_string(&mut content).unwrap();
+/// The closures and async blocks of a function body as pseudo-functions
+/// named `outer::{closure#N}`, none if they are included in the function.
+fn process_cc_closures(outer: &str, block: &syn::Block, func_file: &str, options: &Options) -> Vec<CCFunction> {
+    if options.include_closures {
+        return vec![]
+    }
+    let index = ClosureIndex::new(outer, block);
+    index.closures().iter()
+        .map(|(name, expr)| {
+            let cc = compute_closure_cc(expr, &index, options);
+            let nesting = compute_closure_nesting(expr, &index);
+            CCFunction::new(name.clone(), func_file.to_owned(), cc)
+                .with_nesting(nesting.max_depth, nesting.avg_depth())
+        })
+        .collect()
+}
 
-    let ast = syn::parse_file(&content).unwrap();
 
+/// The CC of the functions and impl methods of a parsed file, those of its
+/// inline modules included, and of their closures unless they are included
+/// in the functions. Test code is left out.
+pub fn cc_functions(ast: &syn::File, func_file: &str, options: &Options) -> Vec<CCFunction> {
+    let metric = CcMetric::new(options);
     let mut functions: Vec<CCFunction> = vec![];
-
-    for item in &ast.items {
-        match item {
-            syn::Item::Impl(item_impl) => {
-                for impl_item in &item_impl.items {
-                    if let syn::ImplItem::Method(method) = impl_item {
-                        match &*item_impl.self_ty {
-                            syn::Type::Path(syn::TypePath { qself: None, path }) => {
-                                let name = String::from(
-                                    format!("{}::{}",
-                                        path.segments.last().unwrap().ident,
-                                        method.sig.ident
-                                    )
-                                );
-                                let cc = compute_impl_method_cc(method);
-                                let func_file = String::from(rs_fpath.to_str().unwrap());
-                                functions.push(CCFunction::new(name, func_file, cc));
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-            // A bare function like `fn function(arg: Arg) -> Result { .. }`
-            syn::Item::Fn(item_fn) => {
-                let name = item_fn.sig.ident.to_string();
-                let cc = compute_itemfn_cc(item_fn);
-                let func_file = String::from(rs_fpath.to_str().unwrap());
-                functions.push(CCFunction::new(name, func_file, cc));
-            }
-            _ => {}
-        }
+    for func in file_functions(ast).into_iter().filter(|func| !func.is_test) {
+        let cc = metric.compute_fn(func.item) as usize;
+        let nesting = compute_fn_nesting(func.item, options);
+        functions.extend(process_cc_closures(&func.name, func.item.block(), func_file, options));
+        functions.push(
+            CCFunction::new(func.name, func_file.to_owned(), cc)
+                .with_nesting(nesting.max_depth, nesting.avg_depth())
+        );
     }
-
     functions
 }
 
 
-pub fn process_cc(path_str: &str) {
-    let mut stats = CCStats::new();
-    for rs_fpath in iter_rs_fpath(path_str) {
-        stats.add_funcs(process_cc_file(&rs_fpath));
+/// The CC of the functions of the `.rs` files under the paths. The files
+/// which do not parse are skipped.
+pub fn analyze_cc<P: AsRef<Path>>(paths: &[P], options: &Options) -> CcReport {
+    let mut functions = Stats::new();
+    functions.extend(cached_results(paths, "cc", options, |ast, func_file| cc_functions(ast, func_file, options)));
+    CcReport{functions}
+}
+
+
+/// Prints the functions (name, file, max nesting) failing the
+/// `--max-nesting` gate, false if there is any.
+pub(crate) fn print_nesting_gate(max_nesting: usize, violations: Vec<(&str, &str, usize)>) -> bool {
+    if violations.is_empty() {
+        return true
+    }
+    println!("######## Quality Gate: max nesting {} ########", max_nesting);
+    for (name, file, nesting) in violations {
+        println!("\t{}, {}, {}", name, file, nesting);
     }
-    stats.summary();
-}
\ No newline at end of file
+    false
+}
+
+
+pub fn process_cc(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_cc(&[path_str], options).summary(report);
+}
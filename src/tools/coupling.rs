@@ -0,0 +1,290 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::fmt;
+
+use crate::graph::Graph;
+use crate::modules::{Module, ModuleTree};
+use crate::options::Options;
+use crate::stats::ReportOptions;
+
+
+const CHART_WIDTH: usize = 40;
+const CHART_HEIGHT: usize = 20;
+const LABELS: &str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+
+/// Chart label of the module at `idx` in the table, `+` once they run out.
+fn label(idx: usize) -> char {
+    LABELS.chars().nth(idx).unwrap_or('+')
+}
+
+
+/// Martin package metrics of a module or a crate.
+#[derive(Debug, Clone)]
+pub struct Coupling {
+    name: String,
+    ca: usize,
+    ce: usize,
+    abstract_items: usize,
+    items: usize,
+}
+
+
+impl fmt::Display for Coupling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {:.2}, {:.2}, {:.2}",
+            self.name, self.ca, self.ce,
+            self.instability(), self.abstractness(), self.distance())
+    }
+}
+
+
+impl Coupling {
+
+    /// `Ce / (Ca + Ce)`, 0 for a module nothing depends on and that
+    /// depends on nothing.
+    pub fn instability(&self) -> f64 {
+        match self.ca + self.ce {
+            0 => 0.0,
+            total => self.ce as f64 / (total as f64),
+        }
+    }
+
+    /// Share of traits and generic items among the items.
+    pub fn abstractness(&self) -> f64 {
+        match self.items {
+            0 => 0.0,
+            items => self.abstract_items as f64 / (items as f64),
+        }
+    }
+
+    /// Distance from the main sequence, `|A + I - 1|`.
+    pub fn distance(&self) -> f64 {
+        (self.abstractness() + self.instability() - 1.0).abs()
+    }
+}
+
+
+/// Counts the abstract items (traits, and structs, enums, unions, type
+/// aliases and functions with type parameters) among all of them.
+fn count_abstract_items(items: &[syn::Item]) -> (usize, usize) {
+    let has_type_params = |generics: &syn::Generics| generics.type_params().next().is_some();
+    let mut abstract_items = 0;
+    let mut total = 0;
+    for item in items {
+        let is_abstract = match item {
+            syn::Item::Trait(_) => true,
+            syn::Item::Struct(item_struct) => has_type_params(&item_struct.generics),
+            syn::Item::Enum(item_enum) => has_type_params(&item_enum.generics),
+            syn::Item::Union(item_union) => has_type_params(&item_union.generics),
+            syn::Item::Type(item_type) => has_type_params(&item_type.generics),
+            syn::Item::Fn(item_fn) => has_type_params(&item_fn.sig.generics),
+            _ => continue
+        };
+        total += 1;
+        if is_abstract {
+            abstract_items += 1;
+        }
+    }
+    (abstract_items, total)
+}
+
+
+pub struct CouplingStats {
+    modules: Vec<Coupling>,
+    crates: Vec<Coupling>,
+}
+
+
+impl Default for CouplingStats {
+    fn default() -> Self {
+        CouplingStats::new()
+    }
+}
+
+
+impl CouplingStats {
+
+    pub fn new() -> Self {
+        CouplingStats{modules: Vec::new(), crates: Vec::new()}
+    }
+
+    /// Ca and Ce of a module are the modules depending on it and the
+    /// ones it depends on. For a crate, they are the modules outside of
+    /// it depending on one of its modules and the other way round.
+    /// Test modules are left out.
+    pub fn add_tree(&mut self, tree: &ModuleTree) {
+        let mut graph = Graph::new();
+        let production: Vec<&Module> = tree.modules.iter()
+            .filter(|module| !module.is_test)
+            .collect();
+        for module in &production {
+            graph.add_node(&module.name);
+            for dep in tree.dependencies(module) {
+                graph.add_edge(&module.name, &dep);
+            }
+        }
+        let crate_of = |module: &str| module.split("::").next().unwrap().to_owned();
+
+        for module in &production {
+            let (abstract_items, items) = count_abstract_items(&module.items);
+            self.modules.push(Coupling{
+                name: module.name.clone(),
+                ca: graph.fan_in(&module.name),
+                ce: graph.fan_out(&module.name),
+                abstract_items,
+                items,
+            });
+        }
+
+        for crate_name in tree.crates() {
+            let members: Vec<&&Module> = production.iter()
+                .filter(|module| crate_of(&module.name) == *crate_name)
+                .collect();
+            let mut afferent = BTreeSet::new();
+            let mut efferent = BTreeSet::new();
+            let mut abstract_items = 0;
+            let mut items = 0;
+            for module in &members {
+                afferent.extend(graph.predecessors(&module.name)
+                    .filter(|pred| crate_of(pred) != *crate_name));
+                efferent.extend(graph.successors(&module.name)
+                    .filter(|succ| crate_of(succ) != *crate_name));
+                let (module_abstract, module_items) = count_abstract_items(&module.items);
+                abstract_items += module_abstract;
+                items += module_items;
+            }
+            self.crates.push(Coupling{
+                name: crate_name.clone(),
+                ca: afferent.len(),
+                ce: efferent.len(),
+                abstract_items,
+                items,
+            });
+        }
+    }
+
+    /// A/I chart with the main sequence `A + I = 1` drawn as dots, modules
+    /// are labeled by their index in the table.
+    fn scatter_chart(&self) -> String {
+        let mut grid = vec![vec![' '; CHART_WIDTH + 1]; CHART_HEIGHT + 1];
+        for (row, line) in grid.iter_mut().enumerate() {
+            let col = (row * CHART_WIDTH) / CHART_HEIGHT;
+            line[col] = '.';
+        }
+        for (idx, module) in self.modules.iter().enumerate() {
+            let col = (module.instability() * CHART_WIDTH as f64).round() as usize;
+            let row = ((1.0 - module.abstractness()) * CHART_HEIGHT as f64).round() as usize;
+            grid[row][col] = match grid[row][col] {
+                ' ' | '.' => label(idx),
+                _ => '*',
+            };
+        }
+
+        let mut chart = String::new();
+        for (row, line) in grid.iter().enumerate() {
+            let axis = match row {
+                0 => "A 1.0 |",
+                r if r == CHART_HEIGHT => "  0.0 |",
+                _ => "      |",
+            };
+            chart += &format!("\t{}{}\n", axis, line.iter().collect::<String>().trim_end());
+        }
+        chart += &format!("\t      +{}\n", "-".repeat(CHART_WIDTH + 1));
+        chart += &format!("\t       0.0{}I 1.0\n", " ".repeat(CHART_WIDTH - 7));
+        chart
+    }
+
+    pub fn summary(&self, report: &ReportOptions) {
+        println!("######## Module Coupling Statistic ########");
+        if self.modules.is_empty() {
+            println!("No crate root (lib.rs or main.rs) found!");
+            return;
+        }
+        let mean_distance = self.modules.iter().map(Coupling::distance).sum::<f64>()
+            / (self.modules.len() as f64);
+        println!("MODULE NUM: {}, MEAN DISTANCE: {:.2}", self.modules.len(), mean_distance);
+        println!("CRATES (NAME, CA, CE, I, A, D):");
+        for krate in &self.crates {
+            println!("\t{:}", krate);
+        }
+        println!("MODULES (LABEL, NAME, CA, CE, I, A, D):");
+        for (idx, module) in self.modules.iter().enumerate() {
+            println!("\t{}, {:}", label(idx), module);
+        }
+
+        // concrete and depended upon, hard to change
+        let mut pain: Vec<&Coupling> = self.modules.iter()
+            .filter(|module| module.ca > 0 && module.abstractness() + module.instability() < 0.5)
+            .collect();
+        report.order.sort_by(&mut pain, |a, b| a.distance().total_cmp(&b.distance()));
+        println!("ZONE OF PAIN (NAME, CA, CE, I, A, D):");
+        for module in pain.iter().take(report.top) {
+            println!("\t{:}", module);
+        }
+        println!("A/I CHART ('*' for several modules):");
+        print!("{}", self.scatter_chart());
+    }
+
+}
+
+
+/// The coupling of the modules and crates under `path`.
+pub fn analyze_coupling(path: &Path, options: &Options) -> CouplingStats {
+    let tree = ModuleTree::from_path(path, options);
+    let mut stats = CouplingStats::new();
+    stats.add_tree(&tree);
+    stats
+}
+
+
+pub fn process_coupling(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_coupling(Path::new(path_str), options).summary(report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::modules::ModuleTree;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::{Coupling, CouplingStats};
+
+    #[test]
+    fn test_coupling() {
+        let dir = fixture_crate("coupling", &[
+            ("src/lib.rs", "pub mod shapes;\npub mod render;\npub mod util;\n"),
+            ("src/shapes.rs", "use crate::util::round;\n\npub trait Shape {\n    fn area(&self) -> f64;\n}\n\npub struct Circle;\n"),
+            ("src/render.rs", "use crate::shapes::{Circle, Shape};\nuse crate::util::round;\n\npub fn draw<S: Shape>(shape: &S) {}\n"),
+            ("src/util.rs", "pub fn round(x: f64) -> f64 { x }\n\n#[cfg(test)]\nmod tests {\n    use crate::render::draw;\n}\n"),
+            ("src/main.rs", "use fixture::render::draw;\n\nfn main() {}\n"),
+        ]);
+        let tree = ModuleTree::from_path(&dir, &Options::default());
+        let mut stats = CouplingStats::new();
+        stats.add_tree(&tree);
+
+        let metrics = |coupling: &Coupling| {
+            (coupling.name.clone(), coupling.ca, coupling.ce, coupling.instability(), coupling.abstractness())
+        };
+        // the tests of `util` using `render` are left out
+        let modules: Vec<(String, usize, usize, f64, f64)> = stats.modules.iter().map(metrics).collect();
+        assert_eq!(modules, vec![
+            ("fixture".to_owned(), 0, 0, 0.0, 0.0),
+            ("fixture::shapes".to_owned(), 1, 1, 0.5, 0.5),
+            ("fixture::render".to_owned(), 1, 2, 2.0 / 3.0, 1.0),
+            ("fixture::util".to_owned(), 2, 0, 0.0, 0.0),
+            ("main".to_owned(), 0, 1, 1.0, 0.0),
+        ]);
+        assert_eq!(stats.modules[3].distance(), 1.0);
+
+        // across crates, only `main` using `render` counts
+        let crates: Vec<(String, usize, usize, f64, f64)> = stats.crates.iter().map(metrics).collect();
+        assert_eq!(crates, vec![
+            ("fixture".to_owned(), 1, 0, 0.0, 0.5),
+            ("main".to_owned(), 0, 1, 1.0, 0.0),
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::fmt;
+
+use crate::modules::ModuleTree;
+use crate::options::Options;
+use crate::stats::ReportOptions;
+use crate::references::{line_range, Reference, ReferenceIndex};
+use crate::utils::{is_cfg_test, is_test_fn};
+
+
+type LineRanges = Vec<(PathBuf, (usize, usize))>;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeadState {
+    Unreferenced,
+    TestOnly,
+    Alive,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct DeadCandidate {
+    kind: &'static str,
+    name: String,
+    self_ty: Option<String>,  // for methods
+    file: PathBuf,
+    lines: (usize, usize),
+    state: DeadState,
+}
+
+
+impl fmt::Display for DeadCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.self_ty {
+            Some(self_ty) => write!(f, "{}, {}::{}, {}:{}",
+                self.kind, self_ty, self.name, self.file.display(), self.lines.0),
+            None => write!(f, "{}, {}, {}:{}",
+                self.kind, self.name, self.file.display(), self.lines.0),
+        }
+    }
+}
+
+
+impl DeadCandidate {
+
+    pub fn new(kind: &'static str, name: String, file: &Path, lines: (usize, usize)) -> Self {
+        DeadCandidate{kind, name, self_ty: None, file: file.to_owned(), lines, state: DeadState::Alive}
+    }
+
+    pub fn with_self_ty(mut self, self_ty: String) -> Self {
+        self.self_ty = Some(self_ty);
+        self
+    }
+
+    fn line_count(&self) -> usize {
+        self.lines.1 - self.lines.0 + 1
+    }
+
+    /// Method names are common, only `.name()` and `Type::name`
+    /// references count for methods, not locals of the same name.
+    fn is_referenced_by(&self, reference: &Reference) -> bool {
+        match &self.self_ty {
+            Some(self_ty) => {
+                reference.is_method || match reference.qualifier.as_deref() {
+                    Some(qualifier) => qualifier == self_ty || qualifier == "Self",
+                    None => false
+                }
+            }
+            None => true
+        }
+    }
+}
+
+
+pub struct DeadCodeStats {
+    candidates: Vec<DeadCandidate>,
+    // line ranges of the impl blocks of every type, references to a type
+    // from its own impls do not keep it alive
+    impls: BTreeMap<String, LineRanges>,
+}
+
+
+fn is_private(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Inherited)
+}
+
+
+fn is_exported(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("no_mangle") || attr.path.is_ident("export_name"))
+}
+
+
+impl Default for DeadCodeStats {
+    fn default() -> Self {
+        DeadCodeStats::new()
+    }
+}
+
+
+impl DeadCodeStats {
+
+    pub fn new() -> Self {
+        DeadCodeStats{candidates: Vec::new(), impls: BTreeMap::new()}
+    }
+
+    pub fn add_module_items(&mut self, items: &[syn::Item], file: &Path, is_crate_root: bool) {
+        for item in items {
+            match item {
+                syn::Item::Fn(item_fn) => {
+                    let attrs = &item_fn.attrs;
+                    let is_main = is_crate_root && item_fn.sig.ident == "main";
+                    if is_private(&item_fn.vis) && !is_main && !is_test_fn(attrs)
+                        && !is_cfg_test(attrs) && !is_exported(attrs) {
+                        let name = item_fn.sig.ident.to_string();
+                        self.candidates.push(DeadCandidate::new("fn", name, file, line_range(item_fn)));
+                    }
+                }
+                syn::Item::Struct(item_struct) if is_private(&item_struct.vis) => {
+                    let name = item_struct.ident.to_string();
+                    self.candidates.push(DeadCandidate::new("struct", name, file, line_range(item_struct)));
+                }
+                syn::Item::Enum(item_enum) if is_private(&item_enum.vis) => {
+                    for variant in &item_enum.variants {
+                        let name = variant.ident.to_string();
+                        self.candidates.push(DeadCandidate::new("variant", name, file, line_range(variant)));
+                    }
+                }
+                syn::Item::Const(item_const) if is_private(&item_const.vis) => {
+                    let name = item_const.ident.to_string();
+                    self.candidates.push(DeadCandidate::new("const", name, file, line_range(item_const)));
+                }
+                syn::Item::Impl(item_impl) => {
+                    let self_ty = match &*item_impl.self_ty {
+                        syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                            path.segments.last().unwrap().ident.to_string()
+                        }
+                        _ => continue
+                    };
+                    self.impls.entry(self_ty.clone())
+                        .or_default()
+                        .push((file.to_owned(), line_range(item_impl)));
+                    // trait methods are reachable through the trait
+                    if item_impl.trait_.is_some() || is_cfg_test(&item_impl.attrs) {
+                        continue;
+                    }
+                    for impl_item in &item_impl.items {
+                        if let syn::ImplItem::Method(method) = impl_item {
+                            if is_private(&method.vis) && !is_test_fn(&method.attrs) {
+                                let name = method.sig.ident.to_string();
+                                self.candidates.push(
+                                    DeadCandidate::new("method", name, file, line_range(method))
+                                        .with_self_ty(self_ty.clone())
+                                );
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Classifies every candidate by the references from outside its own
+    /// definition (and for types, outside their impl blocks).
+    pub fn resolve(&mut self, index: &ReferenceIndex) {
+        for candidate in &mut self.candidates {
+            let mut own_ranges = vec![(candidate.file.clone(), candidate.lines)];
+            if candidate.kind == "struct" {
+                own_ranges.extend(self.impls.get(&candidate.name).cloned().unwrap_or_default());
+            }
+            let outside: Vec<bool> = index.references(&candidate.name).iter()
+                .filter(|r| candidate.is_referenced_by(r))
+                .filter(|r| !own_ranges.iter().any(|(file, (start, end))| {
+                    r.file == *file && *start <= r.line && r.line <= *end
+                }))
+                .map(|r| r.in_test)
+                .collect();
+            candidate.state = if outside.is_empty() {
+                DeadState::Unreferenced
+            } else if outside.iter().all(|&in_test| in_test) {
+                DeadState::TestOnly
+            } else {
+                DeadState::Alive
+            };
+        }
+    }
+
+    pub fn summary(&self, report: &ReportOptions) {
+        println!("######## Dead Code Candidates ########");
+        if self.candidates.is_empty() {
+            println!("No private item found!");
+            return;
+        }
+        let mut unreferenced: Vec<&DeadCandidate> = self.candidates.iter()
+            .filter(|c| c.state == DeadState::Unreferenced)
+            .collect();
+        let mut test_only: Vec<&DeadCandidate> = self.candidates.iter()
+            .filter(|c| c.state == DeadState::TestOnly)
+            .collect();
+        println!("PRIVATE ITEMS: {}, UNREFERENCED: {}, TEST ONLY: {}",
+            self.candidates.len(), unreferenced.len(), test_only.len());
+        report.order.sort_by_key(&mut unreferenced, |c| c.line_count());
+        report.order.sort_by_key(&mut test_only, |c| c.line_count());
+        if !unreferenced.is_empty() {
+            println!("TOP {} UNREFERENCED (KIND, NAME, LOCATION, LINES):", report.top);
+            for candidate in unreferenced.iter().take(report.top) {
+                println!("\t{:}, {}", candidate, candidate.line_count());
+            }
+        }
+        if !test_only.is_empty() {
+            println!("TOP {} REFERENCED FROM TESTS ONLY (KIND, NAME, LOCATION, LINES):", report.top);
+            for candidate in test_only.iter().take(report.top) {
+                println!("\t{:}, {}", candidate, candidate.line_count());
+            }
+        }
+    }
+
+}
+
+
+/// The private items of the production modules under `path`, classified by
+/// their references.
+pub fn analyze_dead_code(path: &Path, options: &Options) -> DeadCodeStats {
+    let tree = ModuleTree::from_path(path, options);
+    let mut stats = DeadCodeStats::new();
+    for module in tree.modules.iter().filter(|m| !m.is_test) {
+        let is_crate_root = tree.crates().contains(&module.name);
+        stats.add_module_items(&module.items, &module.file, is_crate_root);
+    }
+    stats.resolve(&ReferenceIndex::from_tree(&tree));
+    stats
+}
+
+
+pub fn process_dead_code(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_dead_code(Path::new(path_str), options).summary(report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::{analyze_dead_code, DeadState};
+
+    #[test]
+    fn test_resolve() {
+        let dir = fixture_crate("dead-code", &[
+            ("src/lib.rs", "pub fn run() {
+    used();
+}
+
+fn used() {}
+
+fn unused() {}
+
+fn helper() -> usize { 1 }
+
+struct Orphan;
+
+impl Orphan {
+    fn new() -> Self { Orphan }
+}
+
+pub struct Point;
+
+impl Point {
+    pub fn new() -> Self { Point.scaled() }
+    fn scaled(self) -> Self { self }
+    fn norm(&self) -> f64 { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_helper() {
+        assert_eq!(super::helper(), 1);
+    }
+}
+"),
+        ]);
+
+        let stats = analyze_dead_code(&dir, &Options::default());
+        let states: Vec<(&str, &str, DeadState)> = stats.candidates.iter()
+            .map(|c| (c.kind, c.name.as_str(), c.state))
+            .collect();
+        assert_eq!(states, vec![
+            ("fn", "used", DeadState::Alive),
+            ("fn", "unused", DeadState::Unreferenced),
+            ("fn", "helper", DeadState::TestOnly),
+            // only referenced from its own impl
+            ("struct", "Orphan", DeadState::Unreferenced),
+            ("method", "new", DeadState::Unreferenced),
+            ("method", "scaled", DeadState::Alive),
+            ("method", "norm", DeadState::Unreferenced),
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use crate::graph::Graph;
+use crate::modules::ModuleTree;
+use crate::options::Options;
+use crate::stats::ReportOptions;
+
+
+pub struct DepsStats {
+    graph: Graph,
+}
+
+
+impl DepsStats {
+
+    pub fn new(graph: Graph) -> Self {
+        DepsStats{graph}
+    }
+
+    /// Prints the graph in the format, `dot`, `json` or the text summary.
+    pub fn summary(&self, format: &str, report: &ReportOptions) {
+        match format {
+            "dot" => print!("{}", self.graph.to_dot("modules")),
+            "json" => println!("{}", serde_json::to_string_pretty(&self.graph.to_json()).unwrap()),
+            _ => self.print_text(report),
+        }
+    }
+
+    fn print_text(&self, report: &ReportOptions) {
+        println!("######## Module Dependency Statistic ########");
+        if self.graph.node_count() == 0 {
+            println!("No crate root (lib.rs or main.rs) found!");
+            return;
+        }
+        let cycles = self.graph.cycles();
+        println!("MODULE NUM: {}, DEPENDENCY NUM: {}, CYCLE NUM: {}",
+            self.graph.node_count(), self.graph.edge_count(), cycles.len());
+        if !cycles.is_empty() {
+            println!("CYCLES:");
+            for cycle in &cycles {
+                println!("\t{} -> {}", cycle.join(" -> "), cycle[0]);
+            }
+        }
+        // ranked by fan-in plus fan-out
+        let mut modules: Vec<(&String, usize, usize)> = self.graph.nodes()
+            .map(|module| (module, self.graph.fan_in(module), self.graph.fan_out(module)))
+            .collect();
+        report.order.sort_by_key(&mut modules, |(_, fan_in, fan_out)| fan_in + fan_out);
+        println!("TOP {} MODULES (NAME, FAN-IN, FAN-OUT):", report.top);
+        for (module, fan_in, fan_out) in modules.iter().take(report.top) {
+            println!("\t{}, {}, {}", module, fan_in, fan_out);
+        }
+    }
+
+}
+
+
+/// The dependency graph of the modules of the crates under `path`.
+pub fn analyze_deps(path: &Path, options: &Options) -> DepsStats {
+    let tree = ModuleTree::from_path(path, options);
+    DepsStats::new(tree.dependency_graph())
+}
+
+
+pub fn process_deps(path_str: &str, format: &str, options: &Options, report: &ReportOptions) {
+    analyze_deps(Path::new(path_str), options).summary(format, report);
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::fmt;
+use syn::spanned::Spanned;
+
+use crate::metrics::{doc_comment, has_doc_section, PanicKind, PanicVisitor};
+use crate::metrics::{compute_itemfn_panics, compute_impl_method_panics};
+use crate::modules::{Module, ModuleTree};
+use crate::options::Options;
+use crate::stats::ReportOptions;
+
+
+/// A `pub` item and the sections its doc comment lacks.
+#[derive(Debug, Clone)]
+pub struct DocItem {
+    kind: &'static str,
+    name: String,
+    file: String,
+    line: usize,
+    documented: bool,
+    missing_sections: Vec<&'static str>,
+}
+
+
+impl fmt::Display for DocItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}:{}", self.kind, self.name, self.file, self.line)
+    }
+}
+
+
+/// Items and documented items of a file or crate.
+#[derive(Debug, Clone, Default)]
+pub struct DocCoverage {
+    items: usize,
+    documented: usize,
+}
+
+
+impl fmt::Display for DocCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {:.1}%", self.items, self.documented, self.percent())
+    }
+}
+
+
+impl DocCoverage {
+
+    fn add(&mut self, item: &DocItem) {
+        self.items += 1;
+        if item.documented {
+            self.documented += 1;
+        }
+    }
+
+    pub fn undocumented(&self) -> usize {
+        self.items - self.documented
+    }
+
+    pub fn percent(&self) -> f64 {
+        match self.items {
+            0 => 100.0,
+            items => self.documented as f64 * 100.0 / (items as f64),
+        }
+    }
+}
+
+
+fn is_pub(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+
+fn may_panic(panics: &PanicVisitor) -> bool {
+    panics.count(PanicKind::Unwrap) + panics.count(PanicKind::Expect) + panics.count(PanicKind::Macro) > 0
+}
+
+
+pub struct DocStats {
+    items: Vec<DocItem>,
+    crates: BTreeMap<String, DocCoverage>,
+    files: BTreeMap<String, DocCoverage>,
+}
+
+
+impl Default for DocStats {
+    fn default() -> Self {
+        DocStats::new()
+    }
+}
+
+
+impl DocStats {
+
+    pub fn new() -> Self {
+        DocStats{items: Vec::new(), crates: BTreeMap::new(), files: BTreeMap::new()}
+    }
+
+    fn add_item<T: Spanned>(
+        &mut self, module: &Module, kind: &'static str, name: String, node: &T,
+        attrs: &[syn::Attribute], sections: &[&'static str]
+    ) {
+        let doc = doc_comment(attrs);
+        let missing_sections = match &doc {
+            Some(doc) => sections.iter().cloned().filter(|s| !has_doc_section(doc, s)).collect(),
+            None => vec![]
+        };
+        let item = DocItem{
+            kind,
+            name: format!("{}::{}", module.name, name),
+            file: module.file.to_str().unwrap().to_owned(),
+            line: node.span().start().line,
+            documented: doc.is_some(),
+            missing_sections,
+        };
+        let crate_name = module.name.split("::").next().unwrap().to_owned();
+        self.crates.entry(crate_name).or_default().add(&item);
+        self.files.entry(item.file.clone()).or_default().add(&item);
+        self.items.push(item);
+    }
+
+    /// Checks the `pub` fns, methods, structs, enums, traits, consts and
+    /// modules of the tree, leaving out test modules.
+    pub fn add_tree(&mut self, tree: &ModuleTree) {
+        for module in tree.modules.iter().filter(|m| !m.is_test) {
+            for item in &module.items {
+                self.add_module_item(tree, module, item);
+            }
+        }
+    }
+
+    fn add_module_item(&mut self, tree: &ModuleTree, module: &Module, item: &syn::Item) {
+        match item {
+            syn::Item::Fn(item_fn) if is_pub(&item_fn.vis) => {
+                let mut sections = vec!["Examples"];
+                if item_fn.sig.unsafety.is_some() {
+                    sections.push("Safety");
+                }
+                if may_panic(&compute_itemfn_panics(item_fn)) {
+                    sections.push("Panics");
+                }
+                let name = item_fn.sig.ident.to_string();
+                self.add_item(module, "fn", name, &item_fn.sig.ident, &item_fn.attrs, &sections);
+            }
+            syn::Item::Struct(item_struct) if is_pub(&item_struct.vis) => {
+                let name = item_struct.ident.to_string();
+                self.add_item(module, "struct", name, &item_struct.ident, &item_struct.attrs, &[]);
+            }
+            syn::Item::Enum(item_enum) if is_pub(&item_enum.vis) => {
+                let name = item_enum.ident.to_string();
+                self.add_item(module, "enum", name, &item_enum.ident, &item_enum.attrs, &[]);
+            }
+            syn::Item::Trait(item_trait) if is_pub(&item_trait.vis) => {
+                let mut sections = vec!["Examples"];
+                if item_trait.unsafety.is_some() {
+                    sections.push("Safety");
+                }
+                let name = item_trait.ident.to_string();
+                self.add_item(module, "trait", name, &item_trait.ident, &item_trait.attrs, &sections);
+            }
+            syn::Item::Const(item_const) if is_pub(&item_const.vis) => {
+                let name = item_const.ident.to_string();
+                self.add_item(module, "const", name, &item_const.ident, &item_const.attrs, &[]);
+            }
+            syn::Item::Mod(item_mod) if is_pub(&item_mod.vis) => {
+                // documented on the declaration or with `//!` in the module
+                let mut attrs = item_mod.attrs.clone();
+                if let Some(child) = tree.get(&format!("{}::{}", module.name, item_mod.ident)) {
+                    attrs.extend(child.attrs.iter().cloned());
+                }
+                let name = item_mod.ident.to_string();
+                self.add_item(module, "mod", name, &item_mod.ident, &attrs, &[]);
+            }
+            syn::Item::Impl(item_impl) if item_impl.trait_.is_none() => {
+                let self_ty = match &*item_impl.self_ty {
+                    syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                        path.segments.last().unwrap().ident.to_string()
+                    }
+                    _ => return
+                };
+                for impl_item in &item_impl.items {
+                    if let syn::ImplItem::Method(method) = impl_item {
+                        if !is_pub(&method.vis) {
+                            continue;
+                        }
+                        let mut sections = vec![];
+                        if method.sig.unsafety.is_some() {
+                            sections.push("Safety");
+                        }
+                        if may_panic(&compute_impl_method_panics(method)) {
+                            sections.push("Panics");
+                        }
+                        let name = format!("{}::{}", self_ty, method.sig.ident);
+                        self.add_item(module, "method", name, &method.sig.ident, &method.attrs, &sections);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn coverage(&self) -> DocCoverage {
+        let mut coverage = DocCoverage::default();
+        for item in &self.items {
+            coverage.add(item);
+        }
+        coverage
+    }
+
+    /// Crates documented less than `min_percent`.
+    pub fn coverage_violations(&self, min_percent: f64) -> Vec<(&String, &DocCoverage)> {
+        self.crates.iter()
+            .filter(|(_, coverage)| coverage.percent() < min_percent)
+            .collect()
+    }
+
+    /// Prints the crates documented less than `min_percent`, false if there
+    /// is any.
+    pub fn coverage_gate(&self, min_percent: f64) -> bool {
+        let violations = self.coverage_violations(min_percent);
+        if violations.is_empty() {
+            return true
+        }
+        println!("######## Quality Gate: min doc coverage {}% ########", min_percent);
+        for (crate_name, coverage) in &violations {
+            println!("\t{}, {:.1}%", crate_name, coverage.percent());
+        }
+        false
+    }
+
+    pub fn summary(&self, report: &ReportOptions) {
+        println!("######## Doc Coverage Statistic ########");
+        if self.items.is_empty() {
+            println!("No pub item found!");
+            return;
+        }
+        let coverage = self.coverage();
+        println!("ITEM NUM: {}, DOCUMENTED: {}, COVERAGE: {:.1}%",
+            coverage.items, coverage.documented, coverage.percent());
+        println!("CRATES (NAME, ITEMS, DOCUMENTED, COVERAGE):");
+        for (crate_name, coverage) in &self.crates {
+            println!("\t{}, {:}", crate_name, coverage);
+        }
+        let mut files: Vec<(&String, &DocCoverage)> = self.files.iter().collect();
+        report.order.sort_by_key(&mut files, |(_, coverage)| coverage.undocumented());
+        println!("TOP {} FILES BY UNDOCUMENTED (PATH, ITEMS, DOCUMENTED, COVERAGE):", report.top);
+        for (file, coverage) in files.iter().take(report.top) {
+            println!("\t{}, {:}", file, coverage);
+        }
+        println!("UNDOCUMENTED (KIND, NAME, LOCATION):");
+        for item in self.items.iter().filter(|item| !item.documented) {
+            println!("\t{:}", item);
+        }
+        println!("MISSING SECTIONS (KIND, NAME, LOCATION, SECTIONS):");
+        for item in self.items.iter().filter(|item| !item.missing_sections.is_empty()) {
+            println!("\t{:}, {}", item, item.missing_sections.join(" "));
+        }
+    }
+
+}
+
+
+/// The doc coverage of the `pub` items of the crates under `path`.
+pub fn analyze_doc_coverage(path: &Path, options: &Options) -> DocStats {
+    let tree = ModuleTree::from_path(path, options);
+    let mut stats = DocStats::new();
+    stats.add_tree(&tree);
+    stats
+}
+
+
+/// Prints the doc coverage, then the crates under `min_coverage` if it is
+/// set. False if the gate fails.
+pub fn process_doc_coverage(path_str: &str, options: &Options, report: &ReportOptions, min_coverage: Option<f64>) -> bool {
+    let stats = analyze_doc_coverage(Path::new(path_str), options);
+    stats.summary(report);
+    min_coverage.is_none_or(|min_coverage| stats.coverage_gate(min_coverage))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::analyze_doc_coverage;
+
+    #[test]
+    fn test_doc_coverage() {
+        let dir = fixture_crate("doc-coverage", &[
+            ("src/lib.rs", "/// Shapes.\npub mod shapes;\npub mod util;\n"),
+            ("src/shapes.rs", "/// A circle.\npub struct Circle;\n\npub enum Kind { Round }\n\n/// The area.\n///\n/// # Examples\n///\n/// ```\n/// area(1.0);\n/// ```\npub fn area(r: f64) -> f64 { r }\n\n/// The name.\npub fn name(s: Option<&str>) -> &str { s.unwrap() }\n"),
+            ("src/util.rs", "//! Helpers.\n\npub const ZERO: f64 = 0.0;\n\n#[cfg(test)]\nmod tests {\n    pub fn helper() {}\n}\n"),
+            ("src/main.rs", "/// The entry.\npub fn run() {}\n\nfn main() {}\n"),
+        ]);
+        let stats = analyze_doc_coverage(&dir, &Options::default());
+
+        // structs and enums need no `# Examples`
+        let missing: Vec<(&str, Vec<&str>)> = stats.items.iter()
+            .filter(|item| !item.missing_sections.is_empty())
+            .map(|item| (item.name.as_str(), item.missing_sections.clone()))
+            .collect();
+        assert_eq!(missing, [
+            ("fixture::shapes::name", vec!["Examples", "Panics"]),
+            ("main::run", vec!["Examples"]),
+        ]);
+        assert!(stats.items.iter().all(|item| !item.name.contains("helper")));
+
+        let coverage = stats.coverage();
+        assert_eq!((coverage.items, coverage.documented), (8, 6));
+        let crates: Vec<(&str, usize, usize)> = stats.crates.iter()
+            .map(|(name, coverage)| (name.as_str(), coverage.items, coverage.documented))
+            .collect();
+        assert_eq!(crates, [("fixture", 7, 5), ("main", 1, 1)]);
+        let files: Vec<(String, usize)> = stats.files.iter()
+            .map(|(file, coverage)| (file.rsplit('/').next().unwrap().to_owned(), coverage.undocumented()))
+            .collect();
+        assert_eq!(files, [("lib.rs".to_owned(), 0), ("main.rs".to_owned(), 0),
+                           ("shapes.rs".to_owned(), 1), ("util.rs".to_owned(), 1)]);
+
+        let violations: Vec<&String> = stats.coverage_violations(80.0).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(violations, ["fixture"]);
+        assert!(stats.coverage_violations(50.0).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+use std::fmt;
+
+use crate::metrics::{compute_itemfn_exits, compute_impl_method_exits, file_functions, FnItem, ExitVisitor};
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::options::Options;
+use crate::utils::parse_rs_files;
+
+#[derive(Debug, Clone)]
+pub struct ExitFunction {
+    pub func_name: String,
+    pub func_file: String,
+    pub exits: ExitVisitor,
+}
+
+
+impl fmt::Display for ExitFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {}, {}",
+            self.func_name,
+            self.exits.nexits(),
+            self.exits.returns,
+            self.exits.tries,
+            self.exits.tails,
+            self.exits.panics,
+        )
+    }
+}
+
+
+impl ExitFunction {
+
+    pub fn new(func_name: String, func_file: String, exits: ExitVisitor) -> Self {
+        ExitFunction{func_name, func_file, exits}
+    }
+
+    fn nexits(&self) -> usize {
+        self.exits.nexits()
+    }
+}
+
+
+impl Measured for ExitFunction {
+    fn value(&self) -> f64 {
+        self.nexits() as f64
+    }
+}
+
+
+/// The exit points of the functions of a set of files, see `analyze_exits`.
+#[derive(Debug, Clone)]
+pub struct ExitReport {
+    pub functions: Stats<ExitFunction>,
+}
+
+
+impl ExitReport {
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Exit Point Statistic ########");
+        if self.functions.is_empty() {
+            println!("No function or impl method found!");
+            return;
+        }
+        println!("FUNC NUM: {}, MEAN NEXITS: {:.2}", self.functions.len(), self.functions.mean());
+        println!("{}", self.functions.distribution());
+        if let Some(t) = self.functions.max() {
+            println!("MAX: {:}", t);
+        }
+        println!("TOP {} (NAME, NEXITS, RETURN, TRY, TAIL, PANIC, FILE):", report.top);
+        for exitfunc in self.functions.top(report) {
+            println!("\t{:}, {}", exitfunc, exitfunc.func_file);
+        }
+        if let Some(bounds) = &report.buckets {
+            println!("HISTOGRAM (NEXITS, FUNC NUM):");
+            print!("{}", self.functions.histogram(bounds));
+        }
+    }
+
+}
+
+
+/// The exit points of the functions and impl methods of a parsed file, those
+/// of its inline modules included. Test code is left out.
+pub fn exit_functions(ast: &syn::File, func_file: &str) -> Vec<ExitFunction> {
+    file_functions(ast).into_iter()
+        .filter(|func| !func.is_test)
+        .map(|func| {
+            let value = match func.item {
+                FnItem::Fn(item_fn) => compute_itemfn_exits(item_fn),
+                FnItem::Method(method) => compute_impl_method_exits(method),
+            };
+            ExitFunction::new(func.name, func_file.to_owned(), value)
+        })
+        .collect()
+}
+
+
+/// The exit points of the functions of the `.rs` files under the paths. The
+/// files which do not parse are skipped.
+pub fn analyze_exits<P: AsRef<Path>>(paths: &[P], options: &Options) -> ExitReport {
+    let mut functions = Stats::new();
+    for (func_file, ast) in parse_rs_files(paths, options) {
+        functions.extend(exit_functions(&ast, &func_file));
+    }
+    ExitReport{functions}
+}
+
+
+pub fn process_exits(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_exits(&[path_str], options).summary(report);
+}
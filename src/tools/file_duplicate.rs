@@ -1,82 +1,79 @@
 use std::fs;
-use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
 
+/// The files of a tree by content, see `analyze_file_duplicate`.
+#[derive(Debug, Clone, Default)]
 pub struct FileDuplicateStats {
     files: Vec<PathBuf>,
-    file_hashs: HashMap<u64, usize>,
-    is_build: bool,
+    file_hashs: HashMap<u64, usize>,  // content hash to the number of files
 }
 
 
 impl FileDuplicateStats {
 
     pub fn new() -> Self {
-        FileDuplicateStats{
-            files: Vec::new(), 
-            file_hashs: HashMap::new(),
-            is_build: false
-        }
+        FileDuplicateStats::default()
     }
 
-    pub fn summary(&mut self) {
+    pub fn summary(&self) {
         println!("######## File Duplicate Statistic ########");
-        if self.files.len() == 0 {
+        if self.files.is_empty() {
             println!("No file found!");
             return;
         }
-        if !self.is_build {
-            self.build_hashmap()
-        }
-        println!("Total file count: {}", self.files.len());
-        println!("Unique file count: {}", self.file_hashs.len());
+        println!("Total file count: {}", self.file_count());
+        println!("Unique file count: {}", self.unique_count());
         println!("File Duplicate Rate: {}", self.duplicate_rate());
     }
 
-    pub fn duplicate_rate(&mut self) -> f32 {
-        if !self.is_build {
-            self.build_hashmap()
-        }
-        (1.0 - (self.file_hashs.len() as f32) / (self.files.len() as f32))
+    pub fn file_count(&self) -> usize {
+        self.files.len()
     }
 
-    pub fn add_file(&mut self, file_fpath: PathBuf) {
-        self.files.push(file_fpath)
+    pub fn unique_count(&self) -> usize {
+        self.file_hashs.len()
     }
 
-    pub fn build_hashmap(&mut self) {
-        if self.is_build {
-            return
+    /// Share of the files whose content another file already has.
+    pub fn duplicate_rate(&self) -> f32 {
+        if self.files.is_empty() {
+            return 0.0
         }
-        for path in &self.files {
-            let mut file = fs::File::open(path).unwrap();
-            let mut s = String::new();
-            file.read_to_string(&mut s);
-            let mut hasher = DefaultHasher::new();
-            Hash::hash(&s, &mut hasher);
-            let hash_num = hasher.finish();
-            if self.file_hashs.contains_key(&hash_num) {
-                let mut val = self.file_hashs.get_mut(&hash_num).unwrap();
-                *val += 1;
-            } else {
-                self.file_hashs.insert(hash_num, 1);
+        1.0 - (self.unique_count() as f32) / (self.file_count() as f32)
+    }
+
+    /// Hashes the content of a file, a file which cannot be read is skipped.
+    pub fn add_file(&mut self, file_fpath: PathBuf) {
+        let content = match fs::read(&file_fpath) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Skip file {}: {}", file_fpath.display(), e);
+                return;
             }
-        }
-        self.is_build = true;
+        };
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        *self.file_hashs.entry(hasher.finish()).or_insert(0) += 1;
+        self.files.push(file_fpath);
     }
 }
 
 
-fn _process(path_str: &str, stats: &mut FileDuplicateStats) {
-    let items = fs::read_dir(path_str).unwrap();
-    for item in items {
-        let item = item.unwrap();
+fn _process(path: &Path, stats: &mut FileDuplicateStats) {
+    let items = match fs::read_dir(path) {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Skip dir {}: {}", path.display(), e);
+            return;
+        }
+    };
+    for item in items.filter_map(Result::ok) {
         if item.path().is_dir() {
-            _process(item.path().to_str().unwrap(), stats)
+            _process(&item.path(), stats)
         } else {
             stats.add_file(item.path());
         }
@@ -84,10 +81,40 @@ fn _process(path_str: &str, stats: &mut FileDuplicateStats) {
 }
 
 
-pub fn process_file_duplicate(path_str: &str) {
+/// The files under the directory `path` by content.
+pub fn analyze_file_duplicate(path: &Path) -> FileDuplicateStats {
     let mut stats = FileDuplicateStats::new();
-    if PathBuf::from(path_str).is_dir() {
-        _process(path_str, &mut stats);
+    if path.is_dir() {
+        _process(path, &mut stats);
     }
-    stats.summary();
-}
\ No newline at end of file
+    stats
+}
+
+
+pub fn process_file_duplicate(path_str: &str) {
+    analyze_file_duplicate(Path::new(path_str)).summary();
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::utils::fixture_crate;
+    use super::analyze_file_duplicate;
+
+    #[test]
+    fn test_file_duplicate() {
+        // the fixture's Cargo.toml is the fourth file
+        let dir = fixture_crate("fdupl", &[
+            ("src/a.rs", "fn a() {}\n"),
+            ("src/b/a.rs", "fn a() {}\n"),
+            ("src/c.rs", "fn c() {}\n"),
+        ]);
+        let stats = analyze_file_duplicate(&dir);
+        assert_eq!((stats.file_count(), stats.unique_count()), (4, 3));
+        assert_eq!(stats.duplicate_rate(), 0.25);
+        assert_eq!(analyze_file_duplicate(&dir.join("missing")).duplicate_rate(), 0.0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
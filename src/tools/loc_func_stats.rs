@@ -1,23 +1,24 @@
-use std::fmt::Display;
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use std::path::Path;
 use std::fmt;
 
-use happ::metrics::{compute_itemfn_loc, compute_impl_method_loc};
-use happ::utils::iter_rs_fpath;
+use crate::metrics::{compute_closure_loc, compute_closure_nesting, compute_fn_nesting};
+use crate::metrics::{file_functions, ClosureIndex, LlocMetric, LocMetric, Metric};
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::tools::{cached_results, print_nesting_gate, Options};
 
 #[derive(Debug, Clone)]
 pub struct LocFunction {
-    func_name: String,
-    func_file: String,
-    loc: usize,
+    pub func_name: String,
+    pub func_file: String,
+    pub loc: usize,
+    pub lloc: usize,
+    pub max_nesting: usize,
 }
 
 
 impl fmt::Display for LocFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}, {}", self.func_name, self.loc)
+        write!(f, "{}, {}, {}, {}", self.func_name, self.loc, self.lloc, self.max_nesting)
     }
 }
 
@@ -25,7 +26,17 @@ impl fmt::Display for LocFunction {
 impl LocFunction {
 
     pub fn new(func_name:String, func_file: String, loc: usize) -> Self {
-        LocFunction{func_name, func_file, loc}
+        LocFunction{func_name, func_file, loc, lloc: 0, max_nesting: 0}
+    }
+
+    pub fn with_lloc(mut self, lloc: usize) -> Self {
+        self.lloc = lloc;
+        self
+    }
+
+    pub fn with_nesting(mut self, max_nesting: usize) -> Self {
+        self.max_nesting = max_nesting;
+        self
     }
     
     fn name(&self) -> &str {
@@ -35,150 +46,129 @@ impl LocFunction {
     fn file(&self) -> &str {
         &self.func_file
     }
+}
 
-    fn loc(&self) -> usize {
-        self.loc
+
+impl Measured for LocFunction {
+    fn value(&self) -> f64 {
+        self.loc as f64
     }
 }
 
 
-pub struct LocStats {
-    functions: Vec<LocFunction>,
-    is_sorted: bool
+/// The lines of code of the functions of a set of files, see
+/// `analyze_loc`.
+#[derive(Debug, Clone)]
+pub struct LocReport {
+    pub functions: Stats<LocFunction>,
 }
 
-impl LocStats {
 
-    pub fn new() -> Self {
-       LocStats{functions: Vec::new(), is_sorted: false}
+impl LocReport {
+
+    /// The mean logic lines, the LOC which `--expand-macros` changes: the
+    /// physical lines already span the macro invocations.
+    pub fn mean_lloc(&self) -> f64 {
+        if self.functions.is_empty() {
+            return 0.0
+        }
+        let lloc_sum: usize = self.functions.iter().map(|f| f.lloc).sum();
+        lloc_sum as f64 / self.functions.len() as f64
     }
 
-    pub fn add_func(&mut self, func: LocFunction) {
-        self.functions.push(func);
-        self.is_sorted = false;
+    /// Functions whose max nesting depth exceeds `max_nesting`.
+    pub fn nesting_violations(&mut self, max_nesting: usize) -> Vec<&LocFunction> {
+        self.functions.items().iter()
+            .filter(|f| f.max_nesting > max_nesting)
+            .collect()
     }
 
-    pub fn add_funcs(&mut self, funcs: Vec<LocFunction>) {
-        self.functions.extend(funcs);
-        self.is_sorted = false;
+    /// Prints the functions nesting deeper than `max_nesting`, false if
+    /// there is any.
+    pub fn nesting_gate(&mut self, max_nesting: usize) -> bool {
+        let violations = self.nesting_violations(max_nesting).into_iter()
+            .map(|f| (f.name(), f.file(), f.max_nesting))
+            .collect();
+        print_nesting_gate(max_nesting, violations)
     }
 
-    pub fn summary(&mut self) {
+    pub fn summary(&mut self, report: &ReportOptions) {
         println!("######## Function Loc Statistic ########");
-        if self.functions.len() == 0 {
+        if self.functions.is_empty() {
             println!("No function or impl method found!");
             return;
         }
-        println!("FUNC NUM: {}, MEAN: {:.2}", self.functions.len(), self.mean());
-        match self.max() {
-            Some(t) => println!("MAX: {:}", t),
-            _ => ()
-        };
-        match self.min() {
-            Some(t) => println!("MIN: {:}", t),
-            _ => ()
-        };
-        println!("TOP 5:");
-        for locfunc in self.topk(5) {
-            println!("\t{:}", locfunc);
-        }
-    }
-
-    pub fn sort(&mut self) {
-        self.functions.sort_by(
-            |a, b| b.loc.cmp(&a.loc)
-        );
-        self.is_sorted = true;
-    }
-
-    pub fn max(&mut self) -> Option<&LocFunction>{
-        if self.functions.len() == 0 {
-            return None
-        }
-        if !self.is_sorted { self.sort() }
-        Some(&self.functions[0])
-    }
-
-    pub fn min(&mut self) -> Option<&LocFunction> {
-        if self.functions.len() == 0 {
-            return None
+        println!("FUNC NUM: {}, MEAN: {:.2}, MEAN LLOC: {:.2}",
+            self.functions.len(), self.functions.mean(), self.mean_lloc());
+        println!("{}", self.functions.distribution());
+        if let Some(t) = self.functions.max() {
+            println!("MAX: {:}", t);
         }
-        if !self.is_sorted { self.sort() }
-        Some(&self.functions[self.functions.len()-1])
-    }
-
-    pub fn mean(&mut self) -> f64 {
-        if self.functions.len() == 0 {
-            return 0.0
+        if let Some(t) = self.functions.min() {
+            println!("MIN: {:}", t);
         }
-        let mut loc_sum = 0.0;
-        for loc_func in &self.functions {
-            loc_sum += loc_func.loc as f64;
+        println!("TOP {} (NAME, LOC, LLOC, NESTING):", report.top);
+        for locfunc in self.functions.top(report) {
+            println!("\t{:}", locfunc);
         }
-        loc_sum / (self.functions.len() as f64)
-    }
-
-    pub fn topk(&mut self, k: usize) -> &[LocFunction] {
-        if k >= self.functions.len() {
-            &self.functions[0..self.functions.len()]
-        } else {
-            &self.functions[0..k]
+        if let Some(bounds) = &report.buckets {
+            println!("HISTOGRAM (LOC, FUNC NUM):");
+            print!("{}", self.functions.histogram(bounds));
         }
     }
 
 }
 
 
-fn process_loc_file(rs_fpath: &PathBuf) -> Vec<LocFunction> {
-    let mut file = File::open(rs_fpath).unwrap();
-    let mut content = String::new();
-    file.read_to_string(&mut content).unwrap();
+/// The closures and async blocks of a function body as pseudo-functions
+/// named `outer::{closure#N}`, none if they are included in the function.
+fn process_loc_closures(outer: &str, block: &syn::Block, func_file: &str, options: &Options) -> Vec<LocFunction> {
+    if options.include_closures {
+        return vec![]
+    }
+    let index = ClosureIndex::new(outer, block);
+    index.closures().iter()
+        .map(|(name, expr)| {
+            let loc = compute_closure_loc(expr, &index, options);
+            let nesting = compute_closure_nesting(expr, &index).max_depth;
+            LocFunction::new(name.clone(), func_file.to_owned(), loc.ploc())
+                .with_lloc(loc.lloc)
+                .with_nesting(nesting)
+        })
+        .collect()
+}
 
-    let ast = syn::parse_file(&content).unwrap();
 
+/// The LOC of the functions and impl methods of a parsed file, those of its
+/// inline modules included, and of their closures unless they are included
+/// in the functions. Test code is left out.
+pub fn loc_functions(ast: &syn::File, func_file: &str, options: &Options) -> Vec<LocFunction> {
+    let (loc_metric, lloc_metric) = (LocMetric::new(options), LlocMetric::new(options));
     let mut functions: Vec<LocFunction> = vec![];
-
-    for item in &ast.items {
-        match item {
-            syn::Item::Impl(item_impl) => {
-                for impl_item in &item_impl.items {
-                    if let syn::ImplItem::Method(method) = impl_item {
-                        match &*item_impl.self_ty {
-                            syn::Type::Path(syn::TypePath { qself: None, path }) => {
-                                let name = String::from(
-                                    format!("{}::{}",
-                                        path.segments.last().unwrap().ident,
-                                        method.sig.ident
-                                    )
-                                );
-                                let loc = compute_impl_method_loc(method).ploc();
-                                let func_file = String::from(rs_fpath.to_str().unwrap());
-                                functions.push(LocFunction::new(name, func_file, loc));
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-            // A bare function like `fn function(arg: Arg) -> Result { .. }`
-            syn::Item::Fn(item_fn) => {
-                let name = item_fn.sig.ident.to_string();
-                let loc = compute_itemfn_loc(item_fn).ploc();
-                let func_file = String::from(rs_fpath.to_str().unwrap());
-                functions.push(LocFunction::new(name, func_file, loc));
-            }
-            _ => {}
-        }
+    for func in file_functions(ast).into_iter().filter(|func| !func.is_test) {
+        let loc = loc_metric.compute_fn(func.item) as usize;
+        let lloc = lloc_metric.compute_fn(func.item) as usize;
+        let nesting = compute_fn_nesting(func.item, options).max_depth;
+        functions.extend(process_loc_closures(&func.name, func.item.block(), func_file, options));
+        functions.push(
+            LocFunction::new(func.name, func_file.to_owned(), loc)
+                .with_lloc(lloc)
+                .with_nesting(nesting)
+        );
     }
-
     functions
 }
 
 
-pub fn process_loc(path_str: &str) {
-    let mut stats = LocStats::new();
-    for rs_fpath in iter_rs_fpath(path_str) {
-        stats.add_funcs(process_loc_file(&rs_fpath));
-    }
-    stats.summary();
-}
\ No newline at end of file
+/// The LOC of the functions of the `.rs` files under the paths. The files
+/// which do not parse are skipped.
+pub fn analyze_loc<P: AsRef<Path>>(paths: &[P], options: &Options) -> LocReport {
+    let mut functions = Stats::new();
+    functions.extend(cached_results(paths, "loc", options, |ast, func_file| loc_functions(ast, func_file, options)));
+    LocReport{functions}
+}
+
+
+pub fn process_loc(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_loc(&[path_str], options).summary(report);
+}
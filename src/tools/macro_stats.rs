@@ -0,0 +1,110 @@
+use std::fmt;
+use std::path::Path;
+use syn::visit::{self, Visit};
+
+use crate::metrics::{compute_macro_rules, MacroRulesMetrics};
+use crate::utils::parse_rs_files;
+use crate::stats::ReportOptions;
+use crate::options::Options;
+
+
+#[derive(Debug, Clone)]
+pub struct MacroRules {
+    macro_file: String,
+    metrics: MacroRulesMetrics,
+}
+
+
+impl fmt::Display for MacroRules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}!, {}, {}, {}, {}",
+            self.metrics.name, self.metrics.arms, self.metrics.max_repetition_depth,
+            self.metrics.tokens, self.macro_file)
+    }
+}
+
+
+/// `macro_rules!` definitions anywhere in a file, also inside functions.
+#[derive(Default)]
+struct MacroRulesCollector {
+    macros: Vec<MacroRulesMetrics>,
+}
+
+
+impl<'ast> Visit<'ast> for MacroRulesCollector {
+
+    fn visit_item_macro(&mut self, i: &'ast syn::ItemMacro) {
+        if let Some(metrics) = compute_macro_rules(i) {
+            self.macros.push(metrics);
+        }
+        visit::visit_item_macro(self, i);
+    }
+
+}
+
+
+pub struct MacroStats {
+    macros: Vec<MacroRules>,
+}
+
+
+impl Default for MacroStats {
+    fn default() -> Self {
+        MacroStats::new()
+    }
+}
+
+
+impl MacroStats {
+
+    pub fn new() -> Self {
+        MacroStats{macros: Vec::new()}
+    }
+
+    pub fn add_macros(&mut self, macros: Vec<MacroRules>) {
+        self.macros.extend(macros);
+    }
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Macro Statistic ########");
+        if self.macros.is_empty() {
+            println!("No macro_rules! found!");
+            return;
+        }
+        let mean_arms = self.macros.iter().map(|m| m.metrics.arms).sum::<usize>() as f64
+            / (self.macros.len() as f64);
+        println!("MACRO NUM: {}, MEAN ARMS: {:.2}", self.macros.len(), mean_arms);
+        report.order.sort_by_key(&mut self.macros, |m| (m.metrics.arms, m.metrics.tokens));
+        println!("TOP {} (NAME, ARMS, REPETITION DEPTH, TOKENS, FILE):", report.top);
+        for macro_rules in self.macros.iter().take(report.top) {
+            println!("\t{:}", macro_rules);
+        }
+    }
+
+}
+
+
+/// The `macro_rules!` definitions of a parsed file.
+pub fn file_macros(ast: &syn::File, macro_file: &str) -> Vec<MacroRules> {
+    let mut collector = MacroRulesCollector::default();
+    collector.visit_file(ast);
+    collector.macros.into_iter()
+        .map(|metrics| MacroRules{macro_file: macro_file.to_owned(), metrics})
+        .collect()
+}
+
+
+/// The `macro_rules!` definitions of the `.rs` files under the paths. The
+/// files which do not parse are skipped.
+pub fn analyze_macros<P: AsRef<Path>>(paths: &[P], options: &Options) -> MacroStats {
+    let mut stats = MacroStats::new();
+    for (macro_file, ast) in parse_rs_files(paths, options) {
+        stats.add_macros(file_macros(&ast, &macro_file));
+    }
+    stats
+}
+
+
+pub fn process_macros(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_macros(&[path_str], options).summary(report);
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use crate::metrics::{Measurement, MetricRegistry, MetricSummary};
+use crate::options::Options;
+use crate::utils::parse_rs_files;
+use crate::stats::{ReportOptions, Stats};
+
+
+fn print_top(title: &str, measurements: &mut Stats<Measurement>, report: &ReportOptions) {
+    println!("TOP {} {}", report.top, title);
+    for measurement in measurements.top(report) {
+        println!("\t{}", measurement);
+    }
+}
+
+
+/// The values of the metrics of a registry over a set of files, see
+/// `analyze_metrics`.
+#[derive(Debug, Clone)]
+pub struct MetricReport {
+    pub summaries: Vec<MetricSummary>,
+}
+
+
+impl MetricReport {
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        for summary in &mut self.summaries {
+            print_summary(summary, report);
+        }
+    }
+
+}
+
+
+fn print_summary(summary: &mut MetricSummary, report: &ReportOptions) {
+    println!("######## Metric: {} ({}) ########", summary.metric, summary.unit);
+    if summary.files.is_empty() {
+        println!("No file found!");
+        return;
+    }
+    println!("FUNC NUM: {}, FILE NUM: {}, {}: {:.2}",
+        summary.functions.len(), summary.files.len(),
+        summary.aggregation.to_string().to_uppercase(), summary.total()
+    );
+    let metric = summary.metric.to_uppercase();
+    print_top(&format!("FUNCTIONS (NAME, {}):", metric), &mut summary.functions, report);
+    print_top(&format!("FILES (FILE, {}):", metric), &mut summary.files, report);
+}
+
+
+/// Runs the metrics of the registry over the `.rs` files under the paths,
+/// see `MetricRegistry::select` to run only some of them.
+pub fn analyze_metrics<P: AsRef<Path>>(paths: &[P], registry: &MetricRegistry, options: &Options) -> MetricReport {
+    MetricReport{summaries: registry.measure(&parse_rs_files(paths, options))}
+}
+
+
+pub fn process_metrics(path_str: &str, registry: &MetricRegistry, options: &Options, report: &ReportOptions) {
+    analyze_metrics(&[path_str], registry, options).summary(report);
+}
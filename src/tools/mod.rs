@@ -1,7 +1,60 @@
+pub use crate::options::*;
+
+pub mod cache;
+pub use cache::*;
+
 
 pub mod cc_stats;
 pub use cc_stats::*;
 
+pub mod metric_stats;
+pub use metric_stats::*;
+
+pub mod abc_stats;
+pub use abc_stats::*;
+
+pub mod macro_stats;
+pub use macro_stats::*;
+
+pub mod sig_stats;
+pub use sig_stats::*;
+
+pub mod exit_stats;
+pub use exit_stats::*;
+
+pub mod unsafe_stats;
+pub use unsafe_stats::*;
+
+pub mod panic_stats;
+pub use panic_stats::*;
+
+pub mod async_stats;
+pub use async_stats::*;
+
+pub mod deps;
+pub use deps::*;
+
+pub mod coupling;
+pub use coupling::*;
+
+pub mod call_graph;
+pub use call_graph::*;
+
+pub mod dead_code;
+pub use dead_code::*;
+
+pub mod api_stats;
+pub use api_stats::*;
+
+pub mod doc_coverage;
+pub use doc_coverage::*;
+
+pub mod test_stats;
+pub use test_stats::*;
+
+pub mod type_stats;
+pub use type_stats::*;
+
 pub mod loc_func_stats;
 pub use loc_func_stats::*;
 
@@ -12,4 +65,9 @@ pub mod large_dir;
 pub use large_dir::*;
 
 pub mod file_duplicate;
-pub use file_duplicate::*;
\ No newline at end of file
+pub use file_duplicate::*;
+pub mod source;
+pub use source::*;
+
+pub mod watch;
+pub use watch::*;
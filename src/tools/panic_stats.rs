@@ -0,0 +1,205 @@
+use std::path::Path;
+use std::fmt;
+
+use crate::metrics::{compute_itemfn_panics, compute_impl_method_panics, file_functions, FnItem, PanicKind, PanicVisitor};
+use crate::utils::parse_rs_files;
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::options::Options;
+
+
+const PANIC_KINDS: [PanicKind; 5] = [
+    PanicKind::Unwrap, PanicKind::Expect, PanicKind::Macro, PanicKind::Index, PanicKind::Division
+];
+
+
+#[derive(Debug, Clone)]
+pub struct PanicFunction {
+    func_name: String,
+    func_file: String,
+    is_test: bool,
+    panics: PanicVisitor,
+}
+
+
+impl fmt::Display for PanicFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}", self.func_name, self.total())?;
+        for kind in &PANIC_KINDS {
+            write!(f, ", {}", self.panics.count(*kind))?;
+        }
+        Ok(())
+    }
+}
+
+
+impl PanicFunction {
+
+    pub fn new(func_name: String, func_file: String, is_test: bool, panics: PanicVisitor) -> Self {
+        PanicFunction{func_name, func_file, is_test, panics}
+    }
+
+    fn total(&self) -> usize {
+        self.panics.sites.len()
+    }
+}
+
+
+impl Measured for PanicFunction {
+    fn value(&self) -> f64 {
+        self.total() as f64
+    }
+}
+
+
+/// The panic sites of the functions of a set of files, see
+/// `analyze_panics`.
+#[derive(Debug, Clone, Default)]
+pub struct PanicStats {
+    pub functions: Stats<PanicFunction>,
+}
+
+
+impl PanicStats {
+
+    pub fn new() -> Self {
+        PanicStats::default()
+    }
+
+    pub fn add_funcs(&mut self, funcs: Vec<PanicFunction>) {
+        self.functions.extend(funcs);
+    }
+
+    fn print_totals(&self, title: &str, is_test: bool) {
+        let functions: Vec<&PanicFunction> = self.functions.iter()
+            .filter(|f| f.is_test == is_test)
+            .collect();
+        let total: usize = functions.iter().map(|f| f.total()).sum();
+        print!("{}: FUNC NUM: {}, PANIC SITES: {}", title, functions.len(), total);
+        for kind in &PANIC_KINDS {
+            let count: usize = functions.iter().map(|f| f.panics.count(*kind)).sum();
+            print!(", {}: {}", kind.to_string().to_uppercase(), count);
+        }
+        println!();
+    }
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Panic Surface Statistic ########");
+        if self.functions.is_empty() {
+            println!("No function or impl method found!");
+            return;
+        }
+        self.print_totals("PRODUCTION", false);
+        self.print_totals("TEST", true);
+        println!("TOP {} PRODUCTION (NAME, TOTAL, UNWRAP, EXPECT, MACRO, INDEX, DIVISION):", report.top);
+        for panicfunc in self.top_production(report) {
+            println!("\t{:}", panicfunc);
+            for site in &panicfunc.panics.sites {
+                println!("\t\t{} at {}:{}", site.kind, panicfunc.func_file, site.line);
+            }
+        }
+    }
+
+    /// The `--top` production functions with panic sites, in the `--sort`
+    /// order.
+    pub fn top_production(&mut self, report: &ReportOptions) -> Vec<&PanicFunction> {
+        self.functions.ranked(report.order).iter()
+            .filter(|f| !f.is_test && f.total() > 0)
+            .take(report.top)
+            .collect()
+    }
+
+}
+
+
+/// Whether a file is under the `tests/` directory of the crate of the
+/// nearest `Cargo.toml`, the directories above the crate do not count.
+fn is_integration_test(func_file: &str) -> bool {
+    let fpath = Path::new(func_file);
+    fpath.ancestors().skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .and_then(|crate_dir| fpath.strip_prefix(crate_dir).ok())
+        .and_then(|relative| relative.components().next())
+        .map(|c| c.as_os_str() == "tests")
+        .unwrap_or(false)
+}
+
+
+/// The panic sites of the functions and impl methods of a parsed file,
+/// those of its inline modules included. Integration tests under `tests/`
+/// are test code as a whole.
+pub fn panic_functions(ast: &syn::File, func_file: &str) -> Vec<PanicFunction> {
+    let in_test = is_integration_test(func_file);
+    file_functions(ast).into_iter()
+        .map(|func| {
+            let panics = match func.item {
+                FnItem::Fn(item_fn) => compute_itemfn_panics(item_fn),
+                FnItem::Method(method) => compute_impl_method_panics(method),
+            };
+            PanicFunction::new(func.name, func_file.to_owned(), in_test || func.is_test, panics)
+        })
+        .collect()
+}
+
+
+/// The panic sites of the functions of the `.rs` files under the paths.
+/// The files which do not parse are skipped.
+pub fn analyze_panics<P: AsRef<Path>>(paths: &[P], options: &Options) -> PanicStats {
+    let mut stats = PanicStats::new();
+    for (func_file, ast) in parse_rs_files(paths, options) {
+        stats.add_funcs(panic_functions(&ast, &func_file));
+    }
+    stats
+}
+
+
+pub fn process_panics(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_panics(&[path_str], options).summary(report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use syn::parse_quote;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::{analyze_panics, panic_functions};
+
+    #[test]
+    fn test_panic_functions() {
+        let ast: syn::File = parse_quote! {
+            mod imp {
+                fn first(v: &[u8]) -> u8 { v[0] }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                fn parsed() -> usize { "1".parse().unwrap() }
+            }
+        };
+
+        let functions = panic_functions(&ast, "src/lib.rs");
+        let names: Vec<(&str, bool, usize)> = functions.iter()
+            .map(|f| (f.func_name.as_str(), f.is_test, f.total()))
+            .collect();
+        assert_eq!(names, vec![("imp::first", false, 1), ("tests::parsed", true, 1)]);
+        assert!(panic_functions(&ast, "tests/it.rs").iter().all(|f| f.is_test));
+    }
+
+    #[test]
+    fn test_crate_under_tests_dir() {
+        let dir = fixture_crate("panics", &[
+            ("tests/myproj/Cargo.toml", "[package]\nname = \"myproj\"\n"),
+            ("tests/myproj/src/lib.rs", "pub fn first(v: &[u8]) -> u8 { v[0] }\n"),
+            ("tests/myproj/tests/it.rs", "fn parsed() -> usize { \"1\".parse().unwrap() }\n"),
+        ]);
+        let stats = analyze_panics(&[dir.join("tests/myproj")], &Options::default());
+        let mut functions: Vec<(&str, bool)> = stats.functions.iter()
+            .map(|f| (f.func_name.as_str(), f.is_test))
+            .collect();
+        functions.sort();
+        assert_eq!(functions, vec![("first", false), ("parsed", true)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
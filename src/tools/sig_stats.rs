@@ -0,0 +1,150 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::metrics::{compute_signature, file_functions, SignatureMetrics};
+use crate::utils::parse_rs_files;
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::options::Options;
+
+#[derive(Debug, Clone)]
+pub struct SigFunction {
+    func_name: String,
+    func_file: String,
+    sig: SignatureMetrics,
+}
+
+
+impl fmt::Display for SigFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {}, {}, {}, {}",
+            self.func_name,
+            self.sig.inputs,
+            self.sig.generic_types,
+            self.sig.lifetimes,
+            self.sig.where_predicates,
+            self.sig.impl_trait_args,
+            self.sig.max_arg_type_depth,
+            self.sig.return_type_depth,
+        )
+    }
+}
+
+
+impl SigFunction {
+
+    pub fn new(func_name: String, func_file: String, sig: SignatureMetrics) -> Self {
+        SigFunction{func_name, func_file, sig}
+    }
+}
+
+
+/// Ranked by long parameter lists first, then heavy generics, then deeply
+/// nested types.
+impl Measured for SigFunction {
+    fn value(&self) -> f64 {
+        self.sig.inputs as f64
+    }
+
+    fn tie_breaks(&self) -> Vec<f64> {
+        vec![
+            (self.sig.generics() + self.sig.where_predicates + self.sig.impl_trait_args) as f64,
+            self.sig.max_arg_type_depth.max(self.sig.return_type_depth) as f64,
+        ]
+    }
+}
+
+
+/// The signatures of the functions of a set of files, see `analyze_sig`.
+#[derive(Debug, Clone, Default)]
+pub struct SigStats {
+    pub functions: Stats<SigFunction>,
+}
+
+
+impl SigStats {
+
+    pub fn new() -> Self {
+        SigStats::default()
+    }
+
+    pub fn add_funcs(&mut self, funcs: Vec<SigFunction>) {
+        self.functions.extend(funcs);
+    }
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Function Signature Statistic ########");
+        if self.functions.is_empty() {
+            println!("No function or impl method found!");
+            return;
+        }
+        println!("FUNC NUM: {}, MEAN ARGS: {:.2}, MEAN GENERICS: {:.2}",
+            self.functions.len(), self.functions.mean(), self.mean_generics());
+        println!("TOP {} (NAME, ARGS, TYPES, LIFETIMES, WHERE, IMPL TRAIT, ARG DEPTH, RET DEPTH, FILE):", report.top);
+        for sigfunc in self.functions.top(report) {
+            println!("\t{:}, {}", sigfunc, sigfunc.func_file);
+        }
+    }
+
+    pub fn mean_generics(&self) -> f64 {
+        if self.functions.is_empty() {
+            return 0.0
+        }
+        let generics_sum: usize = self.functions.iter().map(|f| f.sig.generics()).sum();
+        generics_sum as f64 / (self.functions.len() as f64)
+    }
+
+}
+
+
+/// The signatures of the functions and impl methods of a parsed file, those
+/// of its inline modules included. Test code is left out.
+pub fn sig_functions(ast: &syn::File, func_file: &str) -> Vec<SigFunction> {
+    file_functions(ast).into_iter()
+        .filter(|func| !func.is_test)
+        .map(|func| SigFunction::new(func.name, func_file.to_owned(), compute_signature(func.item.sig())))
+        .collect()
+}
+
+
+/// The signatures of the functions of the `.rs` files under the paths. The
+/// files which do not parse are skipped.
+pub fn analyze_sig<P: AsRef<Path>>(paths: &[P], options: &Options) -> SigStats {
+    let mut stats = SigStats::new();
+    for (func_file, ast) in parse_rs_files(paths, options) {
+        stats.add_funcs(sig_functions(&ast, &func_file));
+    }
+    stats
+}
+
+
+pub fn process_sig(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_sig(&[path_str], options).summary(report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::sig_functions;
+
+    #[test]
+    fn test_sig_inline_mods() {
+        let ast: syn::File = parse_quote! {
+            mod imp {
+                pub fn open(path: &Path, mode: Mode) -> File { todo!() }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                #[test]
+                fn test_open() {}
+            }
+        };
+
+        let functions = sig_functions(&ast, "fs.rs");
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].func_name, "imp::open");
+        assert_eq!(functions[0].sig.inputs, 2);
+    }
+}
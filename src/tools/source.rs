@@ -0,0 +1,115 @@
+use crate::metrics::compute_file_loc;
+use crate::tools::{abc_functions, cc_functions, exit_functions, loc_functions};
+use crate::tools::{file_macros, panic_functions, sig_functions, unsafe_sites};
+use crate::tools::{AbcFunction, CCFunction, ExitFunction, LocFunction, Options};
+use crate::tools::{MacroRules, PanicFunction, SigFunction, UnsafeSite};
+use crate::utils::parse_rs_file;
+
+
+/// The file name the functions of an `analyze_source` report point to.
+pub const SOURCE_FILE: &str = "<source>";
+
+
+/// The metrics of a single piece of source, see `analyze_source`. The
+/// functions are in the order of the source, with the closures of a function
+/// before it.
+#[derive(Debug, Clone)]
+pub struct SourceReport {
+    pub loc: usize,
+    pub lloc: usize,
+    pub cc: Vec<CCFunction>,
+    pub loc_functions: Vec<LocFunction>,
+    pub abc: Vec<AbcFunction>,
+    pub exits: Vec<ExitFunction>,
+    pub sig: Vec<SigFunction>,
+    pub panics: Vec<PanicFunction>,
+    pub unsafe_sites: Vec<UnsafeSite>,
+    pub macros: Vec<MacroRules>,
+}
+
+
+/// Analyzes source code given as a string, as an editor buffer or a test
+/// would, with the default options.
+pub fn analyze_source(source: &str) -> syn::Result<SourceReport> {
+    analyze_source_with(source, &Options::default())
+}
+
+
+pub fn analyze_source_with(source: &str, options: &Options) -> syn::Result<SourceReport> {
+    let ast = parse_rs_file(source, options)?;
+    let file_loc = compute_file_loc(&ast);
+    Ok(SourceReport{
+        loc: file_loc.ploc(),
+        lloc: file_loc.lloc,
+        cc: cc_functions(&ast, SOURCE_FILE, options),
+        loc_functions: loc_functions(&ast, SOURCE_FILE, options),
+        abc: abc_functions(&ast, SOURCE_FILE),
+        exits: exit_functions(&ast, SOURCE_FILE),
+        sig: sig_functions(&ast, SOURCE_FILE),
+        panics: panic_functions(&ast, SOURCE_FILE),
+        unsafe_sites: unsafe_sites(&ast, source, SOURCE_FILE),
+        macros: file_macros(&ast, SOURCE_FILE),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{analyze_source, analyze_source_with};
+    use crate::tools::Options;
+
+    #[test]
+    fn test_analyze_source() {
+        let source = "
+            fn sign(x: i64) -> i64 {
+                if x > 0 {
+                    1
+                } else if x < 0 {
+                    -1
+                } else {
+                    0
+                }
+            }
+
+            impl Point {
+                fn norm(&self) -> f64 {
+                    let squares = self.coords.iter().map(|c| {
+                        if *c > 0.0 { c * c } else { -c * -c }
+                    });
+                    squares.sum::<f64>().sqrt()
+                }
+
+                fn first(&self) -> f64 {
+                    // SAFETY: a `Point` has at least one coordinate
+                    unsafe { *self.coords.get_unchecked(0) }
+                }
+            }
+
+            macro_rules! point {
+                ($($c:expr),*) => { Point { coords: vec![$($c),*] } };
+            }
+        ";
+        let report = analyze_source(source).unwrap();
+        let names: Vec<&str> = report.cc.iter().map(|f| f.func_name.as_str()).collect();
+        assert_eq!(names, vec!["sign", "Point::norm::{closure#0}", "Point::norm", "Point::first"]);
+        assert_eq!(report.cc[0].cc, 3);
+        assert_eq!(report.cc[1].cc, 2);
+        assert_eq!(report.cc[2].cc, 1);
+        assert_eq!(report.loc_functions[0].loc, 9);
+        assert_eq!(report.exits[0].exits.nexits(), 1);
+        assert_eq!(report.sig.len(), 3);
+        assert_eq!(report.panics.len(), 3);
+        assert_eq!(report.unsafe_sites.len(), 2);  // the block and the dereference
+        assert_eq!(report.macros.len(), 1);
+
+        // the closure counts in its parent only, not twice
+        let options = Options{include_closures: true, ..Options::default()};
+        let report = analyze_source_with(source, &options).unwrap();
+        let names: Vec<&str> = report.cc.iter().map(|f| f.func_name.as_str()).collect();
+        assert_eq!(names, vec!["sign", "Point::norm", "Point::first"]);
+        assert_eq!(report.cc[1].cc, 2);
+
+        assert!(analyze_source("fn broken( {").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::fmt;
+use syn::visit::{self, Visit};
+
+use crate::metrics::{compute_file_loc, count_doc_tests};
+use crate::modules::{Module, ModuleTree};
+use crate::options::Options;
+use crate::references::ReferenceIndex;
+use crate::stats::ReportOptions;
+use crate::utils::{is_cfg_test, is_test_fn};
+
+
+/// Tests of a production module, including the ones in its test modules,
+/// or of an integration test crate.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleTests {
+    test_fns: usize,
+    doc_tests: usize,
+    production_loc: usize,
+    test_loc: usize,
+}
+
+
+impl fmt::Display for ModuleTests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {:.2}",
+            self.test_fns, self.doc_tests, self.production_loc, self.test_loc, self.ratio())
+    }
+}
+
+
+impl ModuleTests {
+
+    /// Test LOC per production LOC.
+    pub fn ratio(&self) -> f64 {
+        match self.production_loc {
+            0 => 0.0,
+            loc => self.test_loc as f64 / (loc as f64),
+        }
+    }
+}
+
+
+/// Doc comment lines of a module, without its inline child modules.
+#[derive(Default)]
+struct DocCollector {
+    lines: Vec<String>,
+}
+
+
+impl<'ast> Visit<'ast> for DocCollector {
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if i.content.is_none() {
+            visit::visit_item_mod(self, i);
+        }
+    }
+
+    fn visit_attribute(&mut self, i: &'ast syn::Attribute) {
+        if let Ok(syn::Meta::NameValue(meta)) = i.parse_meta() {
+            if let (true, syn::Lit::Str(lit)) = (meta.path.is_ident("doc"), &meta.lit) {
+                self.lines.push(lit.value());
+            }
+        }
+    }
+
+}
+
+
+fn items_loc(items: Vec<syn::Item>) -> usize {
+    if items.is_empty() {
+        return 0
+    }
+    compute_file_loc(&syn::File{shebang: None, attrs: vec![], items}).ploc()
+}
+
+
+fn is_inline_mod(item: &syn::Item) -> bool {
+    matches!(item, syn::Item::Mod(syn::ItemMod { content: Some(_), .. }))
+}
+
+
+fn is_test_item(item: &syn::Item) -> bool {
+    match item {
+        syn::Item::Fn(item_fn) => is_test_fn(&item_fn.attrs) || is_cfg_test(&item_fn.attrs),
+        _ => false
+    }
+}
+
+
+fn is_pub(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+
+pub struct TestStats {
+    modules: BTreeMap<String, ModuleTests>,
+    test_modules: usize,
+    integration_files: usize,
+    untested: Vec<(String, String)>,
+}
+
+
+impl Default for TestStats {
+    fn default() -> Self {
+        TestStats::new()
+    }
+}
+
+
+impl TestStats {
+
+    pub fn new() -> Self {
+        TestStats{modules: BTreeMap::new(), test_modules: 0, integration_files: 0, untested: Vec::new()}
+    }
+
+    /// The production module the tests of `module` belong to, or the root
+    /// of its integration test crate.
+    fn owner(tree: &ModuleTree, module: &Module) -> String {
+        let mut name = module.name.as_str();
+        while let Some(pos) = name.rfind("::") {
+            if !tree.get(name).map(|m| m.is_test).unwrap_or(true) {
+                break;
+            }
+            name = &name[..pos];
+        }
+        name.to_owned()
+    }
+
+    pub fn add_tree(&mut self, tree: &ModuleTree, index: &ReferenceIndex) {
+        for module in &tree.modules {
+            let owner = TestStats::owner(tree, module);
+            let items: Vec<&syn::Item> = module.items.iter()
+                .filter(|item| !is_inline_mod(item))
+                .collect();
+            let test_fns = items.iter().filter(|item| match item {
+                syn::Item::Fn(item_fn) => is_test_fn(&item_fn.attrs),
+                _ => false
+            }).count();
+
+            let (production_loc, test_loc) = if module.is_test {
+                (0, items_loc(items.iter().cloned().cloned().collect()))
+            } else {
+                let (tests, production): (Vec<&syn::Item>, Vec<&syn::Item>) = items.iter()
+                    .partition(|item| is_test_item(item));
+                (items_loc(production.into_iter().cloned().collect()), items_loc(tests.into_iter().cloned().collect()))
+            };
+
+            let mut doc_collector = DocCollector::default();
+            for attr in &module.attrs {
+                doc_collector.visit_attribute(attr);
+            }
+            for item in &items {
+                doc_collector.visit_item(item);
+            }
+            let doc_tests = count_doc_tests(&doc_collector.lines.join("\n"));
+
+            if module.is_test {
+                match module.name.contains("::") {
+                    true => self.test_modules += 1,
+                    false => self.integration_files += 1,
+                }
+            } else {
+                self.add_untested(module, index);
+            }
+
+            let tests = self.modules.entry(owner).or_default();
+            tests.test_fns += test_fns;
+            tests.doc_tests += doc_tests;
+            tests.production_loc += production_loc;
+            tests.test_loc += test_loc;
+        }
+    }
+
+    /// Public fns and methods of a production module no test refers to.
+    fn add_untested(&mut self, module: &Module, index: &ReferenceIndex) {
+        let file = module.file.to_str().unwrap();
+        for item in &module.items {
+            match item {
+                syn::Item::Fn(item_fn) if is_pub(&item_fn.vis) && !is_test_item(item) => {
+                    let ident = &item_fn.sig.ident;
+                    let tested = index.references(&ident.to_string()).iter().any(|r| r.in_test);
+                    if !tested {
+                        let location = format!("{}:{}", file, ident.span().start().line);
+                        self.untested.push((format!("{}::{}", module.name, ident), location));
+                    }
+                }
+                syn::Item::Impl(item_impl) if item_impl.trait_.is_none() => {
+                    let self_ty = match &*item_impl.self_ty {
+                        syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                            path.segments.last().unwrap().ident.to_string()
+                        }
+                        _ => continue
+                    };
+                    for impl_item in &item_impl.items {
+                        let method = match impl_item {
+                            syn::ImplItem::Method(method) if is_pub(&method.vis) => method,
+                            _ => continue
+                        };
+                        let ident = &method.sig.ident;
+                        let tested = index.references(&ident.to_string()).iter()
+                            .filter(|r| r.in_test)
+                            .any(|r| r.is_method || r.qualifier.as_deref() == Some(self_ty.as_str()));
+                        if !tested {
+                            let location = format!("{}:{}", file, ident.span().start().line);
+                            self.untested.push((format!("{}::{}::{}", module.name, self_ty, ident), location));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn summary(&self, report: &ReportOptions) {
+        println!("######## Test Statistic ########");
+        if self.modules.is_empty() {
+            println!("No crate root (lib.rs or main.rs) found!");
+            return;
+        }
+        let mut total = ModuleTests::default();
+        for tests in self.modules.values() {
+            total.test_fns += tests.test_fns;
+            total.doc_tests += tests.doc_tests;
+            total.production_loc += tests.production_loc;
+            total.test_loc += tests.test_loc;
+        }
+        println!("TEST FN NUM: {}, DOC TEST NUM: {}, CFG(TEST) MODULE NUM: {}, INTEGRATION TEST FILE NUM: {}",
+            total.test_fns, total.doc_tests, self.test_modules, self.integration_files);
+        println!("PRODUCTION LOC: {}, TEST LOC: {}, TEST/CODE RATIO: {:.2}",
+            total.production_loc, total.test_loc, total.ratio());
+        let mut modules: Vec<(&String, &ModuleTests)> = self.modules.iter().collect();
+        report.order.sort_by(&mut modules, |(_, a), (_, b)| a.ratio().total_cmp(&b.ratio()));
+        println!("TOP {} MODULES BY RATIO (NAME, TEST FNS, DOC TESTS, PRODUCTION LOC, TEST LOC, RATIO):", report.top);
+        for (name, tests) in modules.iter().take(report.top) {
+            println!("\t{}, {:}", name, tests);
+        }
+        println!("UNTESTED PUB FNS (NAME, LOCATION): {}", self.untested.len());
+        for (name, location) in &self.untested {
+            println!("\t{}, {}", name, location);
+        }
+    }
+
+}
+
+
+/// The tests of the modules under `path`, its integration tests included.
+pub fn analyze_tests(path: &Path, options: &Options) -> TestStats {
+    let mut tree = ModuleTree::from_path(path, options);
+    tree.load_test_crates(path);
+    let index = ReferenceIndex::from_tree(&tree);
+    let mut stats = TestStats::new();
+    stats.add_tree(&tree, &index);
+    stats
+}
+
+
+pub fn process_tests(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_tests(Path::new(path_str), options).summary(report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use crate::options::Options;
+    use crate::utils::fixture_crate;
+    use super::analyze_tests;
+
+    #[test]
+    fn test_analyze_tests() {
+        let dir = fixture_crate("tests", &[
+            ("src/lib.rs", "pub mod shapes;\n"),
+            ("src/shapes.rs", r#"/// A circle.
+///
+/// ```
+/// let c = fixture::shapes::Circle::new(1.0);
+/// ```
+pub struct Circle {
+    pub r: f64,
+}
+
+impl Circle {
+    pub fn new(r: f64) -> Self {
+        Circle { r }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.r * self.r
+    }
+}
+
+pub fn unit() -> Circle {
+    Circle::new(1.0)
+}
+
+pub fn scale(c: &Circle, k: f64) -> Circle {
+    Circle { r: c.r * k }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert_eq!(Circle::new(2.0).r, 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_unit() {
+        assert_eq!(unit().r, 1.0);
+    }
+}
+"#),
+            ("tests/it.rs", "use fixture::shapes::unit;\n\n#[test]\nfn test_it() {\n    assert!(unit().r > 0.0);\n}\n"),
+        ]);
+        let stats = analyze_tests(&dir, &Options::default());
+
+        let modules: Vec<(&str, usize, usize, usize, usize)> = stats.modules.iter()
+            .map(|(name, t)| (name.as_str(), t.test_fns, t.doc_tests, t.production_loc, t.test_loc))
+            .collect();
+        // the `#[test]` and `#[tokio::test]` fns of `shapes::tests` count for `shapes`
+        assert_eq!(modules, [
+            ("fixture", 0, 0, 1, 0),
+            ("fixture::shapes", 2, 1, 22, 9),
+            ("it", 1, 0, 0, 5),
+        ]);
+        assert_eq!(stats.modules["fixture::shapes"].ratio(), 9.0 / 22.0);
+        assert_eq!(stats.modules["it"].ratio(), 0.0);
+        assert_eq!((stats.test_modules, stats.integration_files), (1, 1));
+
+        // `Circle::new` is called by a test, `area` and `scale` are not
+        let untested: Vec<(&str, &str)> = stats.untested.iter()
+            .map(|(name, location)| (name.as_str(), location.rsplit('/').next().unwrap()))
+            .collect();
+        assert_eq!(untested, [
+            ("fixture::shapes::Circle::area", "shapes.rs:15"),
+            ("fixture::shapes::scale", "shapes.rs:24"),
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::metrics::{compute_struct_metrics, compute_enum_metrics};
+use crate::metrics::{compute_union_metrics, compute_trait_metrics, TypeKind, TypeMetrics};
+use crate::metrics::{compute_impl_method_cohesion, lcom4_groups, lcom_hs, CohesionVisitor};
+use crate::utils::{is_cfg_test, parse_rs_files};
+use crate::stats::ReportOptions;
+use crate::options::Options;
+
+
+#[derive(Debug, Clone)]
+pub struct TypeEntry {
+    type_file: String,
+    metrics: TypeMetrics,
+    impl_blocks: usize,
+    methods: usize,
+    trait_impls: usize,
+    fields: Vec<String>,
+    groups: Vec<Vec<String>>,
+    lcom_hs: Option<f64>,
+}
+
+
+impl fmt::Display for TypeEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {}, {}, {}, {}, {}, {}",
+            self.metrics.name,
+            self.metrics.kind,
+            self.methods,
+            self.metrics.members,
+            self.metrics.generics,
+            self.metrics.derives,
+            self.impl_blocks,
+            self.trait_impls,
+            self.metrics.lines,
+        )
+    }
+}
+
+
+impl TypeEntry {
+
+    pub fn new(type_file: String, metrics: TypeMetrics) -> Self {
+        TypeEntry{
+            type_file, metrics,
+            impl_blocks: 0, methods: 0, trait_impls: 0,
+            fields: vec![], groups: vec![], lcom_hs: None,
+        }
+    }
+
+    pub fn with_fields(mut self, fields: &syn::Fields) -> Self {
+        self.fields = fields.iter().enumerate()
+            .map(|(idx, field)| match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => idx.to_string(),
+            })
+            .collect();
+        self
+    }
+
+    pub fn lcom4(&self) -> usize {
+        self.groups.len()
+    }
+
+    fn cohesion(&self) -> String {
+        let lcom_hs = match self.lcom_hs {
+            Some(lcom_hs) => format!("{:.2}", lcom_hs),
+            None => "-".to_owned()
+        };
+        format!("{}, {}, {}, {}", self.metrics.name, self.lcom4(), lcom_hs, self.methods)
+    }
+}
+
+
+/// Inherent impl blocks, their methods and the trait impls of a type
+/// name, or the impls of a trait name.
+#[derive(Debug, Clone, Default)]
+pub struct ImplCount {
+    impl_blocks: usize,
+    methods: usize,
+    trait_impls: usize,
+}
+
+
+pub struct TypeStats {
+    types: Vec<TypeEntry>,
+    impls: BTreeMap<String, ImplCount>,
+    // methods with a `self` receiver in the inherent impls of a type
+    cohesion: BTreeMap<String, Vec<(String, CohesionVisitor)>>,
+}
+
+
+impl Default for TypeStats {
+    fn default() -> Self {
+        TypeStats::new()
+    }
+}
+
+
+impl TypeStats {
+
+    pub fn new() -> Self {
+        TypeStats{types: Vec::new(), impls: BTreeMap::new(), cohesion: BTreeMap::new()}
+    }
+
+    fn add_items(&mut self, items: &[syn::Item], type_file: &str) {
+        for item in items {
+            let metrics = match item {
+                syn::Item::Struct(item_struct) => {
+                    let entry = TypeEntry::new(type_file.to_owned(), compute_struct_metrics(item_struct));
+                    self.types.push(entry.with_fields(&item_struct.fields));
+                    continue;
+                }
+                syn::Item::Enum(item_enum) => compute_enum_metrics(item_enum),
+                syn::Item::Union(item_union) => compute_union_metrics(item_union),
+                syn::Item::Trait(item_trait) => {
+                    let methods = item_trait.items.iter()
+                        .filter(|i| matches!(i, syn::TraitItem::Method(_)))
+                        .count();
+                    self.impls.entry(item_trait.ident.to_string()).or_default().methods += methods;
+                    compute_trait_metrics(item_trait)
+                }
+                syn::Item::Impl(item_impl) => {
+                    self.add_impl(item_impl);
+                    continue;
+                }
+                // An inline module, but not `#[cfg(test)] mod tests { .. }`
+                syn::Item::Mod(item_mod) if !is_cfg_test(&item_mod.attrs) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        self.add_items(items, type_file);
+                    }
+                    continue;
+                }
+                _ => continue
+            };
+            self.types.push(TypeEntry::new(type_file.to_owned(), metrics));
+        }
+    }
+
+    fn add_impl(&mut self, item_impl: &syn::ItemImpl) {
+        let self_ty = match &*item_impl.self_ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                path.segments.last().unwrap().ident.to_string()
+            }
+            _ => return
+        };
+        match &item_impl.trait_ {
+            Some((_, trait_path, _)) => {
+                self.impls.entry(self_ty).or_default().trait_impls += 1;
+                // a trait counts how often it is implemented
+                let trait_name = trait_path.segments.last().unwrap().ident.to_string();
+                self.impls.entry(trait_name).or_default().trait_impls += 1;
+            }
+            None => {
+                let methods = item_impl.items.iter()
+                    .filter(|i| matches!(i, syn::ImplItem::Method(_)))
+                    .count();
+                let methods_usage = self.cohesion.entry(self_ty.clone()).or_default();
+                for impl_item in &item_impl.items {
+                    if let syn::ImplItem::Method(method) = impl_item {
+                        if method.sig.receiver().is_some() {
+                            let name = method.sig.ident.to_string();
+                            methods_usage.push((name, compute_impl_method_cohesion(method)));
+                        }
+                    }
+                }
+                let count = self.impls.entry(self_ty).or_default();
+                count.impl_blocks += 1;
+                count.methods += methods;
+            }
+        }
+    }
+
+    /// Attaches the impls collected over the whole input to the types.
+    pub fn resolve_impls(&mut self) {
+        for entry in &mut self.types {
+            let count = self.impls.get(&entry.metrics.name).cloned().unwrap_or_default();
+            entry.impl_blocks = count.impl_blocks;
+            entry.methods = count.methods;
+            entry.trait_impls = count.trait_impls;
+            if let Some(methods) = self.cohesion.get(&entry.metrics.name) {
+                entry.groups = lcom4_groups(methods);
+                entry.lcom_hs = lcom_hs(methods, &entry.fields);
+            }
+        }
+    }
+
+    fn mean<F: Fn(&TypeEntry) -> usize>(&self, f: F) -> f64 {
+        if self.types.is_empty() {
+            return 0.0
+        }
+        self.types.iter().map(f).sum::<usize>() as f64 / (self.types.len() as f64)
+    }
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Type Statistic ########");
+        if self.types.is_empty() {
+            println!("No struct, enum, union or trait found!");
+            return;
+        }
+        let count_kind = |kind: TypeKind| self.types.iter().filter(|t| t.metrics.kind == kind).count();
+        println!("TYPE NUM: {}, STRUCT: {}, ENUM: {}, UNION: {}, TRAIT: {}",
+            self.types.len(),
+            count_kind(TypeKind::Struct),
+            count_kind(TypeKind::Enum),
+            count_kind(TypeKind::Union),
+            count_kind(TypeKind::Trait),
+        );
+        println!("MEAN NOM: {:.2}, MEAN FIELDS: {:.2}",
+            self.mean(|t| t.methods), self.mean(|t| t.metrics.members));
+
+        let header = "NAME, KIND, NOM, FIELDS, GENERICS, DERIVES, IMPLS, TRAIT IMPLS, LINES, FILE";
+        report.order.sort_by_key(&mut self.types, |t| t.methods);
+        println!("TOP {} BY NOM ({}):", report.top, header);
+        for entry in self.types.iter().take(report.top) {
+            println!("\t{:}, {}", entry, entry.type_file);
+        }
+        report.order.sort_by_key(&mut self.types, |t| t.metrics.members);
+        println!("TOP {} BY FIELDS ({}):", report.top, header);
+        for entry in self.types.iter().take(report.top) {
+            println!("\t{:}, {}", entry, entry.type_file);
+        }
+
+        report.order.sort_by_key(&mut self.types, |t| t.lcom4());
+        let split_candidates = self.types.iter().filter(|t| t.lcom4() > 1).count();
+        println!("SPLIT CANDIDATES (LCOM4 > 1): {}", split_candidates);
+        println!("TOP {} BY LCOM4 (NAME, LCOM4, LCOM-HS, NOM):", report.top);
+        for entry in self.types.iter().take(report.top) {
+            println!("\t{}", entry.cohesion());
+            if entry.lcom4() > 1 {
+                for group in &entry.groups {
+                    println!("\t\t{{{}}}", group.join(", "));
+                }
+            }
+        }
+    }
+
+}
+
+
+/// The types of the `.rs` files under the paths with their impls. The files
+/// which do not parse are skipped.
+pub fn analyze_types<P: AsRef<Path>>(paths: &[P], options: &Options) -> TypeStats {
+    let mut stats = TypeStats::new();
+    for (type_file, ast) in parse_rs_files(paths, options) {
+        stats.add_items(&ast.items, &type_file);
+    }
+    stats.resolve_impls();
+    stats
+}
+
+
+pub fn process_types(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_types(&[path_str], options).summary(report);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use syn::parse_quote;
+    use super::TypeStats;
+
+    #[test]
+    fn test_types_inline_mods() {
+        let ast: syn::File = parse_quote! {
+            mod shapes {
+                pub struct Point { x: f64, y: f64 }
+
+                impl Point {
+                    fn norm(&self) -> f64 { self.x + self.y }
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                struct Fixture;
+            }
+        };
+
+        let mut stats = TypeStats::new();
+        stats.add_items(&ast.items, "shapes.rs");
+        stats.resolve_impls();
+        let names: Vec<&str> = stats.types.iter().map(|t| t.metrics.name.as_str()).collect();
+        assert_eq!(names, vec!["Point"]);
+        assert_eq!(stats.types[0].methods, 1);
+    }
+}
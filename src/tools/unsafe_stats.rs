@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::metrics::{compute_file_loc, compute_file_unsafe, UnsafeUsage};
+use crate::utils::{find_crate_name, parse_rs_sources};
+use crate::stats::ReportOptions;
+use crate::options::Options;
+
+
+#[derive(Debug, Clone)]
+pub struct UnsafeSite {
+    file_name: String,
+    usage: UnsafeUsage,
+}
+
+
+impl fmt::Display for UnsafeSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}:{}, {}, {}, {}",
+            self.usage.kind,
+            self.file_name,
+            self.usage.line,
+            self.usage.func.as_deref().unwrap_or("-"),
+            self.usage.stmts,
+            if self.usage.has_safety_comment { "yes" } else { "no" },
+        )
+    }
+}
+
+
+/// Unsafe usages and LOC of one module (file) or one crate.
+#[derive(Debug, Clone, Default)]
+pub struct UnsafeDensity {
+    name: String,
+    unsafe_num: usize,
+    loc: usize,
+}
+
+
+impl fmt::Display for UnsafeDensity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}, {}, {:.2}", self.name, self.unsafe_num, self.loc, self.per_kloc())
+    }
+}
+
+
+impl UnsafeDensity {
+
+    /// Unsafe usages per 1000 lines of code.
+    fn per_kloc(&self) -> f64 {
+        if self.loc == 0 {
+            return 0.0
+        }
+        self.unsafe_num as f64 * 1000.0 / self.loc as f64
+    }
+}
+
+
+pub struct UnsafeStats {
+    sites: Vec<UnsafeSite>,
+    modules: Vec<UnsafeDensity>,
+    crates: BTreeMap<String, UnsafeDensity>,
+}
+
+
+impl Default for UnsafeStats {
+    fn default() -> Self {
+        UnsafeStats::new()
+    }
+}
+
+
+impl UnsafeStats {
+
+    pub fn new() -> Self {
+        UnsafeStats{sites: Vec::new(), modules: Vec::new(), crates: BTreeMap::new()}
+    }
+
+    pub fn add_module(&mut self, crate_name: String, module: UnsafeDensity, sites: Vec<UnsafeSite>) {
+        let krate = self.crates.entry(crate_name.clone())
+            .or_insert_with(|| UnsafeDensity{name: crate_name, ..Default::default()});
+        krate.unsafe_num += module.unsafe_num;
+        krate.loc += module.loc;
+        self.modules.push(module);
+        self.sites.extend(sites);
+    }
+
+    pub fn summary(&mut self, report: &ReportOptions) {
+        println!("######## Unsafe Code Statistic ########");
+        if self.modules.is_empty() {
+            println!("No .rs file found!");
+            return;
+        }
+        let missing_safety = self.sites.iter()
+            .filter(|s| !s.usage.has_safety_comment)
+            .count();
+        println!("UNSAFE NUM: {}, WITHOUT SAFETY COMMENT: {}", self.sites.len(), missing_safety);
+        if self.sites.is_empty() {
+            return;
+        }
+        println!("UNSAFE USAGES (KIND, LOCATION, FUNCTION, STMTS, SAFETY COMMENT):");
+        for site in &self.sites {
+            println!("\t{:}", site);
+        }
+        println!("PER CRATE (CRATE, UNSAFE, LOC, UNSAFE PER KLOC):");
+        for krate in self.crates.values() {
+            println!("\t{:}", krate);
+        }
+        report.order.sort_by(&mut self.modules, |a, b| a.per_kloc().total_cmp(&b.per_kloc()));
+        println!("TOP {} MODULES (MODULE, UNSAFE, LOC, UNSAFE PER KLOC):", report.top);
+        for module in self.modules.iter().filter(|m| m.unsafe_num > 0).take(report.top) {
+            println!("\t{:}", module);
+        }
+    }
+
+}
+
+
+/// The unsafe code of a parsed file, `content` is its source for the
+/// `// SAFETY:` comments.
+pub fn unsafe_sites(ast: &syn::File, content: &str, file_name: &str) -> Vec<UnsafeSite> {
+    compute_file_unsafe(ast, content)
+        .into_iter()
+        .map(|usage| UnsafeSite{file_name: file_name.to_owned(), usage})
+        .collect()
+}
+
+
+fn process_unsafe_file(file_name: String, content: &str, ast: &syn::File, stats: &mut UnsafeStats) {
+    let sites = unsafe_sites(ast, content, &file_name);
+    let crate_name = find_crate_name(Path::new(&file_name));
+    let module = UnsafeDensity{
+        name: file_name,
+        unsafe_num: sites.len(),
+        loc: compute_file_loc(ast).ploc(),
+    };
+
+    stats.add_module(crate_name, module, sites);
+}
+
+
+/// The unsafe code of the `.rs` files under the paths, per crate. The files
+/// which do not parse are skipped.
+pub fn analyze_unsafe<P: AsRef<Path>>(paths: &[P], options: &Options) -> UnsafeStats {
+    let mut stats = UnsafeStats::new();
+    for (file_name, content, ast) in parse_rs_sources(paths, options) {
+        process_unsafe_file(file_name, &content, &ast, &mut stats);
+    }
+    stats
+}
+
+
+pub fn process_unsafe(path_str: &str, options: &Options, report: &ReportOptions) {
+    analyze_unsafe(&[path_str], options).summary(report);
+}
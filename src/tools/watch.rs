@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::stats::{Measured, ReportOptions, Stats};
+use crate::tools::{cc_functions, loc_functions, CCFunction, CcReport, LocFunction, LocReport, Options};
+use crate::utils::{iter_rs_fpath, parse_rs_file};
+
+
+/// The functions `happ watch` keeps up to date, one kind per subcommand.
+pub trait Watched: Measured + Clone + fmt::Display {
+
+    /// Name of the function, to match it across runs.
+    fn key(&self) -> &str;
+
+    fn compute(ast: &syn::File, func_file: &str, options: &Options) -> Vec<Self>;
+
+    fn summary(functions: Stats<Self>, report: &ReportOptions);
+}
+
+
+impl Watched for CCFunction {
+
+    fn key(&self) -> &str {
+        &self.func_name
+    }
+
+    fn compute(ast: &syn::File, func_file: &str, options: &Options) -> Vec<Self> {
+        cc_functions(ast, func_file, options)
+    }
+
+    fn summary(functions: Stats<Self>, report: &ReportOptions) {
+        CcReport{functions}.summary(report)
+    }
+}
+
+
+impl Watched for LocFunction {
+
+    fn key(&self) -> &str {
+        &self.func_name
+    }
+
+    fn compute(ast: &syn::File, func_file: &str, options: &Options) -> Vec<Self> {
+        loc_functions(ast, func_file, options)
+    }
+
+    fn summary(functions: Stats<Self>, report: &ReportOptions) {
+        LocReport{functions}.summary(report)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Changed(T, T),  // before, after
+}
+
+
+impl<T: Watched> fmt::Display for Change<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added(after) => write!(f, "+ {}", after),
+            Change::Removed(before) => write!(f, "- {}", before),
+            Change::Changed(before, after) => write!(f, "~ {} (was {})", after, before),
+        }
+    }
+}
+
+
+/// The functions added, removed or whose metrics changed between two runs
+/// on a file. Functions of the same name are matched in order.
+pub fn diff_functions<T: Watched>(before: &[T], after: &[T]) -> Vec<Change<T>> {
+    let mut unmatched: Vec<&T> = before.iter().collect();
+    let mut changes = vec![];
+    for func in after {
+        match unmatched.iter().position(|old| old.key() == func.key()) {
+            Some(idx) => {
+                let old = unmatched.remove(idx);
+                if old.to_string() != func.to_string() {
+                    changes.push(Change::Changed(old.clone(), func.clone()));
+                }
+            }
+            None => changes.push(Change::Added(func.clone())),
+        }
+    }
+    changes.extend(unmatched.into_iter().map(|old| Change::Removed(old.clone())));
+    changes
+}
+
+
+/// The absolute path of a file, as the watcher reports it. A removed file
+/// has only its directory canonicalized.
+fn canonical(rs_fpath: &Path) -> PathBuf {
+    if let Ok(path) = rs_fpath.canonicalize() {
+        return path
+    }
+    let parent = match rs_fpath.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), rs_fpath.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => rs_fpath.to_owned(),
+    }
+}
+
+
+/// The functions of a tree, per file, re-computed one file at a time.
+pub struct IncrementalStats<T: Watched> {
+    options: Options,
+    files: BTreeMap<PathBuf, Vec<T>>,
+    invalid: BTreeMap<PathBuf, String>,  // the files which do not parse, with the error
+}
+
+
+impl<T: Watched> IncrementalStats<T> {
+
+    pub fn new(options: Options) -> Self {
+        IncrementalStats{options, files: BTreeMap::new(), invalid: BTreeMap::new()}
+    }
+
+    pub fn load(&mut self, path_str: &str) {
+        for rs_fpath in iter_rs_fpath(path_str) {
+            self.update(&rs_fpath);
+        }
+    }
+
+    /// Re-computes the functions of a file which was saved, created or
+    /// removed. A file which does not parse keeps the functions of its last
+    /// valid version.
+    pub fn update(&mut self, rs_fpath: &Path) -> Vec<Change<T>> {
+        let rs_fpath = canonical(rs_fpath);
+        let rs_fpath = rs_fpath.as_path();
+        let before = self.files.get(rs_fpath).cloned().unwrap_or_default();
+        if !rs_fpath.is_file() {
+            self.files.remove(rs_fpath);
+            self.invalid.remove(rs_fpath);
+            return diff_functions(&before, &[])
+        }
+        let parsed = fs::read_to_string(rs_fpath)
+            .map_err(|e| e.to_string())
+            .and_then(|content| parse_rs_file(&content, &self.options).map_err(|e| {
+                format!("{}:{}: {}", e.span().start().line, e.span().start().column, e)
+            }));
+        match parsed {
+            Ok(ast) => {
+                let after = T::compute(&ast, rs_fpath.to_str().unwrap(), &self.options);
+                let changes = diff_functions(&before, &after);
+                self.files.insert(rs_fpath.to_owned(), after);
+                self.invalid.remove(rs_fpath);
+                changes
+            }
+            Err(e) => {
+                self.invalid.insert(rs_fpath.to_owned(), e);
+                vec![]
+            }
+        }
+    }
+
+    pub fn invalid(&self) -> &BTreeMap<PathBuf, String> {
+        &self.invalid
+    }
+
+    pub fn stats(&self) -> Stats<T> {
+        let mut stats = Stats::new();
+        for functions in self.files.values() {
+            stats.extend(functions.clone());
+        }
+        stats
+    }
+
+    fn summary(&self, changes: &[Change<T>], report: &ReportOptions) {
+        T::summary(self.stats(), report);
+        if !changes.is_empty() {
+            println!("CHANGED SINCE LAST RUN:");
+            for change in changes {
+                println!("\t{}", change);
+            }
+        }
+        if !self.invalid.is_empty() {
+            println!("INVALID FILES, LAST VALID VERSION COUNTED (FILE, ERROR):");
+            for (rs_fpath, e) in &self.invalid {
+                println!("\t{}, {}", rs_fpath.display(), e);
+            }
+        }
+    }
+}
+
+
+fn is_rs_file(path: &Path) -> bool {
+    path.extension().map(|s| s == "rs").unwrap_or(false)
+}
+
+
+/// Prints the summary of the tree, then again each time `.rs` files are
+/// saved, once the saves have stopped for `debounce`.
+pub fn process_watch<T: Watched>(path_str: &str, options: &Options, report: &ReportOptions, debounce: Duration) {
+    let mut stats: IncrementalStats<T> = IncrementalStats::new(options.clone());
+    stats.load(path_str);
+    stats.summary(&[], report);
+
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender).unwrap();
+    watcher.watch(Path::new(path_str), RecursiveMode::Recursive).unwrap();
+
+    // blocks for the first event of a burst, then takes the others until
+    // the tree is quiet
+    while let Ok(event) = receiver.recv() {
+        let mut events = vec![event];
+        loop {
+            match receiver.recv_timeout(debounce) {
+                Ok(event) => events.push(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        let mut paths: Vec<PathBuf> = events.into_iter()
+            .filter_map(Result::ok)
+            .filter(|event| matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)))
+            .flat_map(|event| event.paths)
+            .filter(|path| is_rs_file(path))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        if paths.is_empty() {
+            continue;
+        }
+
+        let changes: Vec<Change<T>> = paths.iter().flat_map(|path| stats.update(path)).collect();
+        println!();
+        stats.summary(&changes, report);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::tools::{CCFunction, Options};
+    use super::{Change, IncrementalStats};
+
+    #[test]
+    fn test_incremental_stats() {
+        let dir = std::env::temp_dir().join(format!("happ-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rs_fpath = dir.join("lib.rs");
+        fs::write(&rs_fpath, "fn one() {}\nfn two(x: bool) { if x {} }\n").unwrap();
+
+        let mut stats: IncrementalStats<CCFunction> = IncrementalStats::new(Options::default());
+        stats.load(dir.to_str().unwrap());
+        assert_eq!(stats.stats().sum(), 3.0);
+
+        fs::write(&rs_fpath, "fn two(x: bool) { if x {} else if !x {} }\nfn three() {}\n").unwrap();
+        let changes = stats.update(&rs_fpath);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(&changes[0], Change::Changed(before, after) if before.cc == 2 && after.cc == 3));
+        assert!(matches!(&changes[1], Change::Added(after) if after.func_name == "three"));
+        assert!(matches!(&changes[2], Change::Removed(before) if before.func_name == "one"));
+
+        // half-typed code keeps the last valid results
+        fs::write(&rs_fpath, "fn two(x: bool) { if x {").unwrap();
+        assert!(stats.update(&rs_fpath).is_empty());
+        assert_eq!(stats.invalid().len(), 1);
+        assert_eq!(stats.stats().sum(), 4.0);
+
+        fs::remove_file(&rs_fpath).unwrap();
+        assert_eq!(stats.update(&rs_fpath).len(), 2);
+        assert!(stats.stats().is_empty());
+        assert!(stats.invalid().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_relative_path() {
+        // loaded through a relative path, updated through the watcher's absolute one
+        let dir = PathBuf::from(format!("target/happ-watch-relative-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("lib.rs"), "fn one() {}\n").unwrap();
+
+        let mut stats: IncrementalStats<CCFunction> = IncrementalStats::new(Options::default());
+        stats.load(dir.to_str().unwrap());
+        let rs_fpath = dir.canonicalize().unwrap().join("lib.rs");
+        fs::write(&rs_fpath, "fn one(x: bool) { if x {} }\n").unwrap();
+        let changes = stats.update(&rs_fpath);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], Change::Changed(before, after) if before.cc == 1 && after.cc == 2));
+        assert_eq!(stats.stats().len(), 1);
+
+        fs::remove_file(&rs_fpath).unwrap();
+        assert!(matches!(stats.update(&rs_fpath).as_slice(), [Change::Removed(_)]));
+        assert!(stats.stats().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
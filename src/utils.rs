@@ -1,6 +1,11 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use walkdir;
 
+use crate::cargo::CargoMetadata;
+use crate::options::Options;
+
 
 pub fn iter_rs_fpath(path_str: &str) -> Vec<PathBuf> {
     let input_path: PathBuf = PathBuf::from(path_str);
@@ -18,4 +23,140 @@ pub fn iter_rs_fpath(path_str: &str) -> Vec<PathBuf> {
             .collect();
         rs_fpath_vec
     }
-}
\ No newline at end of file
+}
+
+/// Name of the crate a source file belongs to, read from the nearest
+/// `Cargo.toml` above it. Falls back to the directory name of the manifest,
+/// or `"<unknown>"` if no manifest is found.
+pub fn find_crate_name(fpath: &Path) -> String {
+    for dir in fpath.ancestors().skip(1) {
+        let manifest = dir.join("Cargo.toml");
+        if !manifest.is_file() {
+            continue;
+        }
+        let content = fs::read_to_string(&manifest).unwrap_or_default();
+        let mut in_package = false;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_package = line == "[package]";
+            } else if in_package && line.starts_with("name") {
+                if let Some(name) = line.split('=').nth(1) {
+                    return name.trim().trim_matches('"').to_owned();
+                }
+            }
+        }
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        return dir.canonicalize()
+            .ok()
+            .and_then(|d| d.file_name().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "<unknown>".to_owned());
+    }
+    "<unknown>".to_owned()
+}
+
+
+/// The `[features]` table of the package of the nearest `Cargo.toml` above
+/// a source file, feature name to the features and dependencies it enables,
+/// as `cargo metadata` reads it. Empty if cargo cannot read the manifest.
+pub fn find_features(fpath: &Path) -> BTreeMap<String, Vec<String>> {
+    let manifest = match fpath.ancestors().map(|dir| dir.join("Cargo.toml")).find(|m| m.is_file()) {
+        Some(manifest) => fs::canonicalize(&manifest).unwrap_or(manifest),
+        None => return BTreeMap::new(),
+    };
+    match CargoMetadata::from_manifest(Some(&manifest)) {
+        Ok(metadata) => metadata.packages.into_iter()
+            .find(|package| package.manifest_path == manifest)
+            .map(|package| package.features)
+            .unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Skip the features of {}: {}", manifest.display(), e);
+            BTreeMap::new()
+        }
+    }
+}
+
+
+/// Parses a source file, pruning the items configured out by the `cfg` of
+/// the options, if any.
+pub fn parse_rs_file(content: &str, options: &Options) -> syn::Result<syn::File> {
+    let mut ast = syn::parse_file(content)?;
+    if let Some(config) = &options.cfg {
+        config.prune_file(&mut ast);
+    }
+    Ok(ast)
+}
+
+
+/// Parses the `.rs` files under each of the paths, given with their path
+/// and content. The files which cannot be read or do not parse are skipped.
+pub fn parse_rs_sources<P: AsRef<Path>>(paths: &[P], options: &Options) -> Vec<(String, String, syn::File)> {
+    paths.iter()
+        .flat_map(|path| iter_rs_fpath(path.as_ref().to_str().unwrap()))
+        .filter_map(|rs_fpath| {
+            let content = fs::read_to_string(&rs_fpath).ok()?;
+            let ast = parse_rs_file(&content, options).ok()?;
+            Some((rs_fpath.to_string_lossy().into_owned(), content, ast))
+        })
+        .collect()
+}
+
+
+/// Parses the `.rs` files under each of the paths, given with their path.
+/// The files which cannot be read or do not parse are skipped.
+pub fn parse_rs_files<P: AsRef<Path>>(paths: &[P], options: &Options) -> Vec<(String, syn::File)> {
+    parse_rs_sources(paths, options).into_iter()
+        .map(|(rs_fpath, _, ast)| (rs_fpath, ast))
+        .collect()
+}
+
+
+/// Whether the attributes mark a test function: `#[test]`, or a
+/// `#[tokio::test]`-style attribute from an async runtime.
+pub fn is_test_fn(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.segments.last().map(|s| s.ident == "test").unwrap_or(false)
+    })
+}
+
+
+fn is_test_cfg_predicate(meta: &syn::NestedMeta) -> bool {
+    match meta {
+        syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("test"),
+        syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("all") => {
+            list.nested.iter().any(is_test_cfg_predicate)
+        }
+        _ => false
+    }
+}
+
+
+/// Whether the attributes contain `#[cfg(test)]` (or `#[cfg(all(test, ..))]`),
+/// i.e. the item is only compiled for tests.
+pub fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .any(|meta| match meta {
+            syn::Meta::List(list) => list.nested.iter().any(is_test_cfg_predicate),
+            _ => false
+        })
+}
+
+
+/// A crate named `fixture` written under the temp directory for the test
+/// `test`, the `files` are relative to its root.
+#[cfg(test)]
+pub(crate) fn fixture_crate(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("happ-{}-{}", test, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    for (file, content) in files {
+        let fpath = dir.join(file);
+        fs::create_dir_all(fpath.parent().unwrap()).unwrap();
+        fs::write(fpath, content).unwrap();
+    }
+    fs::write(dir.join("Cargo.toml"), "[package]\nname = \"fixture\"\n").unwrap();
+    dir
+}